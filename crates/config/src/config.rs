@@ -1,8 +1,13 @@
 //! The config module provides the internal representation of Bulwark's configuration.
 
 use crate::{ConfigSerializationError, ResolutionError};
+use bulwark_decision::CombinationStrategy;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
 use regex::Regex;
 use serde::Serialize;
+use sha2::Sha256;
+use std::borrow::Cow;
 use validator::Validate;
 
 lazy_static! {
@@ -16,8 +21,14 @@ lazy_static! {
 pub struct Config {
     /// Configuration for the services being launched.
     pub service: Service,
+    /// Connection settings for the state store.
+    pub state: State,
     /// Configuration for the decision thresholds.
     pub thresholds: Thresholds,
+    /// The global redaction rules for request headers and cookies.
+    ///
+    /// These rules apply to logs and, combined with each plugin's own rules, to the requests plugins receive.
+    pub redaction: Redaction,
     /// A list of configurations for individual plugins.
     pub plugins: Vec<Plugin>,
     /// A list of plugin groups that allows a plugin set to be loaded with a single reference.
@@ -66,21 +77,165 @@ pub struct Service {
     pub admin_port: u16,
     /// True if the admin service is enabled, false otherwise.
     pub admin_enabled: bool,
-    /// The URI for the external Redis state store.
+    /// The URI for the state store shared by plugins.
+    ///
+    /// The scheme selects the backend. `redis://` and `rediss://` URIs use an external Redis server, which shares
+    /// state between Bulwark nodes. `sled://` URIs, like `sled:///var/lib/bulwark/state`, keep state in an embedded
+    /// database in the given directory, which survives restarts but isn't shared. `memory://` keeps state in
+    /// process, which is only suitable for single-node deployments and testing. If unset, state is kept in memory.
     pub remote_state: Option<String>,
     /// The number of trusted proxy hops expected to be exterior to Bulwark.
     ///
     /// This number does not include Bulwark or the proxy hosting it in the proxy hop count. Zero implies that
     /// there are no other proxies exterior to Bulwark. This is used to ensure the `Forwarded` and `X-Forwarded-For`
     /// headers are not spoofed. If this is set incorrectly, the client IP reported to plugins will be incorrect.
+    ///
+    /// Ignored if [`trusted_proxies`](Service::trusted_proxies) is set.
     pub proxy_hops: u8,
-    // TODO: it may be useful to introduce an "auto" setting for `proxy_hops` since it's possible to auto-discover
+    /// The IP ranges of proxies exterior to Bulwark that are trusted to append to the `Forwarded` and
+    /// `X-Forwarded-For` headers.
+    ///
+    /// If set, the headers are walked from the right, skipping trusted addresses, and the first untrusted address
+    /// is taken to be the client IP. If every address is trusted, the leftmost address, the one furthest from the
+    /// proxy hosting Bulwark, is used instead. Unlike [`proxy_hops`](Service::proxy_hops), this handles a variable number of hops.
+    pub trusted_proxies: Vec<IpNet>,
+    /// The name of the request header that carries an identifier used to correlate a request across log events,
+    /// plugins, and the interior service.
+    ///
+    /// If an incoming request doesn't have this header, an identifier is generated. Either way, the identifier is
+    /// sent to the interior service in this header.
+    pub request_id_header: String,
+    /// The maximum number of requests that may be processed concurrently.
+    ///
+    /// Requests beyond this limit are handled according to the [`load_shedding`](Service::load_shedding) policy.
+    /// If unset, there is no limit.
+    pub max_concurrent_requests: Option<usize>,
+    /// The maximum number of plugin instances, both enforcing and shadow, that may exist concurrently.
+    ///
+    /// Each request needs an instance of every plugin on its resource, so this should be at least the largest
+    /// number of plugins on any one resource. Requests whose enforcing plugins would exceed this limit are handled
+    /// according to the [`load_shedding`](Service::load_shedding) policy, while shadow plugins that would exceed it
+    /// are skipped. Shadow plugins hold their instances until their feedback phase finishes. If unset, there is no
+    /// limit.
+    pub max_concurrent_plugins: Option<usize>,
+    /// The policy applied to requests that would exceed a concurrency limit.
+    pub load_shedding: LoadSheddingPolicy,
+    /// The maximum amount of time in milliseconds an outbound HTTP request made by a plugin may take.
+    ///
+    /// Outbound requests are also cut short if the plugin's execution phase would otherwise time out first.
+    pub outbound_timeout: u64,
+    /// The largest response body, in bytes, that a plugin may receive from an outbound HTTP request.
+    ///
+    /// Larger responses cause the outbound request to fail rather than being truncated.
+    pub max_outbound_response_size: usize,
+    /// The directory that compiled plugins are cached in.
+    ///
+    /// Compiling a plugin is by far the slowest part of starting Bulwark. With a cache, a plugin is only compiled
+    /// the first time it's loaded. Cached plugins are trusted without validation, so this directory must only be
+    /// writable by Bulwark. If unset, every plugin is compiled each time Bulwark starts.
+    pub module_cache: Option<String>,
+}
+
+/// The policy applied to requests that arrive while Bulwark is at one of its concurrency limits.
+///
+/// None of the plugins are executed for a shed request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSheddingPolicy {
+    /// Fail open: the request is allowed with an unknown decision and a `load-shed` tag.
+    Open,
+    /// Fail closed: the request is blocked.
+    Closed,
+    /// The request is rejected with a gRPC `RESOURCE_EXHAUSTED` status, leaving the outcome to the proxy's own
+    /// failure mode.
+    Reject,
+}
+
+/// The default [`LoadSheddingPolicy`] value.
+pub const DEFAULT_LOAD_SHEDDING_POLICY: LoadSheddingPolicy = LoadSheddingPolicy::Open;
+
+impl Default for LoadSheddingPolicy {
+    /// The default load shedding policy fails open.
+    fn default() -> Self {
+        DEFAULT_LOAD_SHEDDING_POLICY
+    }
 }
 
 /// The default [`Service::port`] value.
 pub const DEFAULT_PORT: u16 = 8089;
 /// The default [`Service::admin_port`] value.
 pub const DEFAULT_ADMIN_PORT: u16 = 8090;
+/// The default [`Service::request_id_header`] value.
+pub const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+/// The default [`Service::outbound_timeout`] value.
+pub const DEFAULT_OUTBOUND_TIMEOUT: u64 = 1000;
+/// The default [`Service::max_outbound_response_size`] value.
+pub const DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE: usize = 1024 * 1024;
+
+/// Connection settings for the state store selected by [`Service::remote_state`].
+///
+/// These settings only apply to Redis state stores. Standalone servers and Redis Cluster are supported, but Redis
+/// Sentinel is not. TLS is enabled with a `rediss://` URI.
+///
+/// Plugins wait on state operations within their own timeout, so neither time limit may exceed the shortest plugin
+/// timeout. The default time limits are only used for standalone servers without TLS. Connecting to a Redis Cluster or
+/// over TLS takes several more round trips, so both time limits must be set explicitly for those, typically to tens of
+/// milliseconds, along with plugin timeouts long enough to allow for them.
+#[derive(Clone, PartialEq, Eq)]
+pub struct State {
+    /// The largest number of connections kept open to the state store.
+    pub pool_size: u32,
+    /// The time limit for opening a connection, or waiting for one from the pool, in milliseconds.
+    pub connect_timeout: u64,
+    /// The time limit for each command sent to the state store, in milliseconds.
+    pub command_timeout: u64,
+    /// The username to authenticate with, overriding any username in the URI.
+    pub username: Option<String>,
+    /// The password to authenticate with, overriding any password in the URI.
+    pub password: Option<String>,
+    /// Additional seed nodes of a Redis Cluster.
+    ///
+    /// If any are given, the state store is treated as a cluster and the [`Service::remote_state`] URI is used as
+    /// its first seed node.
+    pub cluster_nodes: Vec<String>,
+}
+
+/// The default [`State::pool_size`] value.
+pub const DEFAULT_STATE_POOL_SIZE: u32 = 16;
+/// The default [`State::connect_timeout`] value for standalone servers without TLS.
+///
+/// Kept below [`DEFAULT_PLUGIN_TIMEOUT`].
+pub const DEFAULT_STATE_CONNECT_TIMEOUT: u64 = 5;
+/// The default [`State::command_timeout`] value for standalone servers without TLS.
+///
+/// Kept below [`DEFAULT_PLUGIN_TIMEOUT`].
+pub const DEFAULT_STATE_COMMAND_TIMEOUT: u64 = 3;
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            pool_size: DEFAULT_STATE_POOL_SIZE,
+            connect_timeout: DEFAULT_STATE_CONNECT_TIMEOUT,
+            command_timeout: DEFAULT_STATE_COMMAND_TIMEOUT,
+            username: None,
+            password: None,
+            cluster_nodes: vec![],
+        }
+    }
+}
+
+impl std::fmt::Debug for State {
+    /// The password is masked so that it never appears in logs.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("State")
+            .field("pool_size", &self.pool_size)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("command_timeout", &self.command_timeout)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| REDACTION_MASK))
+            .field("cluster_nodes", &self.cluster_nodes)
+            .finish()
+    }
+}
 
 /// Configuration for the decision thresholds.
 ///
@@ -99,6 +254,15 @@ pub struct Thresholds {
     /// Any decision value below the `trust` threshold will cause the corresponding request to be flagged as trusted.
     /// This primarily affects plugins which use feedback loops.
     pub trust: f64,
+    /// The default rule used to combine plugin decisions, unless a resource selects its own.
+    ///
+    /// See [`Resource::combination`].
+    pub combination: CombinationStrategy,
+    /// True if the request phase and response phase decisions should be combined by default, unless a resource
+    /// selects its own mode.
+    ///
+    /// See [`Resource::combine_phases`].
+    pub combine_phases: bool,
 }
 
 /// The default [`Thresholds::observe_only`] value.
@@ -109,6 +273,8 @@ pub const DEFAULT_RESTRICT_THRESHOLD: f64 = 0.8;
 pub const DEFAULT_SUSPICIOUS_THRESHOLD: f64 = 0.6;
 /// The default [`Thresholds::trust`] value.
 pub const DEFAULT_TRUST_THRESHOLD: f64 = 0.2;
+/// The default [`Thresholds::combine_phases`] value.
+pub const DEFAULT_COMBINE_PHASES: bool = false;
 
 impl Default for Thresholds {
     /// Default decision thresholds.
@@ -118,6 +284,8 @@ impl Default for Thresholds {
             restrict: DEFAULT_RESTRICT_THRESHOLD,
             suspicious: DEFAULT_SUSPICIOUS_THRESHOLD,
             trust: DEFAULT_TRUST_THRESHOLD,
+            combination: CombinationStrategy::default(),
+            combine_phases: DEFAULT_COMBINE_PHASES,
         }
     }
 }
@@ -149,10 +317,86 @@ pub struct Plugin {
     ///
     /// Any attempt to perform an operation within the plugin sandbox that requires a permission to be set will fail.
     pub permissions: Permissions,
+    /// The policy applied when this plugin fails to produce a decision.
+    ///
+    /// If unset, the [`Resource::failure_policy`] of the resource being processed applies.
+    pub failure_policy: Option<FailurePolicy>,
+    /// The number of instance slots this plugin contributes to the pooling instance allocator.
+    ///
+    /// Pooling moves most of the cost of allocating an instance for each request to the time the plugin is loaded.
+    /// Every plugin shares one pool, so once any plugin sets a pool size, all plugins are allocated from the pool.
+    /// The pool holds a slot for every plugin instance that may be in use at once, which is the
+    /// [`Service::max_concurrent_plugins`] limit if one is set and otherwise the sum of the pool sizes of the
+    /// plugins in use, plus every plugin's [`prestart`](Plugin::prestart) instances. Requests that can't get a slot
+    /// are handled according to the [`Service::load_shedding`] policy. If no plugin sets a pool size, instances are
+    /// allocated on demand.
+    pub pool_size: Option<u32>,
+    /// The number of instances of this plugin to create and initialize ahead of the requests that will use them.
+    ///
+    /// Prestarted instances have already run their `on-init` function, so a request only has to hand them its
+    /// details. Each instance is still used by a single request and then discarded, so no state leaks between
+    /// requests. Instances are replenished in the background as requests take them. If unset, or if every
+    /// prestarted instance is in use, instances are created and initialized as part of the request that uses them.
+    pub prestart: Option<u32>,
+    /// The maximum amount of time in milliseconds this plugin may take for each execution phase.
+    ///
+    /// If unset, the [`Resource::timeout`] of the resource being processed applies. Either way, the plugin is still
+    /// bound by the resource's [`deadline`](Resource::deadline).
+    pub timeout: Option<u64>,
+    /// The largest size in bytes that the plugin's linear memory may grow to.
+    ///
+    /// If unset, [`DEFAULT_PLUGIN_MAX_MEMORY`] applies.
+    pub max_memory: Option<usize>,
+    /// The largest number of elements that any of the plugin's tables may grow to.
+    ///
+    /// If unset, [`DEFAULT_PLUGIN_MAX_TABLE_ELEMENTS`] applies.
+    pub max_table_elements: Option<u32>,
+    /// The redaction rules applied to requests before they are exposed to this plugin.
+    ///
+    /// This combines the global rules with any rules specific to the plugin. Headers and cookies the plugin has
+    /// been granted access to in its [`permissions`](Plugin::permissions) are left as-is.
+    pub redaction: Redaction,
 }
 
 /// The default [`Plugin::weight`] value.
 pub const DEFAULT_PLUGIN_WEIGHT: f64 = 1.0;
+/// The default amount of time in milliseconds a plugin may take for each execution phase.
+///
+/// Applies when neither the [`Plugin::timeout`] nor the [`Resource::timeout`] is set.
+pub const DEFAULT_PLUGIN_TIMEOUT: u64 = 10;
+/// The default largest size in bytes that a plugin's linear memory may grow to.
+///
+/// Applies when [`Plugin::max_memory`] is not set.
+pub const DEFAULT_PLUGIN_MAX_MEMORY: usize = 64 * 1024 * 1024;
+/// The default largest number of elements that a plugin's tables may grow to.
+///
+/// Applies when [`Plugin::max_table_elements`] is not set.
+pub const DEFAULT_PLUGIN_MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+/// The policy applied when a plugin fails to produce a decision, either due to an error or a timeout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailurePolicy {
+    /// Fail open: the failed plugin's decision is treated as entirely unknown.
+    Open,
+    /// Fail closed: the failed plugin's decision is treated as a restrict decision with the given mass.
+    ///
+    /// Any remaining mass is assigned to `unknown`.
+    Closed(f64),
+    /// The request is blocked outright, regardless of the decisions made by the other plugins.
+    Block,
+}
+
+/// The default [`FailurePolicy`] value.
+pub const DEFAULT_FAILURE_POLICY: FailurePolicy = FailurePolicy::Open;
+/// The default restrict mass for a [`FailurePolicy::Closed`] policy.
+pub const DEFAULT_FAILURE_RESTRICT: f64 = 1.0;
+
+impl Default for FailurePolicy {
+    /// The default failure policy fails open.
+    fn default() -> Self {
+        DEFAULT_FAILURE_POLICY
+    }
+}
 
 impl Plugin {
     /// Serializes the [`config`](Plugin::config) value to JSON bytes.
@@ -177,6 +421,169 @@ pub struct Permissions {
     ///
     /// This permission also affects rate limits and circuit breakers since they also use the external state store.
     pub state: Vec<String>,
+    /// A list of request header names that a plugin may read even if they would otherwise be redacted.
+    ///
+    /// The permission value must case-insensitively match the entire header name.
+    pub headers: Vec<String>,
+    /// A list of cookie names that a plugin may read even if they would otherwise be redacted.
+    ///
+    /// The permission value must case-sensitively match the entire cookie name.
+    pub cookies: Vec<String>,
+    /// A list of request header names that a plugin may add, replace, or remove before the request is forwarded to
+    /// the interior service.
+    ///
+    /// The permission value must case-insensitively match the entire header name.
+    pub upstream_headers: Vec<String>,
+}
+
+/// Rules for hiding sensitive request headers and cookies from plugins and logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Redaction {
+    /// A list of request header names whose values will be redacted. Case-insensitive.
+    pub headers: Vec<String>,
+    /// A list of cookie names whose values will be redacted within the `Cookie` header. Case-sensitive.
+    pub cookies: Vec<String>,
+    /// How redacted values are replaced.
+    pub mode: RedactionMode,
+    /// The secret key used by [`RedactionMode::Hash`].
+    pub secret: Option<String>,
+}
+
+/// The default [`Redaction::headers`] value.
+pub const DEFAULT_REDACTED_HEADERS: [&str; 2] = ["authorization", "proxy-authorization"];
+/// The default [`Redaction::mode`] value.
+pub const DEFAULT_REDACTION_MODE: RedactionMode = RedactionMode::Mask;
+/// The replacement value used by [`RedactionMode::Mask`].
+pub const REDACTION_MASK: &str = "[redacted]";
+
+/// The way a redacted value is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionMode {
+    /// The value is replaced with a fixed [mask](REDACTION_MASK).
+    Mask,
+    /// The value is replaced with its HMAC-SHA256, keyed with the [redaction secret](Redaction::secret), allowing
+    /// equal values to be correlated without being exposed or guessed.
+    ///
+    /// Values are masked instead if there is no secret.
+    Hash,
+}
+
+impl Default for Redaction {
+    /// By default, only the credential-bearing `Authorization` and `Proxy-Authorization` headers are masked.
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_REDACTED_HEADERS
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            cookies: vec![],
+            mode: DEFAULT_REDACTION_MODE,
+            secret: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Redaction {
+    /// The secret is masked so that it never appears in logs.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Redaction")
+            .field("headers", &self.headers)
+            .field("cookies", &self.cookies)
+            .field("mode", &self.mode)
+            .field("secret", &self.secret.as_ref().map(|_| REDACTION_MASK))
+            .finish()
+    }
+}
+
+impl Redaction {
+    /// Returns a copy of the rules without the headers and cookies that have been explicitly permitted.
+    ///
+    /// # Arguments
+    ///
+    /// * `permissions` - The permissions granted to a plugin.
+    pub fn without_permitted(&self, permissions: &Permissions) -> Self {
+        Self {
+            headers: self
+                .headers
+                .iter()
+                .filter(|header| {
+                    !permissions
+                        .headers
+                        .iter()
+                        .any(|permitted| permitted.eq_ignore_ascii_case(header))
+                })
+                .cloned()
+                .collect(),
+            cookies: self
+                .cookies
+                .iter()
+                .filter(|cookie| !permissions.cookies.contains(cookie))
+                .cloned()
+                .collect(),
+            mode: self.mode,
+            secret: self.secret.clone(),
+        }
+    }
+
+    /// Redacts a request header value according to these rules.
+    ///
+    /// Returns the value unchanged if neither the header nor any cookie it contains is covered by the rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn redact_header<'a>(&self, name: &str, value: &'a [u8]) -> Cow<'a, [u8]> {
+        if self
+            .headers
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case(name))
+        {
+            return Cow::Owned(self.redact_value(value).into_bytes());
+        }
+        if name.eq_ignore_ascii_case("cookie") && !self.cookies.is_empty() {
+            if let Ok(cookies) = std::str::from_utf8(value) {
+                return Cow::Owned(self.redact_cookies(cookies).into_bytes());
+            }
+            // A cookie header that can't be parsed might contain anything, so it's redacted entirely.
+            return Cow::Owned(self.redact_value(value).into_bytes());
+        }
+        Cow::Borrowed(value)
+    }
+
+    /// Redacts the values of any covered cookies within a `Cookie` header value.
+    fn redact_cookies(&self, cookies: &str) -> String {
+        cookies
+            .split(';')
+            .map(|pair| {
+                let pair = pair.trim();
+                match pair.split_once('=') {
+                    Some((name, value)) if self.cookies.iter().any(|cookie| cookie == name) => {
+                        format!("{}={}", name, self.redact_value(value.as_bytes()))
+                    }
+                    _ => pair.to_string(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+
+    /// Replaces a single value according to the redaction mode.
+    fn redact_value(&self, value: &[u8]) -> String {
+        match self.mode {
+            RedactionMode::Mask => REDACTION_MASK.to_string(),
+            RedactionMode::Hash => self
+                .secret
+                .as_ref()
+                .and_then(|secret| Hmac::<Sha256>::new_from_slice(secret.as_bytes()).ok())
+                .map(|mut mac| {
+                    mac.update(value);
+                    format!("hmac-sha256:{:x}", mac.finalize().into_bytes())
+                })
+                // An unkeyed digest of a low-entropy value can be reversed by guessing, so it's masked instead.
+                .unwrap_or_else(|| REDACTION_MASK.to_string()),
+        }
+    }
 }
 
 /// A mapping between a reference identifier and a list of plugins that form a preset plugin group.
@@ -230,8 +637,33 @@ pub struct Resource {
     pub route: String,
     /// The plugin references for this route.
     pub plugins: Vec<Reference>,
-    /// The maximum amount of time a plugin may take for each execution phase.
+    /// The shadow plugin references for this route.
+    ///
+    /// Shadow plugins execute in the same phases as the enforcing plugins but their combined decision is only
+    /// logged. It never affects the response and is never visible to the enforcing plugins.
+    pub shadow_plugins: Vec<Reference>,
+    /// The maximum amount of time in milliseconds a plugin may take for each execution phase.
+    ///
+    /// Individual plugins may override this with their own [`Plugin::timeout`]. If neither is set,
+    /// [`DEFAULT_PLUGIN_TIMEOUT`] applies.
     pub timeout: Option<u64>,
+    /// The maximum amount of time in milliseconds that plugins may take across every phase of a request, starting
+    /// from when the request is routed.
+    ///
+    /// Time spent waiting on the interior service's response isn't counted. Each plugin's timeout is shortened as
+    /// needed so that it can't run past the deadline. If unset, only the per-phase timeouts apply.
+    pub deadline: Option<u64>,
+    /// The policy applied when a plugin fails to produce a decision, unless the plugin sets its own.
+    pub failure_policy: FailurePolicy,
+    /// The rule used to combine the decisions of this resource's plugins.
+    ///
+    /// Defaults to [`Thresholds::combination`] if the resource doesn't select one.
+    pub combination: CombinationStrategy,
+    /// True if the response phase decision should be combined with the request phase decision rather than
+    /// replacing it.
+    ///
+    /// Defaults to [`Thresholds::combine_phases`] if the resource doesn't select a mode.
+    pub combine_phases: bool,
 }
 
 impl Resource {
@@ -247,27 +679,50 @@ impl Resource {
         &'a self,
         config: &'a Config,
     ) -> Result<Vec<&Plugin>, ResolutionError> {
-        let mut plugins: Vec<&Plugin> = Vec::with_capacity(self.plugins.len());
-        for reference in &self.plugins {
-            match reference {
-                Reference::Plugin(ref_name) => {
-                    if let Some(plugin) = config.plugin(ref_name.as_str()) {
-                        plugins.push(plugin);
-                    }
-                }
-                Reference::Preset(ref_name) => {
-                    if let Some(preset) = config.preset(ref_name.as_str()) {
-                        let mut inner_plugins = preset.resolve_plugins(config);
-                        plugins.append(&mut inner_plugins);
-                    }
+        resolve_references(&self.plugins, config)
+    }
+
+    /// Resolves all shadow references within a `Resource`, producing a flattened list of the corresponding
+    /// [`Plugin`]s.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A [`Config`] reference to perform lookups againsts.
+    ///
+    /// See [`Resource::resolve_plugins`].
+    pub fn resolve_shadow_plugins<'a>(
+        &'a self,
+        config: &'a Config,
+    ) -> Result<Vec<&Plugin>, ResolutionError> {
+        resolve_references(&self.shadow_plugins, config)
+    }
+}
+
+/// Resolves a list of references, producing a flattened list of the corresponding [`Plugin`]s.
+fn resolve_references<'a>(
+    references: &'a [Reference],
+    config: &'a Config,
+) -> Result<Vec<&'a Plugin>, ResolutionError> {
+    let mut plugins: Vec<&Plugin> = Vec::with_capacity(references.len());
+    for reference in references {
+        match reference {
+            Reference::Plugin(ref_name) => {
+                if let Some(plugin) = config.plugin(ref_name.as_str()) {
+                    plugins.push(plugin);
                 }
-                Reference::Missing(ref_name) => {
-                    return Err(ResolutionError::Missing(ref_name.to_string()));
+            }
+            Reference::Preset(ref_name) => {
+                if let Some(preset) = config.preset(ref_name.as_str()) {
+                    let mut inner_plugins = preset.resolve_plugins(config);
+                    plugins.append(&mut inner_plugins);
                 }
             }
+            Reference::Missing(ref_name) => {
+                return Err(ResolutionError::Missing(ref_name.to_string()));
+            }
         }
-        Ok(plugins)
     }
+    Ok(plugins)
 }
 
 /// Wraps reference strings and differentiates what the reference points to.
@@ -280,3 +735,74 @@ pub enum Reference {
     /// A reference that could not be resolved.
     Missing(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_header() -> Result<(), Box<dyn std::error::Error>> {
+        let redaction = Redaction {
+            cookies: vec!["session".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            redaction.redact_header("Authorization", b"Bearer secret"),
+            REDACTION_MASK.as_bytes()
+        );
+        assert_eq!(
+            redaction.redact_header("User-Agent", b"curl/7.88.1"),
+            b"curl/7.88.1".as_slice()
+        );
+        assert_eq!(
+            redaction.redact_header("Cookie", b"theme=dark;session=secret"),
+            b"theme=dark; session=[redacted]".as_slice()
+        );
+
+        // Hashing without a secret falls back to masking.
+        let redaction = Redaction {
+            mode: RedactionMode::Hash,
+            ..redaction
+        };
+        assert_eq!(
+            redaction.redact_header("authorization", b"Bearer secret"),
+            REDACTION_MASK.as_bytes()
+        );
+
+        let redaction = Redaction {
+            secret: Some("key".to_string()),
+            ..redaction
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"key")?;
+        mac.update(b"Bearer secret");
+        let hashed = format!("hmac-sha256:{:x}", mac.finalize().into_bytes());
+        assert_eq!(
+            redaction.redact_header("authorization", b"Bearer secret"),
+            hashed.as_bytes()
+        );
+        // The digest depends on the secret.
+        let rekeyed = Redaction {
+            secret: Some("other".to_string()),
+            ..redaction.clone()
+        };
+        assert_ne!(
+            rekeyed.redact_header("authorization", b"Bearer secret"),
+            hashed.as_bytes()
+        );
+
+        let permitted = redaction.without_permitted(&Permissions {
+            headers: vec!["AUTHORIZATION".to_string()],
+            cookies: vec!["session".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(permitted.headers, vec!["proxy-authorization"]);
+        assert!(permitted.cookies.is_empty());
+        assert_eq!(
+            permitted.redact_header("Authorization", b"Bearer secret"),
+            b"Bearer secret".as_slice()
+        );
+
+        Ok(())
+    }
+}
@@ -9,6 +9,16 @@ pub enum ConfigFileError {
     Validation(#[from] validator::ValidationError),
     #[error(transparent)]
     Validations(#[from] validator::ValidationErrors),
+    #[error("invalid trusted proxy, must be an IP address or CIDR range: '{0}'")]
+    InvalidTrustedProxy(String),
+    #[error("failure_restrict is only valid with a closed failure_policy: '{0}'")]
+    FailureRestrictWithoutClosedPolicy(String),
+    #[error("hash redaction mode requires a redaction secret: '{0}'")]
+    MissingRedactionSecret(String),
+    #[error("state timeouts must not exceed the shortest plugin timeout: '{0}'")]
+    StateTimeoutExceedsPluginTimeout(u64),
+    #[error("state connect_timeout and command_timeout must be set for Redis Cluster and TLS connections")]
+    StateTimeoutRequired,
 }
 
 /// This error will be returned if an attempt to serialize a config structure fails.
@@ -4,6 +4,7 @@
 // directly in the [`bulwark_config`](crate) module's structs.
 
 use crate::ConfigFileError;
+use ipnet::IpNet;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{fs, path::Path};
@@ -19,7 +20,11 @@ struct Config {
     #[serde(default)]
     service: Service,
     #[serde(default)]
+    state: State,
+    #[serde(default)]
     thresholds: Thresholds,
+    #[serde(default)]
+    redaction: Redaction,
     #[serde(default, rename(serialize = "include", deserialize = "include"))]
     includes: Vec<Include>,
     #[serde(default, rename(serialize = "plugin", deserialize = "plugin"))]
@@ -31,7 +36,7 @@ struct Config {
 }
 
 /// The TOML serialization for a Service config structure.
-#[derive(Serialize, Deserialize)]
+#[derive(Validate, Serialize, Deserialize)]
 struct Service {
     #[serde(default = "default_port")]
     port: u16,
@@ -43,6 +48,25 @@ struct Service {
     remote_state: Option<String>,
     #[serde(default = "default_proxy_hops")]
     proxy_hops: u8,
+    #[serde(default)]
+    trusted_proxies: Vec<String>,
+    #[serde(default = "default_request_id_header")]
+    request_id_header: String,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_concurrent_requests: Option<usize>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_concurrent_plugins: Option<usize>,
+    #[serde(default)]
+    load_shedding: TomlLoadSheddingPolicy,
+    #[serde(default = "default_outbound_timeout")]
+    #[validate(range(min = 1))]
+    outbound_timeout: u64,
+    #[serde(default = "default_max_outbound_response_size")]
+    max_outbound_response_size: usize,
+    #[serde(default)]
+    module_cache: Option<String>,
 }
 
 /// The default port for the primary service.
@@ -74,6 +98,27 @@ fn default_proxy_hops() -> u8 {
     0
 }
 
+/// The default name of the request header carrying the request ID.
+///
+/// See [`DEFAULT_REQUEST_ID_HEADER`](crate::DEFAULT_REQUEST_ID_HEADER).
+fn default_request_id_header() -> String {
+    crate::DEFAULT_REQUEST_ID_HEADER.to_string()
+}
+
+/// The default time limit for outbound HTTP requests made by plugins.
+///
+/// See [`DEFAULT_OUTBOUND_TIMEOUT`](crate::DEFAULT_OUTBOUND_TIMEOUT).
+fn default_outbound_timeout() -> u64 {
+    crate::DEFAULT_OUTBOUND_TIMEOUT
+}
+
+/// The default size limit for responses to outbound HTTP requests made by plugins.
+///
+/// See [`DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE`](crate::DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE).
+fn default_max_outbound_response_size() -> usize {
+    crate::DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE
+}
+
 impl Default for Service {
     fn default() -> Self {
         Self {
@@ -82,22 +127,129 @@ impl Default for Service {
             admin_enabled: default_admin(),
             remote_state: default_remote_state(),
             proxy_hops: default_proxy_hops(),
+            trusted_proxies: vec![],
+            request_id_header: default_request_id_header(),
+            max_concurrent_requests: None,
+            max_concurrent_plugins: None,
+            load_shedding: TomlLoadSheddingPolicy::default(),
+            outbound_timeout: default_outbound_timeout(),
+            max_outbound_response_size: default_max_outbound_response_size(),
+            module_cache: None,
         }
     }
 }
 
-impl From<Service> for crate::Service {
-    fn from(service: Service) -> Self {
-        Self {
+impl TryFrom<Service> for crate::Service {
+    type Error = ConfigFileError;
+
+    fn try_from(service: Service) -> Result<Self, Self::Error> {
+        Ok(Self {
             port: service.port,
             admin_port: service.admin_port,
             admin_enabled: service.admin_enabled,
             remote_state: service.remote_state.clone(),
             proxy_hops: service.proxy_hops,
+            trusted_proxies: service
+                .trusted_proxies
+                .iter()
+                .map(|proxy| parse_trusted_proxy(proxy))
+                .collect::<Result<Vec<IpNet>, ConfigFileError>>()?,
+            request_id_header: service.request_id_header.to_ascii_lowercase(),
+            max_concurrent_requests: service.max_concurrent_requests,
+            max_concurrent_plugins: service.max_concurrent_plugins,
+            load_shedding: service.load_shedding.into(),
+            outbound_timeout: service.outbound_timeout,
+            max_outbound_response_size: service.max_outbound_response_size,
+            module_cache: service.module_cache,
+        })
+    }
+}
+
+/// The TOML serialization for a State config structure.
+#[derive(Validate, Serialize, Deserialize)]
+struct State {
+    #[serde(default = "default_state_pool_size")]
+    #[validate(range(min = 1))]
+    pool_size: u32,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    connect_timeout: Option<u64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    command_timeout: Option<u64>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    cluster_nodes: Vec<String>,
+}
+
+/// The default number of connections kept open to the state store.
+///
+/// See [`DEFAULT_STATE_POOL_SIZE`](crate::DEFAULT_STATE_POOL_SIZE).
+fn default_state_pool_size() -> u32 {
+    crate::DEFAULT_STATE_POOL_SIZE
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            pool_size: default_state_pool_size(),
+            connect_timeout: None,
+            command_timeout: None,
+            username: None,
+            password: None,
+            cluster_nodes: vec![],
         }
     }
 }
 
+impl From<State> for crate::State {
+    fn from(state: State) -> Self {
+        Self {
+            pool_size: state.pool_size,
+            connect_timeout: state
+                .connect_timeout
+                .unwrap_or(crate::DEFAULT_STATE_CONNECT_TIMEOUT),
+            command_timeout: state
+                .command_timeout
+                .unwrap_or(crate::DEFAULT_STATE_COMMAND_TIMEOUT),
+            username: state.username,
+            password: state.password,
+            cluster_nodes: state.cluster_nodes,
+        }
+    }
+}
+
+/// The TOML serialization for a LoadSheddingPolicy structure.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum TomlLoadSheddingPolicy {
+    #[default]
+    Open,
+    Closed,
+    Reject,
+}
+
+impl From<TomlLoadSheddingPolicy> for crate::LoadSheddingPolicy {
+    fn from(policy: TomlLoadSheddingPolicy) -> Self {
+        match policy {
+            TomlLoadSheddingPolicy::Open => Self::Open,
+            TomlLoadSheddingPolicy::Closed => Self::Closed,
+            TomlLoadSheddingPolicy::Reject => Self::Reject,
+        }
+    }
+}
+
+/// Parses a trusted proxy as either a CIDR range or a single IP address.
+fn parse_trusted_proxy(proxy: &str) -> Result<IpNet, ConfigFileError> {
+    proxy
+        .parse::<IpNet>()
+        .or_else(|_| proxy.parse::<std::net::IpAddr>().map(IpNet::from))
+        .map_err(|_| ConfigFileError::InvalidTrustedProxy(proxy.to_string()))
+}
+
 /// The TOML serialization for a Thresholds structure.
 #[derive(Serialize, Deserialize)]
 struct Thresholds {
@@ -109,6 +261,10 @@ struct Thresholds {
     suspicious: f64,
     #[serde(default = "default_trust_threshold")]
     trust: f64,
+    #[serde(default)]
+    combination: TomlCombinationStrategy,
+    #[serde(default = "default_combine_phases")]
+    combine_phases: bool,
 }
 
 /// The default for whether the primary service should take no action in response to restrict decisions.
@@ -131,6 +287,11 @@ fn default_trust_threshold() -> f64 {
     crate::DEFAULT_TRUST_THRESHOLD
 }
 
+/// The default for whether request phase and response phase decisions are combined.
+fn default_combine_phases() -> bool {
+    crate::DEFAULT_COMBINE_PHASES
+}
+
 impl Default for Thresholds {
     fn default() -> Self {
         Self {
@@ -138,6 +299,8 @@ impl Default for Thresholds {
             restrict: default_restrict_threshold(),
             suspicious: default_suspicious_threshold(),
             trust: default_trust_threshold(),
+            combination: TomlCombinationStrategy::default(),
+            combine_phases: default_combine_phases(),
         }
     }
 }
@@ -149,6 +312,130 @@ impl From<Thresholds> for crate::Thresholds {
             restrict: thresholds.restrict,
             suspicious: thresholds.suspicious,
             trust: thresholds.trust,
+            combination: thresholds.combination.into(),
+            combine_phases: thresholds.combine_phases,
+        }
+    }
+}
+
+/// The TOML serialization for a CombinationStrategy structure.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum TomlCombinationStrategy {
+    #[default]
+    Murphy,
+    Conjunctive,
+    Yager,
+}
+
+impl From<TomlCombinationStrategy> for bulwark_decision::CombinationStrategy {
+    fn from(strategy: TomlCombinationStrategy) -> Self {
+        match strategy {
+            TomlCombinationStrategy::Murphy => Self::Murphy,
+            TomlCombinationStrategy::Conjunctive => Self::Conjunctive,
+            TomlCombinationStrategy::Yager => Self::Yager,
+        }
+    }
+}
+
+/// The TOML serialization for a Redaction structure.
+#[derive(Serialize, Deserialize)]
+struct Redaction {
+    #[serde(default = "default_redacted_headers")]
+    headers: Vec<String>,
+    #[serde(default)]
+    cookies: Vec<String>,
+    #[serde(default)]
+    mode: TomlRedactionMode,
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+/// The default list of redacted request headers.
+///
+/// See [`DEFAULT_REDACTED_HEADERS`](crate::DEFAULT_REDACTED_HEADERS).
+fn default_redacted_headers() -> Vec<String> {
+    crate::DEFAULT_REDACTED_HEADERS
+        .iter()
+        .map(|header| header.to_string())
+        .collect()
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            headers: default_redacted_headers(),
+            cookies: vec![],
+            mode: TomlRedactionMode::default(),
+            secret: None,
+        }
+    }
+}
+
+impl From<Redaction> for crate::Redaction {
+    fn from(redaction: Redaction) -> Self {
+        Self {
+            headers: redaction
+                .headers
+                .iter()
+                .map(|header| header.to_ascii_lowercase())
+                .collect(),
+            cookies: redaction.cookies,
+            mode: redaction.mode.into(),
+            secret: redaction.secret,
+        }
+    }
+}
+
+/// The TOML serialization for a plugin's additional redaction rules.
+///
+/// Headers and cookies are added to the global rules. The mode, if set, replaces the global mode.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct PluginRedaction {
+    #[serde(default)]
+    headers: Vec<String>,
+    #[serde(default)]
+    cookies: Vec<String>,
+    #[serde(default)]
+    mode: Option<TomlRedactionMode>,
+}
+
+impl PluginRedaction {
+    /// Combines the plugin's rules with the global rules.
+    fn resolve(&self, global: &crate::Redaction) -> crate::Redaction {
+        let mut redaction = global.clone();
+        for header in &self.headers {
+            let header = header.to_ascii_lowercase();
+            if !redaction.headers.contains(&header) {
+                redaction.headers.push(header);
+            }
+        }
+        for cookie in &self.cookies {
+            if !redaction.cookies.contains(cookie) {
+                redaction.cookies.push(cookie.clone());
+            }
+        }
+        if let Some(mode) = self.mode {
+            redaction.mode = mode.into();
+        }
+        redaction
+    }
+}
+
+/// The TOML serialization for a RedactionMode structure.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum TomlRedactionMode {
+    #[default]
+    Mask,
+    Hash,
+}
+
+impl From<TomlRedactionMode> for crate::RedactionMode {
+    fn from(mode: TomlRedactionMode) -> Self {
+        match mode {
+            TomlRedactionMode::Mask => Self::Mask,
+            TomlRedactionMode::Hash => Self::Hash,
         }
     }
 }
@@ -174,6 +461,28 @@ struct Plugin {
     config: toml::map::Map<String, toml::Value>,
     #[serde(default)]
     permissions: TomlPermissions,
+    #[serde(default)]
+    failure_policy: Option<TomlFailurePolicy>,
+    #[serde(default)]
+    #[validate(range(min = 0.0, max = 1.0))]
+    failure_restrict: Option<f64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    pool_size: Option<u32>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    prestart: Option<u32>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    timeout: Option<u64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_memory: Option<usize>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_table_elements: Option<u32>,
+    #[serde(default)]
+    redaction: PluginRedaction,
 }
 
 /// The default weight for a plugin.
@@ -191,8 +500,105 @@ impl From<&Plugin> for crate::config::Plugin {
             weight: plugin.weight,
             config: toml_map_to_json(plugin.config.clone()),
             permissions: plugin.permissions.clone().into(),
+            failure_policy: plugin
+                .failure_policy
+                .map(|policy| policy.with_restrict(plugin.failure_restrict)),
+            pool_size: plugin.pool_size,
+            prestart: plugin.prestart,
+            timeout: plugin.timeout,
+            max_memory: plugin.max_memory,
+            max_table_elements: plugin.max_table_elements,
+            redaction: plugin.redaction.resolve(&crate::Redaction::default()),
+        }
+    }
+}
+
+/// The TOML serialization for a FailurePolicy structure.
+///
+/// The restrict mass for a closed policy is set by a separate `failure_restrict` key.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum TomlFailurePolicy {
+    Open,
+    Closed,
+    Block,
+}
+
+impl TomlFailurePolicy {
+    /// Converts to a [`FailurePolicy`](crate::FailurePolicy), using the default restrict mass if none is set.
+    fn with_restrict(self, restrict: Option<f64>) -> crate::FailurePolicy {
+        match self {
+            TomlFailurePolicy::Open => crate::FailurePolicy::Open,
+            TomlFailurePolicy::Closed => {
+                crate::FailurePolicy::Closed(restrict.unwrap_or(crate::DEFAULT_FAILURE_RESTRICT))
+            }
+            TomlFailurePolicy::Block => crate::FailurePolicy::Block,
+        }
+    }
+}
+
+/// Rejects the hash redaction mode if no secret is configured to key it with.
+///
+/// The `name` identifies the global redaction rules or the plugin in the error.
+fn check_redaction_secret(
+    name: &str,
+    mode: TomlRedactionMode,
+    secret: Option<&String>,
+) -> Result<(), ConfigFileError> {
+    match (mode, secret) {
+        (TomlRedactionMode::Hash, None) => {
+            Err(ConfigFileError::MissingRedactionSecret(name.to_string()))
         }
+        _ => Ok(()),
+    }
+}
+
+/// Rejects a `failure_restrict` value that doesn't accompany a closed failure policy, since it would be ignored.
+///
+/// The `name` identifies the plugin or resource in the error.
+fn check_failure_restrict(
+    name: &str,
+    policy: Option<TomlFailurePolicy>,
+    restrict: Option<f64>,
+) -> Result<(), ConfigFileError> {
+    match (policy, restrict) {
+        (_, None) | (Some(TomlFailurePolicy::Closed), Some(_)) => Ok(()),
+        _ => Err(ConfigFileError::FailureRestrictWithoutClosedPolicy(
+            name.to_string(),
+        )),
+    }
+}
+
+/// Rejects state time limits that exceed the shortest plugin timeout, since a plugin waiting on the state store
+/// would time out first.
+///
+/// Only Redis state stores use these time limits. The defaults suit a nearby standalone server, but connecting to a
+/// Redis Cluster or over TLS takes several more round trips, so both time limits must be set explicitly for those.
+fn check_state_timeouts(
+    remote_state: Option<&String>,
+    state: &State,
+    plugin_timeout: u64,
+) -> Result<(), ConfigFileError> {
+    let uri = match remote_state {
+        Some(uri) if uri.starts_with("redis") => uri,
+        _ => return Ok(()),
+    };
+    let (connect_timeout, command_timeout) = match (state.connect_timeout, state.command_timeout) {
+        (Some(connect_timeout), Some(command_timeout)) => (connect_timeout, command_timeout),
+        _ if uri.starts_with("rediss") || !state.cluster_nodes.is_empty() => {
+            return Err(ConfigFileError::StateTimeoutRequired);
+        }
+        (connect_timeout, command_timeout) => (
+            connect_timeout.unwrap_or(crate::DEFAULT_STATE_CONNECT_TIMEOUT),
+            command_timeout.unwrap_or(crate::DEFAULT_STATE_COMMAND_TIMEOUT),
+        ),
+    };
+    if connect_timeout.max(command_timeout) > plugin_timeout {
+        return Err(ConfigFileError::StateTimeoutExceedsPluginTimeout(
+            plugin_timeout,
+        ));
     }
+    Ok(())
 }
 
 fn toml_map_to_json(
@@ -247,6 +653,12 @@ struct TomlPermissions {
     http: Vec<String>,
     #[serde(default)]
     state: Vec<String>,
+    #[serde(default)]
+    upstream_headers: Vec<String>,
+    #[serde(default)]
+    headers: Vec<String>,
+    #[serde(default)]
+    cookies: Vec<String>,
 }
 
 impl From<TomlPermissions> for crate::config::Permissions {
@@ -255,6 +667,9 @@ impl From<TomlPermissions> for crate::config::Permissions {
             env: permissions.env,
             http: permissions.http,
             state: permissions.state,
+            upstream_headers: permissions.upstream_headers,
+            headers: permissions.headers,
+            cookies: permissions.cookies,
         }
     }
 }
@@ -270,12 +685,27 @@ struct Preset {
 }
 
 /// The TOML serialization for a Resource structure.
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Validate, Serialize, Deserialize, Clone)]
 struct Resource {
     route: String,
     plugins: Vec<String>,
-    // TODO: default timeout
+    #[serde(default)]
+    shadow_plugins: Vec<String>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
     timeout: Option<u64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    deadline: Option<u64>,
+    #[serde(default)]
+    failure_policy: Option<TomlFailurePolicy>,
+    #[serde(default)]
+    #[validate(range(min = 0.0, max = 1.0))]
+    failure_restrict: Option<f64>,
+    #[serde(default)]
+    combination: Option<TomlCombinationStrategy>,
+    #[serde(default)]
+    combine_phases: Option<bool>,
 }
 
 /// Loads a TOML config file into a [`Config`](crate::Config) structure.
@@ -328,12 +758,54 @@ where
 
     // Load the raw serialization format and resolve includes
     let root = load_config_recursive(path)?;
+    root.service.validate()?;
+    root.state.validate()?;
     for preset in &root.presets {
         preset.validate()?;
     }
+    check_redaction_secret(
+        "redaction",
+        root.redaction.mode,
+        root.redaction.secret.as_ref(),
+    )?;
     for plugin in &root.plugins {
         plugin.validate()?;
+        check_redaction_secret(
+            &plugin.reference,
+            plugin.redaction.mode.unwrap_or(root.redaction.mode),
+            root.redaction.secret.as_ref(),
+        )?;
+        check_failure_restrict(
+            &plugin.reference,
+            plugin.failure_policy,
+            plugin.failure_restrict,
+        )?;
+    }
+    for resource in &root.resources {
+        resource.validate()?;
+        check_failure_restrict(
+            &resource.route,
+            resource.failure_policy,
+            resource.failure_restrict,
+        )?;
     }
+    // Plugin timeouts override resource timeouts, which fall back to the default.
+    let plugin_timeout = root
+        .plugins
+        .iter()
+        .filter_map(|plugin| plugin.timeout)
+        .chain(
+            root.resources
+                .iter()
+                .map(|resource| resource.timeout.unwrap_or(crate::DEFAULT_PLUGIN_TIMEOUT)),
+        )
+        .min()
+        .unwrap_or(crate::DEFAULT_PLUGIN_TIMEOUT);
+    check_state_timeouts(
+        root.service.remote_state.as_ref(),
+        &root.state,
+        plugin_timeout,
+    )?;
     let resolve_reference = |ref_name: &String| {
         let mut reference = crate::config::Reference::Missing(ref_name.clone());
         for preset in &root.presets {
@@ -348,11 +820,22 @@ where
         }
         reference
     };
+    let thresholds: crate::Thresholds = root.thresholds.into();
+    let redaction: crate::Redaction = root.redaction.into();
     // Transfer to the public config type, checking reference enums
     Ok(crate::Config {
-        service: root.service.into(),
-        thresholds: root.thresholds.into(),
-        plugins: root.plugins.iter().map(|plugin| plugin.into()).collect(),
+        service: root.service.try_into()?,
+        state: root.state.into(),
+        thresholds,
+        plugins: root
+            .plugins
+            .iter()
+            .map(|plugin| crate::config::Plugin {
+                redaction: plugin.redaction.resolve(&redaction),
+                ..crate::config::Plugin::from(plugin)
+            })
+            .collect(),
+        redaction,
         presets: root
             .presets
             .iter()
@@ -367,7 +850,22 @@ where
             .map(|resource| crate::config::Resource {
                 route: resource.route.clone(),
                 plugins: resource.plugins.iter().map(resolve_reference).collect(),
+                shadow_plugins: resource
+                    .shadow_plugins
+                    .iter()
+                    .map(resolve_reference)
+                    .collect(),
                 timeout: resource.timeout,
+                deadline: resource.deadline,
+                failure_policy: resource
+                    .failure_policy
+                    .map(|policy| policy.with_restrict(resource.failure_restrict))
+                    .unwrap_or_default(),
+                combination: resource
+                    .combination
+                    .map(|strategy| strategy.into())
+                    .unwrap_or(thresholds.combination),
+                combine_phases: resource.combine_phases.unwrap_or(thresholds.combine_phases),
             })
             .collect(),
     })
@@ -383,6 +881,7 @@ mod tests {
             r#"
         [service]
         port = 10002
+        trusted_proxies = ["10.0.0.0/8", "192.0.2.43"]
 
         [thresholds]
         restrict = 0.75
@@ -393,6 +892,8 @@ mod tests {
         [[plugin]]
         ref = "evil_bit"
         path = "bulwark-evil-bit.wasm"
+        failure_policy = "closed"
+        failure_restrict = 0.5
 
         [[preset]]
         ref = "custom"
@@ -401,12 +902,36 @@ mod tests {
         [[resource]]
         route = "/"
         plugins = ["custom"]
+        shadow_plugins = ["evil_bit"]
         timeout = 25
+        combination = "conjunctive"
     "#,
         )?;
 
         assert_eq!(root.service.port, 10002); // non-default
         assert_eq!(root.service.admin_port, crate::DEFAULT_ADMIN_PORT);
+        assert_eq!(
+            root.service.trusted_proxies,
+            vec!["10.0.0.0/8", "192.0.2.43"]
+        );
+        assert_eq!(
+            root.service.request_id_header,
+            crate::DEFAULT_REQUEST_ID_HEADER
+        );
+        assert_eq!(root.service.max_concurrent_requests, None);
+        assert_eq!(
+            root.service.outbound_timeout,
+            crate::DEFAULT_OUTBOUND_TIMEOUT
+        );
+        assert_eq!(
+            root.service.max_outbound_response_size,
+            crate::DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE
+        );
+        assert_eq!(root.service.module_cache, None);
+        assert!(matches!(
+            root.service.load_shedding,
+            TomlLoadSheddingPolicy::Open
+        ));
 
         assert_eq!(root.thresholds.restrict, 0.75); // non-default
         assert_eq!(
@@ -414,6 +939,14 @@ mod tests {
             crate::DEFAULT_SUSPICIOUS_THRESHOLD
         );
         assert_eq!(root.thresholds.trust, crate::DEFAULT_TRUST_THRESHOLD);
+        assert!(matches!(
+            root.thresholds.combination,
+            TomlCombinationStrategy::Murphy
+        ));
+        assert_eq!(
+            root.thresholds.combine_phases,
+            crate::DEFAULT_COMBINE_PHASES
+        );
 
         assert_eq!(root.includes.len(), 1);
         assert_eq!(root.includes.get(0).unwrap().path, "default.toml");
@@ -425,6 +958,14 @@ mod tests {
             root.plugins.get(0).unwrap().config,
             toml::map::Map::default()
         );
+        assert_eq!(
+            root.plugins
+                .get(0)
+                .unwrap()
+                .failure_policy
+                .map(|policy| policy.with_restrict(root.plugins.get(0).unwrap().failure_restrict)),
+            Some(crate::FailurePolicy::Closed(0.5))
+        );
 
         assert_eq!(root.presets.len(), 1);
         assert_eq!(root.presets.get(0).unwrap().reference, "custom");
@@ -433,6 +974,15 @@ mod tests {
         assert_eq!(root.resources.len(), 1);
         assert_eq!(root.resources.get(0).unwrap().route, "/");
         assert_eq!(root.resources.get(0).unwrap().plugins, vec!["custom"]);
+        assert_eq!(
+            root.resources.get(0).unwrap().shadow_plugins,
+            vec!["evil_bit"]
+        );
+        assert!(matches!(
+            root.resources.get(0).unwrap().combination,
+            Some(TomlCombinationStrategy::Conjunctive)
+        ));
+        assert_eq!(root.resources.get(0).unwrap().combine_phases, None);
         assert_eq!(root.resources.get(0).unwrap().timeout, Some(25));
 
         Ok(())
@@ -444,6 +994,38 @@ mod tests {
 
         assert_eq!(root.service.port, 10002); // non-default
         assert_eq!(root.service.admin_port, crate::DEFAULT_ADMIN_PORT);
+        assert_eq!(
+            root.service.trusted_proxies,
+            vec![
+                "10.0.0.0/8".parse::<IpNet>()?,
+                "192.0.2.43/32".parse::<IpNet>()?,
+                "2001:db8::/32".parse::<IpNet>()?,
+            ]
+        );
+        assert_eq!(root.service.request_id_header, "x-correlation-id");
+        assert_eq!(root.service.max_concurrent_requests, Some(1024));
+        assert_eq!(root.service.max_concurrent_plugins, Some(4096));
+        assert_eq!(root.service.outbound_timeout, 250);
+        assert_eq!(root.service.max_outbound_response_size, 65536);
+        assert_eq!(
+            root.service.module_cache,
+            Some("/var/cache/bulwark".to_string())
+        );
+        assert_eq!(
+            root.service.load_shedding,
+            crate::LoadSheddingPolicy::Reject
+        );
+
+        assert_eq!(root.state.pool_size, 32);
+        assert_eq!(
+            root.state.connect_timeout,
+            crate::DEFAULT_STATE_CONNECT_TIMEOUT
+        );
+        assert_eq!(root.state.command_timeout, 8);
+        assert_eq!(root.state.username, Some("bulwark".to_string()));
+        assert_eq!(root.state.password, Some("hunter2".to_string()));
+        assert!(!format!("{:?}", root.state).contains("hunter2"));
+        assert!(root.state.cluster_nodes.is_empty());
 
         assert_eq!(root.thresholds.restrict, 0.75); // non-default
         assert_eq!(
@@ -451,6 +1033,11 @@ mod tests {
             crate::DEFAULT_SUSPICIOUS_THRESHOLD
         );
         assert_eq!(root.thresholds.trust, crate::DEFAULT_TRUST_THRESHOLD);
+        assert_eq!(
+            root.thresholds.combination,
+            bulwark_decision::CombinationStrategy::Yager
+        );
+        assert!(!root.thresholds.combine_phases);
 
         assert_eq!(root.plugins.len(), 2);
         assert_eq!(root.plugins.get(0).unwrap().reference, "evil_bit");
@@ -459,6 +1046,64 @@ mod tests {
             root.plugins.get(0).unwrap().config,
             serde_json::map::Map::default()
         );
+        assert_eq!(root.plugins.get(0).unwrap().failure_policy, None);
+        assert_eq!(root.plugins.get(0).unwrap().pool_size, None);
+        assert_eq!(root.plugins.get(1).unwrap().pool_size, Some(32));
+        assert_eq!(root.plugins.get(0).unwrap().prestart, None);
+        assert_eq!(root.plugins.get(1).unwrap().prestart, Some(4));
+        assert_eq!(root.plugins.get(0).unwrap().timeout, None);
+        assert_eq!(root.plugins.get(1).unwrap().timeout, Some(100));
+        assert_eq!(root.plugins.get(0).unwrap().max_memory, None);
+        assert_eq!(root.plugins.get(1).unwrap().max_memory, Some(16777216));
+        assert_eq!(root.plugins.get(1).unwrap().max_table_elements, Some(1000));
+        assert!(root
+            .plugins
+            .get(0)
+            .unwrap()
+            .permissions
+            .upstream_headers
+            .is_empty());
+        assert_eq!(
+            root.plugins.get(1).unwrap().permissions.upstream_headers,
+            vec!["X-Client-Risk"]
+        );
+        assert_eq!(
+            root.plugins.get(1).unwrap().permissions.headers,
+            vec!["Authorization"]
+        );
+
+        assert_eq!(
+            root.redaction,
+            crate::Redaction {
+                headers: vec![
+                    "authorization".to_string(),
+                    "proxy-authorization".to_string()
+                ],
+                cookies: vec!["session".to_string()],
+                mode: crate::RedactionMode::Hash,
+                secret: Some("correct-horse-battery-staple".to_string()),
+            }
+        );
+        assert_eq!(root.plugins.get(0).unwrap().redaction, root.redaction);
+        assert_eq!(
+            root.plugins.get(1).unwrap().redaction,
+            crate::Redaction {
+                headers: vec![
+                    "authorization".to_string(),
+                    "proxy-authorization".to_string(),
+                    "x-api-key".to_string()
+                ],
+                cookies: vec!["session".to_string()],
+                mode: crate::RedactionMode::Mask,
+                secret: Some("correct-horse-battery-staple".to_string()),
+            }
+        );
+        assert_eq!(
+            root.plugins.get(1).unwrap().failure_policy,
+            Some(crate::FailurePolicy::Closed(
+                crate::DEFAULT_FAILURE_RESTRICT
+            ))
+        );
 
         assert_eq!(root.presets.len(), 2);
         assert_eq!(root.presets.get(0).unwrap().reference, "default");
@@ -483,6 +1128,154 @@ mod tests {
             vec![crate::config::Reference::Preset("default".to_string())]
         );
         assert_eq!(root.resources.get(0).unwrap().timeout, Some(25));
+        assert_eq!(root.resources.get(0).unwrap().deadline, None);
+        assert_eq!(root.resources.get(1).unwrap().deadline, Some(150));
+        assert_eq!(
+            root.resources.get(0).unwrap().failure_policy,
+            crate::FailurePolicy::Open
+        );
+        assert_eq!(
+            root.resources.get(1).unwrap().failure_policy,
+            crate::FailurePolicy::Block
+        );
+        assert_eq!(
+            root.resources.get(0).unwrap().combination,
+            bulwark_decision::CombinationStrategy::Yager
+        );
+        assert_eq!(
+            root.resources.get(1).unwrap().combination,
+            bulwark_decision::CombinationStrategy::Murphy
+        );
+        assert!(!root.resources.get(0).unwrap().combine_phases);
+        assert!(root.resources.get(1).unwrap().combine_phases);
+        assert_eq!(root.resources.get(0).unwrap().shadow_plugins, vec![]);
+        assert_eq!(
+            root.resources.get(1).unwrap().shadow_plugins,
+            vec![crate::config::Reference::Plugin("blank_slate".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_trusted_proxy() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            parse_trusted_proxy("10.0.0.0/8")?,
+            "10.0.0.0/8".parse::<IpNet>()?
+        );
+        assert_eq!(
+            parse_trusted_proxy("192.0.2.43")?,
+            "192.0.2.43/32".parse::<IpNet>()?
+        );
+        assert_eq!(
+            parse_trusted_proxy("2001:db8::1")?,
+            "2001:db8::1/128".parse::<IpNet>()?
+        );
+        assert!(matches!(
+            parse_trusted_proxy("10.0.0.0/33"),
+            Err(ConfigFileError::InvalidTrustedProxy(_))
+        ));
+        assert!(matches!(
+            parse_trusted_proxy("bogus"),
+            Err(ConfigFileError::InvalidTrustedProxy(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_failure_restrict() -> Result<(), Box<dyn std::error::Error>> {
+        check_failure_restrict("evil_bit", None, None)?;
+        check_failure_restrict("evil_bit", Some(TomlFailurePolicy::Open), None)?;
+        check_failure_restrict("evil_bit", Some(TomlFailurePolicy::Closed), None)?;
+        check_failure_restrict("evil_bit", Some(TomlFailurePolicy::Closed), Some(0.5))?;
+
+        // A restrict mass is meaningless without a closed policy to apply it.
+        for policy in [
+            None,
+            Some(TomlFailurePolicy::Open),
+            Some(TomlFailurePolicy::Block),
+        ] {
+            assert!(matches!(
+                check_failure_restrict("evil_bit", policy, Some(0.5)),
+                Err(ConfigFileError::FailureRestrictWithoutClosedPolicy(name)) if name == "evil_bit"
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_state_timeouts() -> Result<(), Box<dyn std::error::Error>> {
+        let redis = "redis://127.0.0.1:6379".to_string();
+        let sled = "sled:///var/lib/bulwark/state".to_string();
+        check_state_timeouts(
+            Some(&redis),
+            &State::default(),
+            crate::DEFAULT_PLUGIN_TIMEOUT,
+        )?;
+
+        let slow = State {
+            command_timeout: Some(25),
+            ..Default::default()
+        };
+        check_state_timeouts(Some(&redis), &slow, 25)?;
+        // The time limits only apply to Redis.
+        check_state_timeouts(None, &slow, 10)?;
+        check_state_timeouts(Some(&sled), &slow, 10)?;
+        assert!(matches!(
+            check_state_timeouts(Some(&redis), &slow, 10),
+            Err(ConfigFileError::StateTimeoutExceedsPluginTimeout(10))
+        ));
+
+        // Redis Cluster and TLS connections have no default time limits.
+        let tls = "rediss://127.0.0.1:6379".to_string();
+        let cluster = State {
+            cluster_nodes: vec!["redis://127.0.0.1:6380".to_string()],
+            ..Default::default()
+        };
+        let partial = State {
+            connect_timeout: Some(40),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_state_timeouts(Some(&tls), &State::default(), 100),
+            Err(ConfigFileError::StateTimeoutRequired)
+        ));
+        assert!(matches!(
+            check_state_timeouts(Some(&tls), &partial, 100),
+            Err(ConfigFileError::StateTimeoutRequired)
+        ));
+        assert!(matches!(
+            check_state_timeouts(Some(&redis), &cluster, 100),
+            Err(ConfigFileError::StateTimeoutRequired)
+        ));
+        let explicit = State {
+            connect_timeout: Some(40),
+            command_timeout: Some(20),
+            ..cluster
+        };
+        check_state_timeouts(Some(&tls), &explicit, 100)?;
+        check_state_timeouts(Some(&redis), &explicit, 100)?;
+        assert!(matches!(
+            check_state_timeouts(Some(&tls), &explicit, 25),
+            Err(ConfigFileError::StateTimeoutExceedsPluginTimeout(25))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_redaction_secret() -> Result<(), Box<dyn std::error::Error>> {
+        let secret = "correct-horse-battery-staple".to_string();
+        check_redaction_secret("redaction", TomlRedactionMode::Mask, None)?;
+        check_redaction_secret("redaction", TomlRedactionMode::Mask, Some(&secret))?;
+        check_redaction_secret("redaction", TomlRedactionMode::Hash, Some(&secret))?;
+
+        assert!(matches!(
+            check_redaction_secret("evil_bit", TomlRedactionMode::Hash, None),
+            Err(ConfigFileError::MissingRedactionSecret(name)) if name == "evil_bit"
+        ));
 
         Ok(())
     }
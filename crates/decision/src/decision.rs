@@ -17,6 +17,27 @@ pub enum Outcome {
     Restricted,
 }
 
+/// Selects the rule used to [`combine`](Decision::combine) a set of decisions into a single decision.
+///
+/// Different rules handle conflict between decisions differently, so the best choice depends on how often the
+/// decisions being combined are expected to disagree.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CombinationStrategy {
+    /// See [`combine_murphy`](Decision::combine_murphy).
+    Murphy,
+    /// See [`combine_conjunctive`](Decision::combine_conjunctive).
+    Conjunctive,
+    /// See [`combine_yager`](Decision::combine_yager).
+    Yager,
+}
+
+impl Default for CombinationStrategy {
+    /// The Murphy average rule is the default because it behaves well under high conflict.
+    fn default() -> Self {
+        CombinationStrategy::Murphy
+    }
+}
+
 /// A two-state [Dempster-Shafer](https://en.wikipedia.org/wiki/Dempster%E2%80%93Shafer_theory) mass
 /// function that represents whether an operation should be accepted or restricted. The power set is represented
 /// by the `unknown` value.
@@ -279,6 +300,25 @@ impl Decision {
         }
     }
 
+    /// Combines a set of decisions using the selected [`CombinationStrategy`], returning a new [`Decision`] as the
+    /// result.
+    ///
+    /// # Arguments
+    ///
+    /// * `decisions` - The `Decision`s to be combined.
+    /// * `strategy` - The combination rule to apply.
+    pub fn combine<'a, I>(decisions: I, strategy: CombinationStrategy) -> Self
+    where
+        Self: 'a,
+        I: IntoIterator<Item = &'a Self>,
+    {
+        match strategy {
+            CombinationStrategy::Murphy => Self::combine_murphy(decisions),
+            CombinationStrategy::Conjunctive => Self::combine_conjunctive(decisions),
+            CombinationStrategy::Yager => Self::combine_yager(decisions),
+        }
+    }
+
     /// Calculates the conjunctive combination of a set of decisions, returning a new [`Decision`] as the result.
     ///
     /// Unlike [`combine_murphy`](Decision::combine_murphy), `combine_conjunctive` will produce a `NaN` result under
@@ -346,6 +386,47 @@ impl Decision {
         }
         d
     }
+
+    /// Calculates the Yager combination of a set of decisions, returning a new [`Decision`] as the result.
+    ///
+    /// Yager's rule[^1] combines decisions conjunctively without normalizing. Instead, any mass that would have been
+    /// assigned to the null hypothesis due to conflict is reassigned to `unknown`. Unlike
+    /// [`combine_murphy`](Decision::combine_murphy), a single strong decision isn't diluted by other neutral
+    /// decisions, and unlike [`combine_conjunctive`](Decision::combine_conjunctive), it never produces a `NaN`
+    /// result under high conflict.
+    ///
+    /// # Arguments
+    ///
+    /// * `decisions` - The `Decision`s to be combined.
+    ///
+    /// [^1]: Ronald R. Yager. 1987. On the Dempster-Shafer framework and new combination rules.
+    ///     Information Sciences 41, 2 (1987), 93-137. DOI:<https://doi.org/10.1016/0020-0255(87)90007-7>
+    pub fn combine_yager<'a, I>(decisions: I) -> Self
+    where
+        Self: 'a,
+        I: IntoIterator<Item = &'a Self>,
+    {
+        let mut d = Self {
+            accept: 0.0,
+            restrict: 0.0,
+            unknown: 1.0,
+        };
+        // The unnormalized conjunctive rule is associative, so conflict can be accumulated pairwise.
+        let mut conflict = 0.0;
+        for m in decisions {
+            conflict += d.accept * m.restrict + d.restrict * m.accept;
+            d = Self {
+                accept: d.accept * m.accept + d.accept * m.unknown + d.unknown * m.accept,
+                restrict: d.restrict * m.restrict + d.restrict * m.unknown + d.unknown * m.restrict,
+                unknown: d.unknown * m.unknown,
+            };
+        }
+        Self {
+            accept: d.accept,
+            restrict: d.restrict,
+            unknown: d.unknown + conflict,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -867,6 +948,89 @@ mod tests {
         assert!(d.unknown.is_nan());
     }
 
+    test_decision!(
+        combine_yager_simple_with_unknown,
+        Decision::combine_yager(&[
+            Decision {
+                accept: 0.35,
+                restrict: 0.20,
+                unknown: 0.45,
+            },
+            Decision {
+                accept: 0.0,
+                restrict: 0.0,
+                unknown: 1.0,
+            }
+        ]),
+        true,
+        accept = 0.35,
+        restrict = 0.2,
+        unknown = 0.45
+    );
+
+    test_decision!(
+        combine_yager_with_conflict,
+        Decision::combine_yager(&[
+            Decision {
+                accept: 0.5,
+                restrict: 0.0,
+                unknown: 0.5,
+            },
+            Decision {
+                accept: 0.0,
+                restrict: 0.5,
+                unknown: 0.5,
+            }
+        ]),
+        true,
+        accept = 0.25,
+        restrict = 0.25,
+        unknown = 0.5
+    );
+
+    test_decision!(
+        combine_yager_high_conflict,
+        Decision::combine_yager(&[
+            Decision {
+                accept: 1.0,
+                restrict: 0.0,
+                unknown: 0.0,
+            },
+            Decision {
+                accept: 0.0,
+                restrict: 1.0,
+                unknown: 0.0,
+            }
+        ]),
+        true,
+        accept = 0.0,
+        restrict = 0.0,
+        unknown = 1.0
+    );
+
+    test_decision!(
+        combine_strategy_murphy,
+        Decision::combine(
+            &[
+                Decision {
+                    accept: 0.35,
+                    restrict: 0.20,
+                    unknown: 0.45,
+                },
+                Decision {
+                    accept: 0.0,
+                    restrict: 0.0,
+                    unknown: 1.0,
+                }
+            ],
+            CombinationStrategy::Murphy
+        ),
+        true,
+        accept = 0.2946891191709844,
+        restrict = 0.16062176165803108,
+        unknown = 0.5446891191709845
+    );
+
     test_decision!(
         combine_murphy_high_conflict,
         Decision::combine_murphy(&[
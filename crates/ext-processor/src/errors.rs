@@ -28,6 +28,8 @@ pub enum PrepareRequestError {
     MissingPath,
     #[error("missing envoy headers")]
     MissingHeaders,
+    #[error("missing envoy request attributes")]
+    MissingAttributes,
 }
 
 /// Returned when trying to assemble a [`Request`](bulwark_wasm_sdk::Response) struct and Envoy sends missing
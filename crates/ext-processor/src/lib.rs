@@ -1,6 +1,9 @@
 //! Provides an [Envoy external processing][1] service for Bulwark.
 //!
+//! The same service also implements [Envoy external authorization][2] for proxies that only support that API.
+//!
 //! [1]: https://www.envoyproxy.io/docs/envoy/latest/configuration/http/http_filters/ext_proc_filter
+//! [2]: https://www.envoyproxy.io/docs/envoy/latest/configuration/http/http_filters/ext_authz_filter
 
 mod errors;
 mod headers;
@@ -8,5 +11,6 @@ mod service;
 
 use headers::*;
 
+pub use bulwark_wasm_host::{open_state_backend, MetricsRegistry, StateBackend};
 pub use errors::*;
 pub use service::*;
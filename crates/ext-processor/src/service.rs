@@ -1,67 +1,312 @@
-//! The service module contains the main Envoy external processor service implementation.
+//! The service module contains the main Envoy external processor and external authorization service
+//! implementations.
 
 use {
     crate::{
         serialize_decision_sfv, serialize_tags_sfv, PluginGroupInstantiationError,
         PrepareRequestError, PrepareResponseError, ProcessingMessageError, SfvError,
     },
-    bulwark_config::{Config, Thresholds},
+    bulwark_config::{
+        Config, FailurePolicy, LoadSheddingPolicy, Redaction, Thresholds, DEFAULT_PLUGIN_TIMEOUT,
+    },
     bulwark_wasm_host::{
-        DecisionComponents, ForwardedIP, Plugin, PluginExecutionError, PluginInstance,
-        PluginLoadError, RedisInfo, RequestContext, ScriptRegistry,
+        escape_label_value, DecisionComponents, ForwardedIP, HttpInfo, MetricsRegistry, Plugin,
+        PluginEngine, PluginExecutionError, PluginInstance, PluginLoadError, PrestartedInstances,
+        RemoteIP, RequestId, StateBackend, UpstreamHeaderMutation, EPOCH_TICK,
     },
-    bulwark_wasm_sdk::{BodyChunk, Decision},
-    envoy_control_plane::envoy::{
-        config::core::v3::{HeaderMap, HeaderValue, HeaderValueOption},
-        r#type::v3::HttpStatus,
-        service::ext_proc::v3::{
-            external_processor_server::ExternalProcessor, processing_request, processing_response,
-            CommonResponse, HeaderMutation, HeadersResponse, HttpHeaders, ImmediateResponse,
-            ProcessingRequest, ProcessingResponse,
+    bulwark_wasm_sdk::{BodyChunk, CombinationStrategy, Decision, Outcome},
+    envoy_control_plane::{
+        envoy::{
+            config::core::v3::{address, HeaderMap, HeaderValue, HeaderValueOption},
+            r#type::v3::HttpStatus,
+            service::{
+                auth::v3::{
+                    attribute_context, authorization_server::Authorization, check_response,
+                    CheckRequest, CheckResponse, DeniedHttpResponse, OkHttpResponse,
+                },
+                ext_proc::v3::{
+                    external_processor_server::ExternalProcessor, processing_request,
+                    processing_response, CommonResponse, HeaderMutation, HeadersResponse,
+                    HttpHeaders, ImmediateResponse, ProcessingRequest, ProcessingResponse,
+                },
+            },
         },
+        google::rpc,
+        pbjson_types_any::{Struct, Value as MetadataValue},
     },
     forwarded_header_value::ForwardedHeaderValue,
-    futures::{channel::mpsc::UnboundedSender, SinkExt, Stream},
+    futures::{channel::mpsc::Sender, SinkExt, Stream},
     http::StatusCode,
+    ipnet::IpNet,
     matchit::Router,
     std::{
-        collections::HashSet,
+        collections::{HashMap, HashSet},
+        fmt::{self, Write},
         net::IpAddr,
         pin::Pin,
         str,
         str::FromStr,
-        sync::{Arc, Mutex},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex, MutexGuard, TryLockError, Weak,
+        },
         time::Duration,
     },
-    tokio::{sync::RwLock, task::JoinSet, time::timeout},
-    tonic::{Request, Response, Status, Streaming},
+    tokio::{
+        sync::{oneshot, OwnedSemaphorePermit, RwLock, Semaphore},
+        task::{JoinHandle, JoinSet},
+        time::{error::Elapsed, timeout, timeout_at, Instant},
+    },
+    tonic::{Code, Request, Response, Status, Streaming},
     tracing::{debug, error, info, instrument, warn, Instrument},
+    uuid::Uuid,
 };
 
-extern crate redis;
-
 type ExternalProcessorStream =
     Pin<Box<dyn Stream<Item = Result<ProcessingResponse, Status>> + Send>>;
-type PluginList = Vec<Arc<Plugin>>;
+type ResponseSender = Sender<Result<ProcessingResponse, Status>>;
+type PluginList = Vec<Arc<PrestartedInstances>>;
+type DecisionTaskSet = JoinSet<(
+    usize,
+    Result<Result<DecisionComponents, PluginExecutionError>, Elapsed>,
+)>;
+
+/// The tag attached to any decision where at least one plugin failed to produce a decision.
+const PLUGIN_FAILURE_TAG: &str = "plugin-failure";
+/// The tag attached to the decision for a request that was allowed without running any plugins due to load shedding.
+const LOAD_SHED_TAG: &str = "load-shed";
+/// The longest request ID that will be accepted from an incoming request header.
+///
+/// Longer values are replaced with a generated ID so that arbitrarily large values don't end up in every log event.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+/// The dynamic metadata namespace that decision details are emitted under.
+const DYNAMIC_METADATA_NAMESPACE: &str = "bulwark";
+/// The number of processing responses that may be queued for Envoy on each external processing stream.
+///
+/// At most one response is sent for each of the request and response phases, so sending never has to wait on Envoy.
+const RESPONSE_CHANNEL_CAPACITY: usize = 2;
+/// How long an execution that timed out is waited on for the guest to trap at its epoch deadline.
+///
+/// The epoch advances every [`EPOCH_TICK`], so guest code traps almost immediately. Only an execution stuck in a
+/// blocking host call takes longer, and it's abandoned rather than holding up the request.
+const TIMEOUT_GRACE_PERIOD: Duration = Duration::from_millis(5);
+/// Every outcome a request may reach, in the order their counts are kept in [`OutcomeCounters`].
+const OUTCOMES: [Outcome; 4] = [
+    Outcome::Trusted,
+    Outcome::Accepted,
+    Outcome::Suspected,
+    Outcome::Restricted,
+];
 
 /// A RouteTarget allows a router to map from a routing pattern to a plugin group and associated config values.
 ///
 /// See [`bulwark_config::Resource`] for its configuration.
 struct RouteTarget {
     plugins: PluginList,
-    timeout: Option<u64>,
+    shadow_plugins: PluginList,
+    timeout: Duration,
+    deadline: Option<Duration>,
+    failure_policy: FailurePolicy,
+    combination: CombinationStrategy,
+    combine_phases: bool,
+    outcomes: Arc<OutcomeCounters>,
+}
+
+/// Counts the final outcome of every request routed to a resource, separately for its enforcing and shadow plugins.
+///
+/// Shadow outcomes are never acted upon, so comparing the two shows what a shadow plugin set would change if it were
+/// promoted to enforcing.
+#[derive(Default)]
+struct OutcomeCounters {
+    enforcing: [AtomicU64; OUTCOMES.len()],
+    shadow: [AtomicU64; OUTCOMES.len()],
+}
+
+impl OutcomeCounters {
+    /// Counts a request that reached the given outcome.
+    fn record(&self, outcome: Outcome, shadow: bool) {
+        let counters = if shadow {
+            &self.shadow
+        } else {
+            &self.enforcing
+        };
+        // The outcomes are listed in declaration order.
+        counters[outcome as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A plugin instance created for a routed request, along with the plugin it was created from.
+///
+/// The plugin's settings are read from `plugin` rather than through the instance's lock, because an execution that
+/// timed out may still be holding the lock. The lock is only ever taken on the blocking thread pool.
+#[derive(Clone)]
+struct RoutedInstance {
+    plugin: Arc<Plugin>,
+    instance: Arc<Mutex<PluginInstance>>,
+}
+
+/// The enforcing plugins instantiated for a routed request, along with everything needed to execute them.
+struct RoutedPlugins {
+    plugin_instances: Vec<RoutedInstance>,
+    settings: ExecutionSettings,
+    /// Hands the interior service's response to the shadow plugins, if there are any.
+    ///
+    /// Dropping the sender instead causes the shadow plugins to skip the response phase.
+    shadow_response: oneshot::Sender<Arc<bulwark_wasm_sdk::Response>>,
+}
+
+/// The settings that govern plugin execution and decision handling for a single request.
+///
+/// Combines the service-wide [`Thresholds`] with the settings of the matched [`RouteTarget`].
+#[derive(Clone)]
+struct ExecutionSettings {
+    thresholds: Thresholds,
+    /// The default amount of time each plugin may take for each execution phase.
+    timeout_duration: Duration,
+    /// The time by which all plugins must finish every phase of the request, if the resource sets a deadline.
+    ///
+    /// It's pushed back by [`resume_deadline`](ExecutionSettings::resume_deadline) once the response arrives, so
+    /// that only plugin execution counts against it.
+    deadline: Option<Instant>,
+    failure_policy: FailurePolicy,
+    combination: CombinationStrategy,
+    combine_phases: bool,
+    /// Where the final outcome of the request is counted.
+    outcomes: Arc<OutcomeCounters>,
+}
+
+impl ExecutionSettings {
+    /// Pushes the request's deadline back by the time elapsed since `paused_at`.
+    ///
+    /// Called with the time the request phase finished once the interior service has responded, so that waiting on
+    /// the interior service doesn't use up the time the plugins have left for the response phase.
+    fn resume_deadline(&mut self, paused_at: Instant) {
+        if let Some(deadline) = self.deadline.as_mut() {
+            *deadline += paused_at.elapsed();
+        }
+    }
+}
+
+/// A cap on some unit of concurrent work, such as requests being processed or plugin instances in use.
+///
+/// Capacity is reserved without waiting, so work that would exceed the cap is shed rather than queued.
+#[derive(Clone)]
+struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a new [`ConcurrencyLimit`], which only counts work in use if there is no `limit`.
+    fn new(limit: Option<usize>) -> Self {
+        let limit = limit.unwrap_or(Semaphore::MAX_PERMITS);
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    /// Reserves capacity for `amount` units of work, returning `None` if that would exceed the cap.
+    ///
+    /// The capacity is released when the returned permit is dropped.
+    fn try_acquire(&self, amount: usize) -> Option<OwnedSemaphorePermit> {
+        let amount = u32::try_from(amount).ok()?;
+        self.semaphore.clone().try_acquire_many_owned(amount).ok()
+    }
+
+    /// The number of units of work currently in use.
+    fn in_flight(&self) -> usize {
+        self.limit - self.semaphore.available_permits()
+    }
+}
+
+/// The concurrency limits for the processor and the policy applied to requests that would exceed them.
+#[derive(Clone)]
+struct ConcurrencyLimits {
+    requests: ConcurrencyLimit,
+    plugins: ConcurrencyLimit,
+    load_shedding: LoadSheddingPolicy,
+    /// The number of requests shed so far because one of the limits was reached.
+    shed: Arc<AtomicU64>,
+}
+
+/// A snapshot of the amount of work the processor is currently handling, and of how much it has shed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessorLoad {
+    /// The number of requests currently being processed.
+    pub requests: usize,
+    /// The number of plugin instances, both enforcing and shadow, currently reserved by requests.
+    pub plugin_instances: usize,
+    /// The number of requests shed since the processor was created because a concurrency limit was reached.
+    pub shed: u64,
+}
+
+/// The configuration details needed to interpret a plugin's result once its execution has finished.
+///
+/// These are captured before execution begins because the result of a plugin that panics is never returned. They're
+/// read even if an earlier panic poisoned the instance's lock, since configuration can't be left half-modified by a
+/// panic, and the poisoned instance then fails with [`PluginExecutionError::Poisoned`] under its failure policy.
+struct PluginResultContext {
+    reference: String,
+    weight: f64,
+    failure_policy: FailurePolicy,
+}
+
+/// The reason a plugin failed to produce a decision.
+enum PluginFailure {
+    /// The plugin returned an error or trapped.
+    Error(PluginExecutionError),
+    /// The plugin did not finish before its timeout elapsed.
+    Timeout,
+    /// The plugin's task panicked or was cancelled before it could produce a decision.
+    Aborted,
+}
+
+impl PluginFailure {
+    /// A short identifier for the kind of failure, suitable for logging.
+    fn kind(&self) -> &'static str {
+        match self {
+            PluginFailure::Error(
+                PluginExecutionError::TimeLimitExceeded | PluginExecutionError::Busy,
+            ) => "timeout",
+            PluginFailure::Error(
+                PluginExecutionError::MemoryLimitExceeded { .. }
+                | PluginExecutionError::TableLimitExceeded { .. },
+            ) => "limit",
+            PluginFailure::Error(_) => "error",
+            PluginFailure::Timeout => "timeout",
+            PluginFailure::Aborted => "aborted",
+        }
+    }
+}
+
+impl fmt::Display for PluginFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginFailure::Error(err) => write!(f, "plugin execution failed: {}", err),
+            PluginFailure::Timeout => write!(f, "plugin execution timed out"),
+            PluginFailure::Aborted => write!(f, "plugin execution aborted"),
+        }
+    }
 }
 
 /// The `BulwarkProcessor` implements the primary envoy processing service logic via the [`ExternalProcessor`] trait.
 ///
-/// The [`process`](BulwarkProcessor::process) function is the main request handler.
+/// The [`process`](BulwarkProcessor::process) function is the main request handler. The [`Authorization`] trait is
+/// also implemented as an alternative integration for proxies that only support external authorization, via the
+/// [`check`](BulwarkProcessor::check) function.
 pub struct BulwarkProcessor {
     // TODO: may need to have a plugin registry at some point
     router: Arc<RwLock<Router<RouteTarget>>>,
-    redis_info: Option<Arc<RedisInfo>>,
     thresholds: bulwark_config::Thresholds,
     hops: usize,
-    // TODO: redis circuit breaker for health monitoring
+    trusted_proxies: Vec<IpNet>,
+    request_id_header: String,
+    limits: ConcurrencyLimits,
+    redaction: Redaction,
+    /// The outcome counters of each resource, keyed by its route.
+    outcomes: Vec<(String, Arc<OutcomeCounters>)>,
+    // TODO: state backend circuit breaker for health monitoring
 }
 
 #[tonic::async_trait]
@@ -69,58 +314,112 @@ impl ExternalProcessor for BulwarkProcessor {
     type ProcessStream = ExternalProcessorStream;
 
     /// Processes an incoming request, performing all Envoy-specific handling needed by [`bulwark_wasm_host`].
-    #[instrument(name = "handle request", skip(self, tonic_request))]
+    #[instrument(
+        name = "handle request",
+        skip(self, tonic_request),
+        fields(request_id = tracing::field::Empty)
+    )]
     async fn process(
         &self,
         tonic_request: Request<Streaming<ProcessingRequest>>,
     ) -> Result<Response<ExternalProcessorStream>, Status> {
         let mut stream = tonic_request.into_inner();
         let thresholds = self.thresholds;
-        if let Ok(http_req) = Self::prepare_request(&mut stream, self.hops).await {
-            let redis_info = self.redis_info.clone();
+        if let Ok(http_req) = Self::prepare_request(
+            &mut stream,
+            self.hops,
+            &self.trusted_proxies,
+            &self.request_id_header,
+        )
+        .await
+        {
             let http_req = Arc::new(http_req);
             let router = self.router.clone();
+            let request_id_echo = Self::record_request_id(&self.request_id_header, &http_req);
 
-            info!(
-                message = "process request",
-                method = http_req.method().to_string(),
-                uri = http_req.uri().to_string(),
-                user_agent = http_req
-                    .headers()
-                    .get("User-Agent")
-                    .map(|ua: &http::HeaderValue| ua.to_str().unwrap_or_default())
-            );
+            Self::log_request(&self.redaction, &http_req);
 
+            let limits = self.limits.clone();
+            let request_permit = limits.requests.try_acquire(1);
             let child_span = tracing::info_span!("route request");
-            let (sender, receiver) = futures::channel::mpsc::unbounded();
+            let (sender, receiver) = futures::channel::mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
             tokio::task::spawn(
                 async move {
+                    // The permit is held until the request has been fully handled.
+                    let _request_permit = match request_permit {
+                        Some(permit) => permit,
+                        None => {
+                            Self::shed_request(
+                                sender,
+                                stream,
+                                &limits,
+                                "requests",
+                                thresholds,
+                                request_id_echo,
+                            )
+                            .await;
+                            return;
+                        }
+                    };
                     let http_req = http_req.clone();
                     let router = router.read().await;
                     let route_result = router.at(http_req.uri().path());
-                    // TODO: router needs to point to a struct that bundles the plugin set and associated config like timeout duration
-                    // TODO: put default timeout in a constant somewhere central
-                    let mut timeout_duration = Duration::from_millis(10);
                     match route_result {
                         Ok(route_match) => {
+                            let _plugin_permit =
+                                match limits.plugins.try_acquire(route_match.value.plugins.len()) {
+                                    Some(permit) => permit,
+                                    None => {
+                                        Self::shed_request(
+                                            sender,
+                                            stream,
+                                            &limits,
+                                            "plugins",
+                                            thresholds,
+                                            request_id_echo,
+                                        )
+                                        .await;
+                                        return;
+                                    }
+                                };
+                            let shadow_permit = limits
+                                .plugins
+                                .try_acquire(route_match.value.shadow_plugins.len());
                             // TODO: may want to expose params to logging after redaction
-                            let route_target = route_match.value;
                             // TODO: figure out how to bubble the error out of the task and up to the parent
                             // TODO: figure out if tonic-error or some other option is the best way to convert to a tonic Status error
-                            let plugin_instances = Self::instantiate_plugins(
-                                &route_target.plugins,
-                                redis_info.clone(),
+                            let routed = match Self::route_plugins(
+                                route_match.value,
+                                &route_match.params,
                                 http_req.clone(),
-                                route_match.params,
-                            )
-                            .unwrap();
-                            if let Some(millis) = route_match.value.timeout {
-                                timeout_duration = Duration::from_millis(millis);
-                            }
+                                thresholds,
+                                shadow_permit,
+                            ) {
+                                Ok(routed) => routed,
+                                Err(err) => {
+                                    // Most likely the instance pool is exhausted, so this is handled like any other
+                                    // lack of capacity.
+                                    error!(
+                                        message = "plugin instantiation failed",
+                                        error_message = %err,
+                                    );
+                                    Self::shed_request(
+                                        sender,
+                                        stream,
+                                        &limits,
+                                        "instances",
+                                        thresholds,
+                                        request_id_echo,
+                                    )
+                                    .await;
+                                    return;
+                                }
+                            };
 
                             let combined = Self::execute_request_phase(
-                                plugin_instances.clone(),
-                                timeout_duration,
+                                routed.plugin_instances.clone(),
+                                routed.settings.clone(),
+                                false,
                             )
                             .await;
 
@@ -128,9 +427,10 @@ impl ExternalProcessor for BulwarkProcessor {
                                 sender,
                                 stream,
                                 combined,
-                                thresholds,
-                                plugin_instances,
-                                timeout_duration,
+                                routed.settings,
+                                routed.plugin_instances,
+                                routed.shadow_response,
+                                request_id_echo,
                             )
                             .await;
                         }
@@ -151,66 +451,335 @@ impl ExternalProcessor for BulwarkProcessor {
     }
 }
 
+#[tonic::async_trait]
+impl Authorization for BulwarkProcessor {
+    /// Checks an incoming request, performing all Envoy-specific handling needed by [`bulwark_wasm_host`].
+    ///
+    /// Only the request phase is executed because the external authorization API never sees the response.
+    #[instrument(
+        name = "handle request",
+        skip(self, tonic_request),
+        fields(request_id = tracing::field::Empty)
+    )]
+    async fn check(
+        &self,
+        tonic_request: Request<CheckRequest>,
+    ) -> Result<Response<CheckResponse>, Status> {
+        let check_request = tonic_request.into_inner();
+        let http_req = Arc::new(
+            Self::prepare_check_request(
+                &check_request,
+                self.hops,
+                &self.trusted_proxies,
+                &self.request_id_header,
+            )
+            .map_err(|err| Status::invalid_argument(err.to_string()))?,
+        );
+        let request_id_echo = Self::record_request_id(&self.request_id_header, &http_req);
+
+        Self::log_request(&self.redaction, &http_req);
+
+        // The permit is held until the request has been fully handled.
+        let _request_permit = match self.limits.requests.try_acquire(1) {
+            Some(permit) => permit,
+            None => {
+                return Self::shed_check(&self.limits, "requests", self.thresholds, request_id_echo)
+            }
+        };
+        let child_span = tracing::info_span!("route request");
+        async {
+            let router = self.router.read().await;
+            let route_match = router.at(http_req.uri().path()).map_err(|_| {
+                error!(uri = http_req.uri().to_string(), message = "match error");
+                Status::not_found("match error")
+            })?;
+            let _plugin_permit = match self
+                .limits
+                .plugins
+                .try_acquire(route_match.value.plugins.len())
+            {
+                Some(permit) => permit,
+                None => {
+                    return Self::shed_check(
+                        &self.limits,
+                        "plugins",
+                        self.thresholds,
+                        request_id_echo,
+                    )
+                }
+            };
+            let shadow_permit = self
+                .limits
+                .plugins
+                .try_acquire(route_match.value.shadow_plugins.len());
+            let routed = match Self::route_plugins(
+                route_match.value,
+                &route_match.params,
+                http_req.clone(),
+                self.thresholds,
+                shadow_permit,
+            ) {
+                Ok(routed) => routed,
+                Err(err) => {
+                    // Most likely the instance pool is exhausted, so this is handled like any other lack of capacity.
+                    error!(message = "plugin instantiation failed", error_message = %err);
+                    return Self::shed_check(
+                        &self.limits,
+                        "instances",
+                        self.thresholds,
+                        request_id_echo,
+                    );
+                }
+            };
+
+            let combined = Self::execute_request_phase(
+                routed.plugin_instances.clone(),
+                routed.settings.clone(),
+                false,
+            )
+            .await;
+            // There's no response phase, so the shadow plugins skip straight to feedback.
+            drop(routed.shadow_response);
+
+            Self::handle_check_decision(
+                combined,
+                routed.settings,
+                routed.plugin_instances,
+                request_id_echo,
+            )
+            .map(Response::new)
+            .map_err(|err| Status::internal(err.to_string()))
+        }
+        .instrument(child_span.or_current())
+        .await
+    }
+}
+
 impl BulwarkProcessor {
     /// Creates a new [`BulwarkProcessor`].
     ///
     /// # Arguments
     ///
     /// * `config` - The root of the Bulwark configuration structure to be used to initialize the service.
-    pub fn new(config: Config) -> Result<Self, PluginLoadError> {
-        let redis_info = if let Some(remote_state_addr) = config.service.remote_state.as_ref() {
-            // TODO: better error handling instead of unwrap/panic
-            let client = redis::Client::open(remote_state_addr.as_str()).unwrap();
-            // TODO: make pool size configurable
-            Some(Arc::new(RedisInfo {
-                // TODO: better error handling instead of unwrap/panic
-                pool: r2d2::Pool::builder().max_size(16).build(client).unwrap(),
-                registry: ScriptRegistry::default(),
-            }))
-        } else {
-            None
-        };
-
+    /// * `metrics` - The registry that custom metrics recorded by plugins are stored in.
+    /// * `state_backend` - The store of state shared between plugin instances.
+    pub fn new(
+        config: Config,
+        metrics: Arc<MetricsRegistry>,
+        state_backend: Arc<dyn StateBackend>,
+    ) -> Result<Self, PluginLoadError> {
         let mut router: Router<RouteTarget> = Router::new();
         if config.resources.is_empty() {
             // TODO: return an init error not a plugin load error
             return Err(PluginLoadError::ResourceMissing);
         }
+        let engine = Self::create_engine(&config)?.with_metrics(metrics);
+        let http_info = Arc::new(HttpInfo::new(
+            Duration::from_millis(config.service.outbound_timeout),
+            config.service.max_outbound_response_size,
+        ));
+        // Plugins referenced by more than one resource are only compiled once.
+        let mut loaded_plugins: HashMap<String, Arc<PrestartedInstances>> = HashMap::new();
+        let mut outcomes = Vec::with_capacity(config.resources.len());
         for resource in &config.resources {
-            let plugin_configs = resource.resolve_plugins(&config)?;
-            let mut plugins: PluginList = Vec::with_capacity(plugin_configs.len());
-            for plugin_config in plugin_configs {
-                // TODO: pass in the plugin config
-                debug!(
-                    message = "load plugin",
-                    path = plugin_config.path,
-                    resource = resource.route
-                );
-                let plugin = Plugin::from_file(plugin_config.path.clone(), plugin_config)?;
-                plugins.push(Arc::new(plugin));
-            }
+            let plugins = Self::load_plugins(
+                &engine,
+                &mut loaded_plugins,
+                resource.resolve_plugins(&config)?,
+                resource,
+                &state_backend,
+                &http_info,
+            )?;
+            let shadow_plugins = Self::load_plugins(
+                &engine,
+                &mut loaded_plugins,
+                resource.resolve_shadow_plugins(&config)?,
+                resource,
+                &state_backend,
+                &http_info,
+            )?;
+            let resource_outcomes = Arc::new(OutcomeCounters::default());
+            outcomes.push((resource.route.clone(), resource_outcomes.clone()));
             router
                 .insert(
                     resource.route.clone(),
                     // TODO: the route target will probably need access to the route itself in the future
                     RouteTarget {
-                        timeout: resource.timeout,
+                        timeout: Duration::from_millis(
+                            resource.timeout.unwrap_or(DEFAULT_PLUGIN_TIMEOUT),
+                        ),
+                        deadline: resource.deadline.map(Duration::from_millis),
+                        failure_policy: resource.failure_policy,
+                        combination: resource.combination,
+                        combine_phases: resource.combine_phases,
+                        outcomes: resource_outcomes,
                         plugins,
+                        shadow_plugins,
                     },
                 )
                 .ok();
         }
+        Self::spawn_epoch_ticker(
+            engine,
+            loaded_plugins
+                .values()
+                .map(|plugin| Arc::downgrade(plugin.plugin()))
+                .collect(),
+        );
+        // Prestarting happens up front so that the first requests don't pay for it. The epoch ticker has to be
+        // running first for plugin timeouts to be enforced.
+        for plugin in loaded_plugins.values() {
+            plugin.replenish();
+        }
         Ok(Self {
             router: Arc::new(RwLock::new(router)),
-            redis_info,
             thresholds: config.thresholds,
             hops: usize::from(config.service.proxy_hops),
+            trusted_proxies: config.service.trusted_proxies.clone(),
+            request_id_header: config.service.request_id_header.clone(),
+            limits: ConcurrencyLimits {
+                requests: ConcurrencyLimit::new(config.service.max_concurrent_requests),
+                plugins: ConcurrencyLimit::new(config.service.max_concurrent_plugins),
+                load_shedding: config.service.load_shedding,
+                shed: Arc::new(AtomicU64::new(0)),
+            },
+            redaction: config.redaction.clone(),
+            outcomes,
+        })
+    }
+
+    /// Creates the engine shared by every plugin.
+    ///
+    /// The pooling allocator, if any plugin uses it, is sized for every plugin instance that may exist at once. That
+    /// is the plugin concurrency limit if there is one, otherwise the sum of the plugins' pool sizes, plus the
+    /// instances each plugin keeps prestarted.
+    fn create_engine(config: &Config) -> Result<PluginEngine, PluginLoadError> {
+        let mut plugin_configs: HashMap<&str, &bulwark_config::Plugin> = HashMap::new();
+        for resource in &config.resources {
+            for plugin_config in resource
+                .resolve_plugins(config)?
+                .into_iter()
+                .chain(resource.resolve_shadow_plugins(config)?)
+            {
+                plugin_configs.insert(plugin_config.reference.as_str(), plugin_config);
+            }
+        }
+        let pool_size = plugin_configs
+            .values()
+            .filter_map(|plugin_config| plugin_config.pool_size)
+            .reduce(u32::saturating_add)
+            .map(|pool_size| {
+                let in_use = config
+                    .service
+                    .max_concurrent_plugins
+                    .map_or(pool_size, |limit| u32::try_from(limit).unwrap_or(u32::MAX));
+                plugin_configs
+                    .values()
+                    .filter_map(|plugin_config| plugin_config.prestart)
+                    .fold(in_use, u32::saturating_add)
+            });
+
+        let engine = PluginEngine::new(pool_size)?;
+        Ok(match config.service.module_cache.as_ref() {
+            Some(module_cache) => engine.with_module_cache(module_cache),
+            None => engine,
         })
     }
 
+    /// Advances the epoch of the plugin engine so that plugin time limits are enforced.
+    ///
+    /// This runs on a dedicated thread rather than a tokio task because plugins execute on the blocking pool and the
+    /// ticker must keep running even if the runtime's workers are busy. The thread exits once every plugin is dropped.
+    fn spawn_epoch_ticker(engine: PluginEngine, plugins: Vec<Weak<Plugin>>) {
+        std::thread::Builder::new()
+            .name("bulwark-epoch-ticker".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(EPOCH_TICK);
+                if plugins.iter().all(|plugin| plugin.strong_count() == 0) {
+                    break;
+                }
+                engine.increment_epoch();
+            })
+            .expect("could not spawn epoch ticker thread");
+    }
+
+    /// Returns the amount of work the processor is currently handling and the number of requests it has shed.
+    ///
+    /// The plugin instance count is the number reserved by requests, which is the number of plugins, enforcing and
+    /// shadow, on the resources those requests were routed to.
+    pub fn load(&self) -> ProcessorLoad {
+        ProcessorLoad {
+            requests: self.limits.requests.in_flight(),
+            plugin_instances: self.limits.plugins.in_flight(),
+            shed: self.limits.shed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Renders the number of requests that reached each outcome on each resource in the Prometheus text format.
+    ///
+    /// Enforcing and shadow outcomes are exported as separate series of the same metric, distinguished by the
+    /// `shadow` label.
+    pub fn render_outcomes(&self) -> String {
+        let mut output = String::from("# TYPE bulwark_decisions_total counter\n");
+        for (route, counters) in self.outcomes.iter() {
+            for (shadow, counters) in [(false, &counters.enforcing), (true, &counters.shadow)] {
+                for (outcome, count) in OUTCOMES.iter().zip(counters.iter()) {
+                    // Writing to a String can't fail.
+                    writeln!(
+                        output,
+                        "bulwark_decisions_total{{resource=\"{}\",outcome=\"{}\",shadow=\"{}\"}} {}",
+                        escape_label_value(route),
+                        Self::outcome_name(*outcome),
+                        shadow,
+                        count.load(Ordering::Relaxed)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        output
+    }
+
+    /// Loads the plugins for a resource, reusing any that an earlier resource already loaded.
+    fn load_plugins(
+        engine: &PluginEngine,
+        loaded_plugins: &mut HashMap<String, Arc<PrestartedInstances>>,
+        plugin_configs: Vec<&bulwark_config::Plugin>,
+        resource: &bulwark_config::Resource,
+        state_backend: &Arc<dyn StateBackend>,
+        http_info: &Arc<HttpInfo>,
+    ) -> Result<PluginList, PluginLoadError> {
+        let mut plugins: PluginList = Vec::with_capacity(plugin_configs.len());
+        for plugin_config in plugin_configs {
+            if let Some(plugin) = loaded_plugins.get(&plugin_config.reference) {
+                plugins.push(plugin.clone());
+                continue;
+            }
+            debug!(
+                message = "load plugin",
+                path = plugin_config.path,
+                resource = resource.route
+            );
+            let plugin = Arc::new(PrestartedInstances::new(
+                Arc::new(Plugin::from_file(
+                    engine,
+                    plugin_config.path.clone(),
+                    plugin_config,
+                )?),
+                state_backend.clone(),
+                http_info.clone(),
+            ));
+            loaded_plugins.insert(plugin_config.reference.clone(), plugin.clone());
+            plugins.push(plugin);
+        }
+        Ok(plugins)
+    }
+
     async fn prepare_request(
         stream: &mut Streaming<ProcessingRequest>,
         proxy_hops: usize,
+        trusted_proxies: &[IpNet],
+        request_id_header: &str,
     ) -> Result<bulwark_wasm_sdk::Request, PrepareRequestError> {
         if let Some(header_msg) = Self::get_request_headers(stream).await {
             // TODO: currently this information isn't used and isn't accessible to the plugin environment yet
@@ -249,23 +818,90 @@ impl BulwarkProcessor {
 
             // TODO: remote IP should probably be received via an external attribute, but that doesn't seem to be currently supported by envoy?
             // NOTE: header keys must be sent in lower case
-            if let Some(forwarded) = Self::get_header_value(&header_msg.headers, "forwarded") {
-                if let Some(ip_addr) = Self::parse_forwarded_ip(forwarded, proxy_hops) {
-                    request = request.extension(ForwardedIP(ip_addr));
-                }
-            } else if let Some(forwarded) =
-                Self::get_header_value(&header_msg.headers, "x-forwarded-for")
-            {
-                if let Some(ip_addr) = Self::parse_x_forwarded_for_ip(forwarded, proxy_hops) {
-                    request = request.extension(ForwardedIP(ip_addr));
-                }
+            if let Some(ip_addr) = Self::resolve_forwarded_ip(
+                Self::get_header_value(&header_msg.headers, "forwarded"),
+                Self::get_header_value(&header_msg.headers, "x-forwarded-for"),
+                proxy_hops,
+                trusted_proxies,
+            ) {
+                request = request.extension(ForwardedIP(ip_addr));
             }
+            request = request.extension(Self::resolve_request_id(Self::get_header_value(
+                &header_msg.headers,
+                request_id_header,
+            )));
 
             return Ok(request.body(request_chunk)?);
         }
         Err(PrepareRequestError::MissingHeaders)
     }
 
+    fn prepare_check_request(
+        check_request: &CheckRequest,
+        proxy_hops: usize,
+        trusted_proxies: &[IpNet],
+        request_id_header: &str,
+    ) -> Result<bulwark_wasm_sdk::Request, PrepareRequestError> {
+        let attributes = check_request
+            .attributes
+            .as_ref()
+            .ok_or(PrepareRequestError::MissingAttributes)?;
+        let http_attributes = attributes
+            .request
+            .as_ref()
+            .and_then(|request| request.http.as_ref())
+            .ok_or(PrepareRequestError::MissingHeaders)?;
+
+        if http_attributes.method.is_empty() {
+            return Err(PrepareRequestError::MissingMethod);
+        }
+        if http_attributes.path.is_empty() {
+            return Err(PrepareRequestError::MissingPath);
+        }
+        let method = http::Method::from_str(&http_attributes.method)?;
+        let mut request = http::Request::builder();
+        // TODO: read the request body
+        let request_chunk = bulwark_wasm_sdk::BodyChunk {
+            // the authorization API never streams the body, any body content arrives in a single message
+            end_of_stream: true,
+            size: 0,
+            start: 0,
+            content: vec![],
+        };
+        request = request.method(method).uri(&http_attributes.path);
+        for (key, value) in &http_attributes.headers {
+            // must not pass through Envoy pseudo headers here, http module treats them as invalid
+            if !key.starts_with(':') {
+                request = request.header(key, value);
+            }
+        }
+
+        // NOTE: header keys are sent in lower case
+        if let Some(ip_addr) = Self::resolve_forwarded_ip(
+            http_attributes.headers.get("forwarded").map(String::as_str),
+            http_attributes
+                .headers
+                .get("x-forwarded-for")
+                .map(String::as_str),
+            proxy_hops,
+            trusted_proxies,
+        ) {
+            request = request.extension(ForwardedIP(ip_addr));
+        }
+        // Unlike the external processing API, the authorization API tells us the downstream peer address.
+        if let Some(ip_addr) = attributes.source.as_ref().and_then(Self::peer_ip) {
+            request = request.extension(RemoteIP(ip_addr));
+        }
+        request = request.extension(Self::resolve_request_id(
+            http_attributes
+                .headers
+                .get(request_id_header)
+                .map(String::as_str),
+        ));
+
+        Ok(request.body(request_chunk)?)
+    }
+
     async fn prepare_response(
         stream: &mut Streaming<ProcessingRequest>,
     ) -> Result<bulwark_wasm_sdk::Response, PrepareResponseError> {
@@ -298,12 +934,14 @@ impl BulwarkProcessor {
         Err(PrepareResponseError::MissingHeaders)
     }
 
+    /// Creates an instance of each plugin for a request, preferring prestarted instances.
+    ///
+    /// Plugins whose prestarted instances run low are replenished on the blocking thread pool.
     fn instantiate_plugins(
         plugins: &PluginList,
-        redis_info: Option<Arc<RedisInfo>>,
         http_req: Arc<bulwark_wasm_sdk::Request>,
-        params: matchit::Params,
-    ) -> Result<Vec<Arc<Mutex<PluginInstance>>>, PluginGroupInstantiationError> {
+        params: &matchit::Params,
+    ) -> Result<Vec<RoutedInstance>, PluginGroupInstantiationError> {
         let mut plugin_instances = Vec::with_capacity(plugins.len());
         let mut shared_params = bulwark_wasm_sdk::Map::new();
         for (key, value) in params.iter() {
@@ -312,51 +950,186 @@ impl BulwarkProcessor {
         }
         let shared_params = Arc::new(Mutex::new(shared_params));
         for plugin in plugins {
-            let request_context = RequestContext::new(
-                plugin.clone(),
-                redis_info.clone(),
-                shared_params.clone(),
-                http_req.clone(),
-            )?;
-
-            plugin_instances.push(Arc::new(Mutex::new(PluginInstance::new(
-                plugin.clone(),
-                request_context,
-            )?)));
+            plugin_instances.push(RoutedInstance {
+                plugin: plugin.plugin().clone(),
+                instance: Arc::new(Mutex::new(
+                    plugin.instantiate(shared_params.clone(), http_req.clone())?,
+                )),
+            });
+            if plugin.needs_replenishing() {
+                let plugin = plugin.clone();
+                tokio::task::spawn_blocking(move || plugin.replenish());
+            }
         }
         Ok(plugin_instances)
     }
 
+    /// Records an incoming request.
+    ///
+    /// Header values are redacted before the event is emitted so that no log format can expose them.
+    fn log_request(redaction: &Redaction, http_req: &bulwark_wasm_sdk::Request) {
+        info!(
+            message = "process request",
+            request_id = http_req
+                .extensions()
+                .get::<RequestId>()
+                .map(|request_id| request_id.0.as_str()),
+            method = http_req.method().to_string(),
+            uri = http_req.uri().to_string(),
+            user_agent = Self::loggable_header(redaction, http_req.headers(), "user-agent")
+        );
+    }
+
+    /// Returns a request header value with the redaction rules applied, ready to be logged.
+    fn loggable_header(
+        redaction: &Redaction,
+        headers: &http::HeaderMap,
+        name: &str,
+    ) -> Option<String> {
+        let value = headers.get(name)?;
+        Some(String::from_utf8_lossy(&redaction.redact_header(name, value.as_bytes())).into_owned())
+    }
+
+    /// Uses the request ID from the incoming request header if it's usable, otherwise generates a new one.
+    fn resolve_request_id(header_value: Option<&str>) -> RequestId {
+        match header_value {
+            Some(request_id)
+                if !request_id.is_empty()
+                    && request_id.len() <= MAX_REQUEST_ID_LENGTH
+                    && request_id.chars().all(|c| c.is_ascii_graphic()) =>
+            {
+                RequestId(request_id.to_string())
+            }
+            _ => RequestId(Uuid::new_v4().to_string()),
+        }
+    }
+
+    /// Attaches the request ID to the current span, returning the header change that echoes it to the interior
+    /// service.
+    fn record_request_id(
+        request_id_header: &str,
+        http_req: &bulwark_wasm_sdk::Request,
+    ) -> Option<UpstreamHeaderMutation> {
+        let request_id = http_req.extensions().get::<RequestId>()?;
+        tracing::Span::current().record("request_id", request_id.0.as_str());
+        Some(UpstreamHeaderMutation::Set {
+            name: request_id_header.to_string(),
+            value: request_id.0.as_bytes().to_vec(),
+        })
+    }
+
+    /// Instantiates the plugins for a matched route and starts the execution of any shadow plugins.
+    ///
+    /// The shadow plugins only run if capacity was reserved for them in `shadow_permit`, which they hold until every
+    /// phase, including feedback, has finished. Shadow plugins are skipped rather than shedding the request because
+    /// they can never affect the response.
+    fn route_plugins(
+        route_target: &RouteTarget,
+        params: &matchit::Params,
+        http_req: Arc<bulwark_wasm_sdk::Request>,
+        thresholds: Thresholds,
+        shadow_permit: Option<OwnedSemaphorePermit>,
+    ) -> Result<RoutedPlugins, PluginGroupInstantiationError> {
+        let plugin_instances =
+            Self::instantiate_plugins(&route_target.plugins, http_req.clone(), params)?;
+        let settings = ExecutionSettings {
+            thresholds,
+            timeout_duration: route_target.timeout,
+            deadline: route_target
+                .deadline
+                .map(|deadline| Instant::now() + deadline),
+            failure_policy: route_target.failure_policy,
+            combination: route_target.combination,
+            combine_phases: route_target.combine_phases,
+            outcomes: route_target.outcomes.clone(),
+        };
+
+        // The shadow plugins run independently so that they can never affect the response.
+        let (shadow_response_sender, shadow_response_receiver) = oneshot::channel();
+        match shadow_permit {
+            _ if route_target.shadow_plugins.is_empty() => {}
+            Some(shadow_permit) => {
+                // Shadow plugins get their own params so they can't influence enforcing plugins.
+                let shadow_instances =
+                    Self::instantiate_plugins(&route_target.shadow_plugins, http_req, params)?;
+                let shadow_child_span = tracing::info_span!("execute shadow plugins");
+                let settings = settings.clone();
+                tokio::task::spawn(
+                    async move {
+                        Self::execute_shadow_phases(
+                            shadow_instances,
+                            shadow_response_receiver,
+                            settings,
+                        )
+                        .await;
+                        drop(shadow_permit);
+                    }
+                    .instrument(shadow_child_span.or_current()),
+                );
+            }
+            None => {
+                warn!(
+                    message = "shadow plugins skipped",
+                    limit = "plugins",
+                    shadow_plugins = route_target.shadow_plugins.len(),
+                );
+            }
+        }
+
+        Ok(RoutedPlugins {
+            plugin_instances,
+            settings,
+            shadow_response: shadow_response_sender,
+        })
+    }
+
     async fn execute_request_phase(
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-        timeout_duration: std::time::Duration,
+        plugin_instances: Vec<RoutedInstance>,
+        settings: ExecutionSettings,
+        shadow: bool,
     ) -> DecisionComponents {
-        Self::execute_request_phase_one(plugin_instances.clone(), timeout_duration).await;
-        Self::execute_request_phase_two(plugin_instances.clone(), timeout_duration).await
+        Self::execute_request_phase_one(plugin_instances.clone(), settings.clone()).await;
+        Self::execute_request_phase_two(plugin_instances.clone(), settings, shadow).await
     }
 
     async fn execute_request_phase_one(
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-        timeout_duration: std::time::Duration,
+        plugin_instances: Vec<RoutedInstance>,
+        settings: ExecutionSettings,
     ) {
         let mut phase_one_tasks = JoinSet::new();
         for plugin_instance in plugin_instances.clone() {
             let phase_one_child_span = tracing::info_span!("execute on_request",);
+            let deadline = Self::plugin_deadline(
+                &plugin_instance.plugin,
+                settings.timeout_duration,
+                settings.deadline,
+            );
             phase_one_tasks.spawn(
-                timeout(timeout_duration, async move {
-                    // TODO: avoid unwraps
-                    Self::execute_plugin_initialization(plugin_instance.clone()).unwrap();
-                    Self::execute_on_request(plugin_instance.clone()).unwrap();
-                })
+                async move {
+                    Self::execute_blocking(deadline, move || {
+                        let mut plugin_instance =
+                            Self::lock_instance(&plugin_instance.instance, deadline)?;
+                        Self::execute_plugin_initialization(&mut plugin_instance)?;
+                        Self::execute_on_request(&mut plugin_instance)
+                    })
+                    .await
+                }
                 .instrument(phase_one_child_span.or_current()),
             );
         }
         // efficiently hand execution off to the plugins
         tokio::task::yield_now().await;
 
+        // Failures here are only logged, the failure policy gets applied if the decision phase fails too.
         while let Some(r) = phase_one_tasks.join_next().await {
             match r {
-                Ok(Ok(_)) => {}
+                Ok(Ok(Ok(_))) => {}
+                Ok(Ok(Err(e))) => {
+                    warn!(
+                        message = "error on plugin execution",
+                        error_message = %e,
+                    );
+                }
                 Ok(Err(e)) => {
                     warn!(
                         message = "timeout on plugin execution",
@@ -374,135 +1147,136 @@ impl BulwarkProcessor {
     }
 
     async fn execute_request_phase_two(
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-        timeout_duration: std::time::Duration,
+        plugin_instances: Vec<RoutedInstance>,
+        settings: ExecutionSettings,
+        shadow: bool,
     ) -> DecisionComponents {
-        let decision_components = Arc::new(Mutex::new(Vec::with_capacity(plugin_instances.len())));
-        let mut phase_two_tasks = JoinSet::new();
-        for plugin_instance in plugin_instances.clone() {
+        let result_contexts = Self::plugin_result_contexts(&plugin_instances, &settings);
+        let mut phase_two_tasks: DecisionTaskSet = JoinSet::new();
+        for (index, plugin_instance) in plugin_instances.into_iter().enumerate() {
             let phase_two_child_span = tracing::info_span!("execute on_request_decision",);
-            let decision_components = decision_components.clone();
+            let deadline = Self::plugin_deadline(
+                &plugin_instance.plugin,
+                settings.timeout_duration,
+                settings.deadline,
+            );
             phase_two_tasks.spawn(
-                timeout(timeout_duration, async move {
-                    let decision_result =
-                        Self::execute_on_request_decision(plugin_instance.clone());
-                    let mut decision_component = decision_result.unwrap();
-                    {
-                        // Re-weight the decision based on its weighting value from the configuration
-                        let plugin_instance = plugin_instance.lock().unwrap();
-                        decision_component.decision =
-                            decision_component.decision.weight(plugin_instance.weight());
-
-                        let decision = &decision_component.decision;
-                        info!(
-                            message = "plugin decision",
-                            name = plugin_instance.plugin_reference(),
-                            accept = decision.accept,
-                            restrict = decision.restrict,
-                            unknown = decision.unknown,
-                            score = decision.pignistic().restrict,
-                        );
-                    }
-                    let mut decision_components = decision_components.lock().unwrap();
-                    decision_components.push(decision_component);
-                })
+                async move {
+                    let result = Self::execute_blocking(deadline, move || {
+                        let mut plugin_instance =
+                            Self::lock_instance(&plugin_instance.instance, deadline)?;
+                        Self::execute_on_request_decision(&mut plugin_instance)
+                    })
+                    .await;
+                    (index, result)
+                }
                 .instrument(phase_two_child_span.or_current()),
             );
         }
         // efficiently hand execution off to the plugins
         tokio::task::yield_now().await;
 
-        while let Some(r) = phase_two_tasks.join_next().await {
-            match r {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    warn!(
-                        message = "timeout on plugin execution",
-                        elapsed = ?e,
-                    );
-                }
-                Err(e) => {
-                    warn!(
-                        message = "join error on plugin execution",
-                        error_message = ?e,
-                    );
-                }
-            }
-        }
-        let decision_vec: Vec<Decision>;
-        let tags: HashSet<String>;
-        {
-            let decision_components = decision_components.lock().unwrap();
-            decision_vec = decision_components.iter().map(|dc| dc.decision).collect();
-            tags = decision_components
-                .iter()
-                .flat_map(|dc| dc.tags.clone())
-                .collect();
-        }
-        let decision = Decision::combine_murphy(&decision_vec);
-
-        DecisionComponents {
-            decision,
-            tags: tags.into_iter().collect(),
-        }
+        let results = Self::join_decision_tasks(phase_two_tasks, result_contexts.len()).await;
+        Self::combine_plugin_results(result_contexts, results, settings.combination, shadow)
     }
 
     async fn execute_response_phase(
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
+        plugin_instances: Vec<RoutedInstance>,
         response: Arc<http::Response<BodyChunk>>,
-        timeout_duration: std::time::Duration,
+        settings: ExecutionSettings,
+        shadow: bool,
     ) -> DecisionComponents {
-        let decision_components = Arc::new(Mutex::new(Vec::with_capacity(plugin_instances.len())));
-        let mut response_phase_tasks = JoinSet::new();
-        for plugin_instance in plugin_instances.clone() {
+        let result_contexts = Self::plugin_result_contexts(&plugin_instances, &settings);
+        let mut response_phase_tasks: DecisionTaskSet = JoinSet::new();
+        for (index, plugin_instance) in plugin_instances.into_iter().enumerate() {
             let response_phase_child_span = tracing::info_span!("execute on_response_decision",);
-            {
-                // Make sure the plugin instance knows about the response
-                let mut plugin_instance = plugin_instance.lock().unwrap();
-                let response = response.clone();
-                plugin_instance.record_response(response);
-            }
-            let decision_components = decision_components.clone();
+            let deadline = Self::plugin_deadline(
+                &plugin_instance.plugin,
+                settings.timeout_duration,
+                settings.deadline,
+            );
+            let response = response.clone();
             response_phase_tasks.spawn(
-                timeout(timeout_duration, async move {
-                    let decision_result =
-                        Self::execute_on_response_decision(plugin_instance.clone());
-                    let mut decision_component = decision_result.unwrap();
-                    {
-                        // Re-weight the decision based on its weighting value from the configuration
-                        let plugin_instance = plugin_instance.lock().unwrap();
-                        decision_component.decision =
-                            decision_component.decision.weight(plugin_instance.weight());
-
-                        let decision = &decision_component.decision;
-                        info!(
-                            message = "plugin decision",
-                            name = plugin_instance.plugin_reference(),
-                            accept = decision.accept,
-                            restrict = decision.restrict,
-                            unknown = decision.unknown,
-                            score = decision.pignistic().restrict,
-                        );
-                    }
-                    let mut decision_components = decision_components.lock().unwrap();
-                    decision_components.push(decision_component);
-                })
+                async move {
+                    let result = Self::execute_blocking(deadline, move || {
+                        let mut plugin_instance =
+                            Self::lock_instance(&plugin_instance.instance, deadline)?;
+                        // Make sure the plugin instance knows about the response.
+                        plugin_instance.record_response(response);
+                        Self::execute_on_response_decision(&mut plugin_instance)
+                    })
+                    .await;
+                    (index, result)
+                }
                 .instrument(response_phase_child_span.or_current()),
             );
         }
         // efficiently hand execution off to the plugins
         tokio::task::yield_now().await;
 
-        while let Some(r) = response_phase_tasks.join_next().await {
+        let results = Self::join_decision_tasks(response_phase_tasks, result_contexts.len()).await;
+        Self::combine_plugin_results(result_contexts, results, settings.combination, shadow)
+    }
+
+    /// Determines when a plugin's next execution phase must finish.
+    ///
+    /// The plugin's own timeout takes precedence over the resource's timeout, but either one is cut short by the
+    /// request's overall deadline.
+    fn plugin_deadline(
+        plugin: &Plugin,
+        timeout_duration: Duration,
+        request_deadline: Option<Instant>,
+    ) -> Instant {
+        let timeout_duration = plugin
+            .timeout()
+            .map(Duration::from_millis)
+            .unwrap_or(timeout_duration);
+        let mut deadline = Instant::now() + timeout_duration;
+        if let Some(request_deadline) = request_deadline {
+            deadline = deadline.min(request_deadline);
+        }
+        deadline
+    }
+
+    /// Captures the details needed to interpret each plugin's result, resolving each plugin's failure policy.
+    fn plugin_result_contexts(
+        plugin_instances: &[RoutedInstance],
+        settings: &ExecutionSettings,
+    ) -> Vec<PluginResultContext> {
+        plugin_instances
+            .iter()
+            .map(|plugin_instance| PluginResultContext {
+                reference: plugin_instance.plugin.reference().to_string(),
+                weight: plugin_instance.plugin.weight(),
+                failure_policy: plugin_instance
+                    .plugin
+                    .failure_policy()
+                    .unwrap_or(settings.failure_policy),
+            })
+            .collect()
+    }
+
+    /// Waits for every decision task to finish, collecting the results in plugin order.
+    ///
+    /// Any plugin whose task never reported back is treated as aborted.
+    async fn join_decision_tasks(
+        mut tasks: DecisionTaskSet,
+        count: usize,
+    ) -> Vec<Result<DecisionComponents, PluginFailure>> {
+        let mut results: Vec<Result<DecisionComponents, PluginFailure>> =
+            (0..count).map(|_| Err(PluginFailure::Aborted)).collect();
+        while let Some(r) = tasks.join_next().await {
             match r {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    warn!(
-                        message = "timeout on plugin execution",
-                        elapsed = ?e,
-                    );
+                Ok((index, Ok(Ok(decision_components)))) => {
+                    results[index] = Ok(decision_components);
                 }
-                Err(e) => {
+                Ok((index, Ok(Err(e)))) => {
+                    results[index] = Err(PluginFailure::Error(e));
+                }
+                Ok((index, Err(_))) => {
+                    results[index] = Err(PluginFailure::Timeout);
+                }
+                Err(e) => {
                     warn!(
                         message = "join error on plugin execution",
                         error_message = ?e,
@@ -510,17 +1284,124 @@ impl BulwarkProcessor {
                 }
             }
         }
-        let decision_vec: Vec<Decision>;
-        let tags: HashSet<String>;
-        {
-            let decision_components = decision_components.lock().unwrap();
-            decision_vec = decision_components.iter().map(|dc| dc.decision).collect();
-            tags = decision_components
-                .iter()
-                .flat_map(|dc| dc.tags.clone())
-                .collect();
+        results
+    }
+
+    /// Combines the results of a decision phase into a single set of decision components.
+    ///
+    /// Plugins that failed to produce a decision have their failure policy applied in place of a decision. Failing
+    /// open contributes an unknown decision, failing closed contributes a restrict decision, and blocking overrides
+    /// the combined decision entirely.
+    fn combine_plugin_results(
+        result_contexts: Vec<PluginResultContext>,
+        results: Vec<Result<DecisionComponents, PluginFailure>>,
+        combination: CombinationStrategy,
+        shadow: bool,
+    ) -> DecisionComponents {
+        let mut decision_vec: Vec<Decision> = Vec::with_capacity(results.len());
+        let mut tags: HashSet<String> = HashSet::new();
+        let mut blocked = false;
+        for (context, result) in result_contexts.into_iter().zip(results) {
+            match result {
+                Ok(mut decision_component) => {
+                    // Re-weight the decision based on its weighting value from the configuration
+                    decision_component.decision =
+                        decision_component.decision.weight(context.weight);
+
+                    let decision = &decision_component.decision;
+                    info!(
+                        message = "plugin decision",
+                        name = context.reference,
+                        accept = decision.accept,
+                        restrict = decision.restrict,
+                        unknown = decision.unknown,
+                        score = decision.pignistic().restrict,
+                        shadow = shadow,
+                    );
+                    decision_vec.push(decision_component.decision);
+                    tags.extend(decision_component.tags);
+                }
+                Err(failure) => {
+                    warn!(
+                        message = "plugin failure",
+                        name = context.reference,
+                        failure = failure.kind(),
+                        error_message = %failure,
+                        shadow = shadow,
+                    );
+                    let decision = match context.failure_policy {
+                        FailurePolicy::Open => Decision {
+                            accept: 0.0,
+                            restrict: 0.0,
+                            unknown: 1.0,
+                        },
+                        FailurePolicy::Closed(restrict) => Decision {
+                            accept: 0.0,
+                            restrict,
+                            unknown: 1.0 - restrict,
+                        },
+                        FailurePolicy::Block => {
+                            blocked = true;
+                            Decision {
+                                accept: 0.0,
+                                restrict: 1.0,
+                                unknown: 0.0,
+                            }
+                        }
+                    };
+                    info!(
+                        message = "plugin decision",
+                        name = context.reference,
+                        accept = decision.accept,
+                        restrict = decision.restrict,
+                        unknown = decision.unknown,
+                        score = decision.pignistic().restrict,
+                        failure = failure.kind(),
+                        shadow = shadow,
+                    );
+                    decision_vec.push(decision);
+                    tags.insert(PLUGIN_FAILURE_TAG.to_string());
+                }
+            }
         }
-        let decision = Decision::combine_murphy(&decision_vec);
+        let decision = if blocked {
+            Decision {
+                accept: 0.0,
+                restrict: 1.0,
+                unknown: 0.0,
+            }
+        } else {
+            Decision::combine(&decision_vec, combination)
+        };
+
+        DecisionComponents {
+            decision,
+            tags: tags.into_iter().collect(),
+        }
+    }
+
+    /// Combines the request phase and response phase decisions into a single set of decision components.
+    ///
+    /// A response phase decision that's certain to restrict, such as one forced by a blocking failure policy, is
+    /// kept as-is rather than being softened by the request phase decision.
+    fn combine_phase_decisions(
+        request_decision_components: &DecisionComponents,
+        response_decision_components: DecisionComponents,
+        combination: CombinationStrategy,
+    ) -> DecisionComponents {
+        let mut tags: HashSet<String> = request_decision_components.tags.iter().cloned().collect();
+        tags.extend(response_decision_components.tags);
+        let decision = if response_decision_components.decision.restrict >= 1.0 {
+            response_decision_components.decision
+        } else {
+            Decision::combine(
+                &[
+                    request_decision_components.decision,
+                    response_decision_components.decision,
+                ],
+                combination,
+            )
+        };
 
         DecisionComponents {
             decision,
@@ -528,18 +1409,154 @@ impl BulwarkProcessor {
         }
     }
 
+    /// Executes all phases for a resource's shadow plugins.
+    ///
+    /// The response is received from the enforcing plugins' handler if it gets that far. If the request was
+    /// blocked instead, the sender will be dropped and the shadow plugins skip directly to the feedback phase.
+    async fn execute_shadow_phases(
+        plugin_instances: Vec<RoutedInstance>,
+        response: oneshot::Receiver<Arc<bulwark_wasm_sdk::Response>>,
+        mut settings: ExecutionSettings,
+    ) {
+        let mut decision_components =
+            Self::execute_request_phase(plugin_instances.clone(), settings.clone(), true).await;
+        let mut outcome = Self::log_shadow_decision(&decision_components, settings.thresholds);
+        // The request's deadline is stopped while the request is with the interior service.
+        let request_phase_finished = Instant::now();
+
+        if let Ok(response) = response.await {
+            settings.resume_deadline(request_phase_finished);
+            let response_decision_components = Self::execute_response_phase(
+                plugin_instances.clone(),
+                response,
+                settings.clone(),
+                true,
+            )
+            .await;
+            decision_components = if settings.combine_phases {
+                Self::combine_phase_decisions(
+                    &decision_components,
+                    response_decision_components,
+                    settings.combination,
+                )
+            } else {
+                response_decision_components
+            };
+            outcome = Self::log_shadow_decision(&decision_components, settings.thresholds);
+        }
+        settings.outcomes.record(outcome, true);
+
+        // Shadow plugins only ever see their own combined decision in the feedback phase.
+        let feedback_tasks = Self::handle_decision_feedback(
+            decision_components,
+            outcome,
+            plugin_instances,
+            settings.timeout_duration,
+        );
+        // Waiting on feedback keeps the shadow plugins' capacity reserved until they're completely done.
+        for feedback_task in feedback_tasks {
+            feedback_task.await.ok();
+        }
+    }
+
+    /// Records the combined decision for a set of shadow plugins.
+    ///
+    /// The outcome is calculated against the same thresholds as the enforcing plugins, but it's never acted upon.
+    fn log_shadow_decision(
+        decision_components: &DecisionComponents,
+        thresholds: Thresholds,
+    ) -> bulwark_wasm_sdk::Outcome {
+        let decision = decision_components.decision;
+        let outcome = decision
+            .outcome(thresholds.trust, thresholds.suspicious, thresholds.restrict)
+            .unwrap();
+
+        info!(
+            message = "combine decision",
+            accept = decision.accept,
+            restrict = decision.restrict,
+            unknown = decision.unknown,
+            score = decision.pignistic().restrict,
+            outcome = Self::outcome_name(outcome),
+            shadow = true,
+            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
+            tags = decision_components
+                .tags
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<&str>>()
+                .to_vec()
+                .join(","),
+        );
+
+        outcome
+    }
+
+    /// Runs plugin code on the blocking thread pool, timing out at the given deadline.
+    ///
+    /// Guest code and host calls like outbound HTTP requests and Redis commands block the thread they run on, so
+    /// running them on an executor thread would stall unrelated requests. A panic in the plugin code is propagated
+    /// to the calling task.
+    ///
+    /// The plugin's epoch deadline is set from the same deadline, so guest code traps as soon as it times out and
+    /// the blocking task is given [`TIMEOUT_GRACE_PERIOD`] to finish. A task that's still stuck in a host call after
+    /// that is abandoned. It keeps holding the instance's lock until it finishes, so any later phase fails straight
+    /// away with [`PluginExecutionError::Busy`] rather than waiting on it.
+    async fn execute_blocking<F, T>(deadline: Instant, f: F) -> Result<T, Elapsed>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        // The blocking pool doesn't inherit the current span, so events logged by plugins would otherwise be orphaned.
+        let span = tracing::Span::current();
+        let mut task = None;
+        let result = timeout_at(deadline, async {
+            // Yielding first ensures a plugin whose deadline has already passed times out instead of running.
+            tokio::task::yield_now().await;
+            task.insert(tokio::task::spawn_blocking(move || span.in_scope(f)))
+                .await
+        })
+        .await;
+        match result {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(err)) => std::panic::resume_unwind(err.into_panic()),
+            Err(elapsed) => {
+                if let Some(task) = task {
+                    timeout(TIMEOUT_GRACE_PERIOD, task).await.ok();
+                }
+                Err(elapsed)
+            }
+        }
+    }
+
+    /// Takes the lock on a plugin instance so it can be executed, recording the deadline for the execution so that
+    /// it's enforced within the guest environment.
+    ///
+    /// Only ever called on the blocking thread pool. An instance whose lock was poisoned by a panic during an
+    /// earlier phase, or that's still held by an earlier phase that timed out, returns an error instead of waiting,
+    /// so that the plugin's failure policy is applied.
+    fn lock_instance(
+        plugin_instance: &Mutex<PluginInstance>,
+        deadline: Instant,
+    ) -> Result<MutexGuard<PluginInstance>, PluginExecutionError> {
+        let mut plugin_instance = plugin_instance.try_lock().map_err(|err| match err {
+            TryLockError::Poisoned(_) => PluginExecutionError::Poisoned,
+            TryLockError::WouldBlock => PluginExecutionError::Busy,
+        })?;
+        plugin_instance.record_deadline(deadline.into_std());
+        Ok(plugin_instance)
+    }
+
     fn execute_plugin_initialization(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
+        plugin_instance: &mut PluginInstance,
     ) -> Result<(), PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().unwrap();
-        // unlike on_request, the _start/main function is mandatory
+        // unlike on-request, the on-init function is mandatory
         plugin_instance.start()
     }
 
     fn execute_on_request(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
+        plugin_instance: &mut PluginInstance,
     ) -> Result<(), PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().unwrap();
         let result = plugin_instance.handle_request();
         match result {
             Ok(_) => result,
@@ -553,9 +1570,8 @@ impl BulwarkProcessor {
     }
 
     fn execute_on_request_decision(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
+        plugin_instance: &mut PluginInstance,
     ) -> Result<DecisionComponents, PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().unwrap();
         let result = plugin_instance.handle_request_decision();
         if let Err(e) = result {
             match e {
@@ -569,9 +1585,8 @@ impl BulwarkProcessor {
     }
 
     fn execute_on_response_decision(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
+        plugin_instance: &mut PluginInstance,
     ) -> Result<DecisionComponents, PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().unwrap();
         let result = plugin_instance.handle_response_decision();
         if let Err(e) = result {
             match e {
@@ -585,9 +1600,8 @@ impl BulwarkProcessor {
     }
 
     fn execute_on_decision_feedback(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
+        plugin_instance: &mut PluginInstance,
     ) -> Result<(), PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().unwrap();
         let result = plugin_instance.handle_decision_feedback();
         if let Err(e) = result {
             match e {
@@ -600,48 +1614,196 @@ impl BulwarkProcessor {
         Ok(())
     }
 
-    async fn handle_request_phase_decision(
-        sender: UnboundedSender<Result<ProcessingResponse, Status>>,
+    /// Logs that a request is being shed and the current load, and counts the shed request.
+    fn log_load_shed(limits: &ConcurrencyLimits, limit: &str) {
+        limits.shed.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            message = "load shed",
+            limit,
+            policy = match limits.load_shedding {
+                LoadSheddingPolicy::Open => "open",
+                LoadSheddingPolicy::Closed => "closed",
+                LoadSheddingPolicy::Reject => "reject",
+            },
+            in_flight_requests = limits.requests.in_flight(),
+            in_flight_plugins = limits.plugins.in_flight(),
+        );
+    }
+
+    /// The decision used for a request that fails open due to load shedding.
+    fn load_shed_decision() -> DecisionComponents {
+        DecisionComponents {
+            decision: Decision {
+                accept: 0.0,
+                restrict: 0.0,
+                unknown: 1.0,
+            },
+            tags: vec![LOAD_SHED_TAG.to_string()],
+        }
+    }
+
+    /// Handles an external processing stream without running any plugins because a concurrency limit was reached.
+    async fn shed_request(
+        mut sender: ResponseSender,
         mut stream: Streaming<ProcessingRequest>,
-        decision_components: DecisionComponents,
+        limits: &ConcurrencyLimits,
+        limit: &str,
         thresholds: Thresholds,
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-        timeout_duration: std::time::Duration,
+        request_id_echo: Option<UpstreamHeaderMutation>,
     ) {
-        let decision = decision_components.decision;
-        let outcome = decision
-            .outcome(thresholds.trust, thresholds.suspicious, thresholds.restrict)
-            .unwrap();
+        Self::log_load_shed(limits, limit);
+
+        let allowed = Self::shed_request_phase(
+            &mut sender,
+            limits.load_shedding,
+            thresholds,
+            request_id_echo,
+        )
+        .await;
+        if let Some((decision_components, metadata)) = allowed {
+            // The response still needs to be acknowledged for the stream to complete.
+            if Self::prepare_response(&mut stream).await.is_ok() {
+                let result =
+                    Self::allow_response(&mut sender, &decision_components, metadata).await;
+                if let Err(err) = result {
+                    debug!(message = format!("send error: {}", err));
+                }
+            }
+        }
+    }
 
-        info!(
-            message = "combine decision",
-            accept = decision.accept,
-            restrict = decision.restrict,
-            unknown = decision.unknown,
-            score = decision.pignistic().restrict,
-            outcome = match outcome {
-                bulwark_wasm_sdk::Outcome::Trusted => "trusted",
-                bulwark_wasm_sdk::Outcome::Accepted => "accepted",
-                bulwark_wasm_sdk::Outcome::Suspected => "suspected",
-                bulwark_wasm_sdk::Outcome::Restricted => "restricted",
-            },
-            observe_only = thresholds.observe_only,
-            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
-            tags = decision_components
-                .tags
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
-                .to_vec()
-                .join(","),
+    /// Answers the request phase of an external processing stream that's being shed, according to the load
+    /// shedding policy.
+    ///
+    /// Returns the decision and its metadata if the request was allowed, in which case the response phase still
+    /// needs to be answered.
+    async fn shed_request_phase(
+        sender: &mut ResponseSender,
+        load_shedding: LoadSheddingPolicy,
+        thresholds: Thresholds,
+        request_id_echo: Option<UpstreamHeaderMutation>,
+    ) -> Option<(DecisionComponents, Struct)> {
+        match load_shedding {
+            LoadSheddingPolicy::Open => {
+                let decision_components = Self::load_shed_decision();
+                let outcome = Self::log_combined_decision(&decision_components, thresholds);
+                let metadata =
+                    Self::decision_metadata(&decision_components, outcome, thresholds, &[]);
+                let upstream_header_mutations: Vec<UpstreamHeaderMutation> =
+                    request_id_echo.into_iter().collect();
+                let result = Self::allow_request(
+                    sender,
+                    &decision_components,
+                    &upstream_header_mutations,
+                    metadata.clone(),
+                )
+                .await;
+                // TODO: must perform proper error handling on sender results, sending can fail
+                if let Err(err) = result {
+                    debug!(message = format!("send error: {}", err));
+                }
+                Some((decision_components, metadata))
+            }
+            LoadSheddingPolicy::Closed => {
+                let decision_components = Self::load_shed_decision();
+                let metadata = Self::decision_metadata(
+                    &decision_components,
+                    bulwark_wasm_sdk::Outcome::Restricted,
+                    thresholds,
+                    &[],
+                );
+                info!(message = "process response", status = 403);
+                let result = Self::block_request(sender, &decision_components, metadata).await;
+                if let Err(err) = result {
+                    debug!(message = format!("send error: {}", err));
+                }
+                None
+            }
+            LoadSheddingPolicy::Reject => {
+                let result = sender
+                    .send(Err(Status::resource_exhausted("load shed")))
+                    .await;
+                if let Err(err) = result {
+                    debug!(message = format!("send error: {}", err));
+                }
+                None
+            }
+        }
+    }
+
+    /// Answers an external authorization check without running any plugins because a concurrency limit was reached.
+    fn shed_check(
+        limits: &ConcurrencyLimits,
+        limit: &str,
+        thresholds: Thresholds,
+        request_id_echo: Option<UpstreamHeaderMutation>,
+    ) -> Result<Response<CheckResponse>, Status> {
+        Self::log_load_shed(limits, limit);
+
+        let decision_components = Self::load_shed_decision();
+        match limits.load_shedding {
+            LoadSheddingPolicy::Open => {
+                let outcome = Self::log_combined_decision(&decision_components, thresholds);
+                let metadata =
+                    Self::decision_metadata(&decision_components, outcome, thresholds, &[]);
+                let upstream_header_mutations: Vec<UpstreamHeaderMutation> =
+                    request_id_echo.into_iter().collect();
+                Self::allow_check(&decision_components, &upstream_header_mutations, metadata)
+                    .map(Response::new)
+                    .map_err(|err| Status::internal(err.to_string()))
+            }
+            LoadSheddingPolicy::Closed => {
+                let metadata = Self::decision_metadata(
+                    &decision_components,
+                    bulwark_wasm_sdk::Outcome::Restricted,
+                    thresholds,
+                    &[],
+                );
+                info!(message = "process response", status = 403);
+                Ok(Response::new(Self::deny_check(
+                    &decision_components,
+                    metadata,
+                )))
+            }
+            LoadSheddingPolicy::Reject => Err(Status::resource_exhausted("load shed")),
+        }
+    }
+
+    async fn handle_request_phase_decision(
+        mut sender: ResponseSender,
+        mut stream: Streaming<ProcessingRequest>,
+        decision_components: DecisionComponents,
+        mut settings: ExecutionSettings,
+        plugin_instances: Vec<RoutedInstance>,
+        shadow_response: oneshot::Sender<Arc<bulwark_wasm_sdk::Response>>,
+        request_id_echo: Option<UpstreamHeaderMutation>,
+    ) {
+        // The request's deadline is stopped while the request is with the interior service.
+        let request_phase_finished = Instant::now();
+        let thresholds = settings.thresholds;
+        let outcome = Self::log_combined_decision(&decision_components, thresholds);
+        let metadata = Self::decision_metadata(
+            &decision_components,
+            outcome,
+            thresholds,
+            &Self::plugin_references(&plugin_instances),
         );
+        let mut upstream_header_mutations = Self::upstream_header_mutations(&plugin_instances);
+        // The request ID is applied last so that it can't be overwritten by a plugin.
+        upstream_header_mutations.extend(request_id_echo);
 
         match outcome {
             bulwark_wasm_sdk::Outcome::Trusted
             | bulwark_wasm_sdk::Outcome::Accepted
             // suspected requests are monitored but not rejected
             | bulwark_wasm_sdk::Outcome::Suspected => {
-                let result = Self::allow_request(&sender, &decision_components).await;
+                let result = Self::allow_request(
+                    &mut sender,
+                    &decision_components,
+                    &upstream_header_mutations,
+                    metadata,
+                )
+                .await;
                 // TODO: must perform proper error handling on sender results, sending can fail
                 if let Err(err) = result {
                     debug!(message = format!("send error: {}", err));
@@ -650,7 +1812,8 @@ impl BulwarkProcessor {
             bulwark_wasm_sdk::Outcome::Restricted => {
                 if !thresholds.observe_only {
                     info!(message = "process response", status = 403);
-                    let result = Self::block_request(&sender, &decision_components).await;
+                    let result =
+                        Self::block_request(&mut sender, &decision_components, metadata).await;
                     // TODO: must perform proper error handling on sender results, sending can fail
                     if let Err(err) = result {
                         debug!(message = format!("send error: {}", err));
@@ -658,16 +1821,23 @@ impl BulwarkProcessor {
 
                     // Normally we initiate feedback after the response phase, but if we skip the response phase
                     // we need to do it here instead.
+                    settings.outcomes.record(outcome, false);
                     Self::handle_decision_feedback(
                         decision_components,
                         outcome,
                         plugin_instances,
-                        timeout_duration,
+                        settings.timeout_duration,
                     );
                     // Short-circuit if restricted, we can skip the response phase
                     return;
                 } else {
-                    let result = Self::allow_request(&sender, &decision_components).await;
+                    let result = Self::allow_request(
+                        &mut sender,
+                        &decision_components,
+                        &upstream_header_mutations,
+                        metadata,
+                    )
+                    .await;
                     // TODO: must perform proper error handling on sender results, sending can fail
                     if let Err(err) = result {
                         debug!(message = format!("send error: {}", err));
@@ -677,56 +1847,52 @@ impl BulwarkProcessor {
         }
 
         if let Ok(http_resp) = Self::prepare_response(&mut stream).await {
+            settings.resume_deadline(request_phase_finished);
             let http_resp = Arc::new(http_resp);
             let status = http_resp.status();
+            // If there are no shadow plugins, the receiver will already be gone, which is fine.
+            shadow_response.send(http_resp.clone()).ok();
+
+            let mut response_decision_components = Self::execute_response_phase(
+                plugin_instances.clone(),
+                http_resp,
+                settings.clone(),
+                false,
+            )
+            .await;
+            if settings.combine_phases {
+                response_decision_components = Self::combine_phase_decisions(
+                    &decision_components,
+                    response_decision_components,
+                    settings.combination,
+                );
+            }
 
             Self::handle_response_phase_decision(
                 sender,
-                Self::execute_response_phase(plugin_instances.clone(), http_resp, timeout_duration)
-                    .await,
+                response_decision_components,
                 status,
-                thresholds,
+                settings,
                 plugin_instances.clone(),
-                timeout_duration,
             )
             .await;
         }
     }
 
     async fn handle_response_phase_decision(
-        sender: UnboundedSender<Result<ProcessingResponse, Status>>,
+        mut sender: ResponseSender,
         decision_components: DecisionComponents,
         response_status: StatusCode,
-        thresholds: Thresholds,
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-        timeout_duration: std::time::Duration,
+        settings: ExecutionSettings,
+        plugin_instances: Vec<RoutedInstance>,
     ) {
-        let decision = decision_components.decision;
-        let outcome = decision
-            .outcome(thresholds.trust, thresholds.suspicious, thresholds.restrict)
-            .unwrap();
-
-        info!(
-            message = "combine decision",
-            accept = decision.accept,
-            restrict = decision.restrict,
-            unknown = decision.unknown,
-            score = decision.pignistic().restrict,
-            outcome = match outcome {
-                bulwark_wasm_sdk::Outcome::Trusted => "trusted",
-                bulwark_wasm_sdk::Outcome::Accepted => "accepted",
-                bulwark_wasm_sdk::Outcome::Suspected => "suspected",
-                bulwark_wasm_sdk::Outcome::Restricted => "restricted",
-            },
-            observe_only = thresholds.observe_only,
-            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
-            tags = decision_components
-                .tags
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
-                .to_vec()
-                .join(","),
+        let thresholds = settings.thresholds;
+        let outcome = Self::log_combined_decision(&decision_components, thresholds);
+        let metadata = Self::decision_metadata(
+            &decision_components,
+            outcome,
+            thresholds,
+            &Self::plugin_references(&plugin_instances),
         );
 
         match outcome {
@@ -735,7 +1901,8 @@ impl BulwarkProcessor {
             // suspected requests are monitored but not rejected
             | bulwark_wasm_sdk::Outcome::Suspected => {
                 info!(message = "process response", status = u16::from(response_status));
-                let result = Self::allow_response(&sender, &decision_components).await;
+                let result =
+                    Self::allow_response(&mut sender, &decision_components, metadata).await;
                 // TODO: must perform proper error handling on sender results, sending can fail
                 if let Err(err) = result {
                     debug!(message = format!("send error: {}", err));
@@ -744,14 +1911,16 @@ impl BulwarkProcessor {
             bulwark_wasm_sdk::Outcome::Restricted => {
                 if !thresholds.observe_only {
                     info!(message = "process response", status = 403);
-                    let result = Self::block_response(&sender, &decision_components).await;
+                    let result =
+                        Self::block_response(&mut sender, &decision_components, metadata).await;
                     // TODO: must perform proper error handling on sender results, sending can fail
                     if let Err(err) = result {
                         debug!(message = format!("send error: {}", err));
                     }
                 } else {
                     info!(message = "process response", status = u16::from(response_status));
-                    let result = Self::allow_response(&sender, &decision_components).await;
+                    let result =
+                        Self::allow_response(&mut sender, &decision_components, metadata).await;
                     // TODO: must perform proper error handling on sender results, sending can fail
                     if let Err(err) = result {
                         debug!(message = format!("send error: {}", err));
@@ -760,39 +1929,248 @@ impl BulwarkProcessor {
             }
         }
 
+        settings.outcomes.record(outcome, false);
         Self::handle_decision_feedback(
             decision_components,
             outcome,
             plugin_instances,
-            timeout_duration,
+            settings.timeout_duration,
         );
     }
 
+    /// Starts the feedback phase for each plugin, returning the handles of the tasks executing it.
+    ///
+    /// Feedback runs after the response has been sent, so callers are free to let the tasks run detached.
     fn handle_decision_feedback(
         decision_components: DecisionComponents,
         outcome: bulwark_wasm_sdk::Outcome,
-        plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
+        plugin_instances: Vec<RoutedInstance>,
         timeout_duration: std::time::Duration,
-    ) {
+    ) -> Vec<JoinHandle<()>> {
+        let decision_components = Arc::new(decision_components);
+        let mut feedback_tasks = Vec::with_capacity(plugin_instances.len());
         for plugin_instance in plugin_instances {
             let response_phase_child_span = tracing::info_span!("execute on_decision_feedback",);
-            {
-                // Make sure the plugin instance knows about the final combined decision
-                let mut plugin_instance = plugin_instance.lock().unwrap();
-                plugin_instance.record_combined_decision(&decision_components, outcome);
-            }
-            tokio::spawn(
-                timeout(timeout_duration, async move {
-                    Self::execute_on_decision_feedback(plugin_instance.clone()).ok();
-                })
+            // The response has already been sent by now, so feedback isn't bound by the request's deadline.
+            let deadline = Self::plugin_deadline(&plugin_instance.plugin, timeout_duration, None);
+            let decision_components = decision_components.clone();
+            feedback_tasks.push(tokio::spawn(
+                async move {
+                    Self::execute_blocking(deadline, move || {
+                        let mut plugin_instance =
+                            Self::lock_instance(&plugin_instance.instance, deadline)?;
+                        // Make sure the plugin instance knows about the final combined decision
+                        plugin_instance.record_combined_decision(&decision_components, outcome);
+                        Self::execute_on_decision_feedback(&mut plugin_instance)
+                    })
+                    .await
+                    .ok();
+                }
                 .instrument(response_phase_child_span.or_current()),
-            );
+            ));
+        }
+        feedback_tasks
+    }
+
+    fn handle_check_decision(
+        decision_components: DecisionComponents,
+        settings: ExecutionSettings,
+        plugin_instances: Vec<RoutedInstance>,
+        request_id_echo: Option<UpstreamHeaderMutation>,
+    ) -> Result<CheckResponse, SfvError> {
+        let thresholds = settings.thresholds;
+        let outcome = Self::log_combined_decision(&decision_components, thresholds);
+        let metadata = Self::decision_metadata(
+            &decision_components,
+            outcome,
+            thresholds,
+            &Self::plugin_references(&plugin_instances),
+        );
+        let mut upstream_header_mutations = Self::upstream_header_mutations(&plugin_instances);
+        // The request ID is applied last so that it can't be overwritten by a plugin.
+        upstream_header_mutations.extend(request_id_echo);
+
+        let result = match outcome {
+            bulwark_wasm_sdk::Outcome::Restricted if !thresholds.observe_only => {
+                info!(message = "process response", status = 403);
+                Ok(Self::deny_check(&decision_components, metadata))
+            }
+            _ => Self::allow_check(&decision_components, &upstream_header_mutations, metadata),
+        };
+
+        // There's no response phase, so feedback always happens immediately.
+        settings.outcomes.record(outcome, false);
+        Self::handle_decision_feedback(
+            decision_components,
+            outcome,
+            plugin_instances,
+            settings.timeout_duration,
+        );
+        result
+    }
+
+    /// Records the combined decision for the enforcing plugins, returning its outcome.
+    fn log_combined_decision(
+        decision_components: &DecisionComponents,
+        thresholds: Thresholds,
+    ) -> bulwark_wasm_sdk::Outcome {
+        let decision = decision_components.decision;
+        let outcome = decision
+            .outcome(thresholds.trust, thresholds.suspicious, thresholds.restrict)
+            .unwrap();
+
+        info!(
+            message = "combine decision",
+            accept = decision.accept,
+            restrict = decision.restrict,
+            unknown = decision.unknown,
+            score = decision.pignistic().restrict,
+            outcome = Self::outcome_name(outcome),
+            observe_only = thresholds.observe_only,
+            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
+            tags = decision_components
+                .tags
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<&str>>()
+                .to_vec()
+                .join(","),
+        );
+
+        outcome
+    }
+
+    fn outcome_name(outcome: bulwark_wasm_sdk::Outcome) -> &'static str {
+        match outcome {
+            bulwark_wasm_sdk::Outcome::Trusted => "trusted",
+            bulwark_wasm_sdk::Outcome::Accepted => "accepted",
+            bulwark_wasm_sdk::Outcome::Suspected => "suspected",
+            bulwark_wasm_sdk::Outcome::Restricted => "restricted",
+        }
+    }
+
+    /// Returns the references of the plugins that contributed to a combined decision.
+    fn plugin_references(plugin_instances: &[RoutedInstance]) -> Vec<String> {
+        plugin_instances
+            .iter()
+            .map(|plugin_instance| plugin_instance.plugin.reference().to_string())
+            .collect()
+    }
+
+    /// Builds the dynamic metadata that Envoy will expose to access logs and subsequent filters.
+    ///
+    /// The decision details are placed under the [`DYNAMIC_METADATA_NAMESPACE`] namespace so that they can be
+    /// consumed without parsing the `Bulwark-Decision` header.
+    fn decision_metadata(
+        decision_components: &DecisionComponents,
+        outcome: bulwark_wasm_sdk::Outcome,
+        thresholds: Thresholds,
+        plugin_references: &[String],
+    ) -> Struct {
+        let decision = decision_components.decision;
+        let decision_value: Struct = [
+            ("accept".to_string(), decision.accept.into()),
+            ("restrict".to_string(), decision.restrict.into()),
+            ("unknown".to_string(), decision.unknown.into()),
+        ]
+        .into_iter()
+        .collect();
+        let details: Struct = [
+            ("decision".to_string(), decision_value.into()),
+            ("score".to_string(), decision.pignistic().restrict.into()),
+            ("outcome".to_string(), Self::outcome_name(outcome).into()),
+            (
+                "tags".to_string(),
+                Self::metadata_list(&decision_components.tags),
+            ),
+            (
+                "plugins".to_string(),
+                Self::metadata_list(plugin_references),
+            ),
+            ("observe_only".to_string(), thresholds.observe_only.into()),
+        ]
+        .into_iter()
+        .collect();
+        [(DYNAMIC_METADATA_NAMESPACE.to_string(), details.into())]
+            .into_iter()
+            .collect()
+    }
+
+    /// Builds a dynamic metadata list value out of a list of strings.
+    fn metadata_list(values: &[String]) -> MetadataValue {
+        values
+            .iter()
+            .cloned()
+            .map(MetadataValue::from)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn allow_check(
+        decision_components: &DecisionComponents,
+        upstream_header_mutations: &[UpstreamHeaderMutation],
+        metadata: Struct,
+    ) -> Result<CheckResponse, SfvError> {
+        // Send back a response that changes the request header for the HTTP target.
+        let mut headers = vec![Self::header_value_option(
+            "Bulwark-Decision",
+            &serialize_decision_sfv(decision_components.decision)
+                .map_err(|err| SfvError::Serialization(err.to_string()))?,
+        )];
+        if !decision_components.tags.is_empty() {
+            headers.push(Self::header_value_option(
+                "Bulwark-Tags",
+                &serialize_tags_sfv(decision_components.tags.clone())
+                    .map_err(|err| SfvError::Serialization(err.to_string()))?,
+            ));
+        }
+        let (upstream_headers, headers_to_remove) =
+            Self::upstream_header_options(upstream_header_mutations);
+        headers.extend(upstream_headers);
+        Ok(CheckResponse {
+            status: Some(rpc::Status {
+                code: Code::Ok as i32,
+                ..Default::default()
+            }),
+            http_response: Some(check_response::HttpResponse::OkResponse(OkHttpResponse {
+                headers,
+                headers_to_remove,
+                ..Default::default()
+            })),
+            dynamic_metadata: Some(metadata),
+        })
+    }
+
+    fn deny_check(
+        // TODO: this will be used in the future
+        _decision_components: &DecisionComponents,
+        metadata: Struct,
+    ) -> CheckResponse {
+        // Send back a response indicating the request has been blocked.
+        CheckResponse {
+            status: Some(rpc::Status {
+                code: Code::PermissionDenied as i32,
+                // TODO: add decision debug
+                message: "blocked by bulwark".to_string(),
+                ..Default::default()
+            }),
+            http_response: Some(check_response::HttpResponse::DeniedResponse(
+                DeniedHttpResponse {
+                    status: Some(HttpStatus { code: 403 }),
+                    headers: vec![],
+                    // TODO: better default response + customizability
+                    body: "Access Denied".to_string(),
+                },
+            )),
+            dynamic_metadata: Some(metadata),
         }
     }
 
     async fn allow_request(
-        mut sender: &UnboundedSender<Result<ProcessingResponse, Status>>,
+        sender: &mut ResponseSender,
         decision_components: &DecisionComponents,
+        upstream_header_mutations: &[UpstreamHeaderMutation],
+        metadata: Struct,
     ) -> Result<(), ProcessingMessageError> {
         // Send back a response that changes the request header for the HTTP target.
         let mut req_headers_cr = CommonResponse::default();
@@ -810,21 +2188,32 @@ impl BulwarkProcessor {
                     .map_err(|err| SfvError::Serialization(err.to_string()))?,
             );
         }
+        if !upstream_header_mutations.is_empty() {
+            let (set_headers, remove_headers) =
+                Self::upstream_header_options(upstream_header_mutations);
+            let hm = req_headers_cr
+                .header_mutation
+                .get_or_insert_with(HeaderMutation::default);
+            hm.set_headers.extend(set_headers);
+            hm.remove_headers.extend(remove_headers);
+        }
         let req_headers_resp = ProcessingResponse {
             response: Some(processing_response::Response::RequestHeaders(
                 HeadersResponse {
                     response: Some(req_headers_cr),
                 },
             )),
+            dynamic_metadata: Some(metadata),
             ..Default::default()
         };
         Ok(sender.send(Ok(req_headers_resp)).await?)
     }
 
     async fn block_request(
-        mut sender: &UnboundedSender<Result<ProcessingResponse, Status>>,
+        sender: &mut ResponseSender,
         // TODO: this will be used in the future
         _decision_components: &DecisionComponents,
+        metadata: Struct,
     ) -> Result<(), ProcessingMessageError> {
         // Send back a response indicating the request has been blocked.
         let req_headers_resp = ProcessingResponse {
@@ -839,29 +2228,33 @@ impl BulwarkProcessor {
                     grpc_status: None,
                 },
             )),
+            dynamic_metadata: Some(metadata),
             ..Default::default()
         };
         Ok(sender.send(Ok(req_headers_resp)).await?)
     }
 
     async fn allow_response(
-        mut sender: &UnboundedSender<Result<ProcessingResponse, Status>>,
+        sender: &mut ResponseSender,
         // TODO: this will be used in the future
         _decision_components: &DecisionComponents,
+        metadata: Struct,
     ) -> Result<(), ProcessingMessageError> {
         let resp_headers_resp = ProcessingResponse {
             response: Some(processing_response::Response::RequestHeaders(
                 HeadersResponse { response: None },
             )),
+            dynamic_metadata: Some(metadata),
             ..Default::default()
         };
         Ok(sender.send(Ok(resp_headers_resp)).await?)
     }
 
     async fn block_response(
-        mut sender: &UnboundedSender<Result<ProcessingResponse, Status>>,
+        sender: &mut ResponseSender,
         // TODO: this will be used in the future
         _decision_components: &DecisionComponents,
+        metadata: Struct,
     ) -> Result<(), ProcessingMessageError> {
         // Send back a response indicating the request has been blocked.
         let resp_headers_resp = ProcessingResponse {
@@ -876,6 +2269,7 @@ impl BulwarkProcessor {
                     grpc_status: None,
                 },
             )),
+            dynamic_metadata: Some(metadata),
             ..Default::default()
         };
         Ok(sender.send(Ok(resp_headers_resp)).await?)
@@ -916,14 +2310,63 @@ impl BulwarkProcessor {
         }
     }
 
-    fn add_set_header(cr: &mut CommonResponse, key: &str, value: &str) {
-        let new_header = HeaderValueOption {
+    fn header_value_option(key: &str, value: &str) -> HeaderValueOption {
+        HeaderValueOption {
             header: Some(HeaderValue {
                 key: key.into(),
                 value: value.into(),
             }),
             ..Default::default()
-        };
+        }
+    }
+
+    /// Collects the upstream request header changes made by the enforcing plugins, in plugin order.
+    ///
+    /// Plugins whose instance lock was poisoned by a panic, or is still held by an execution that timed out, are
+    /// skipped since their changes can't be trusted. The lock is never waited on because this runs on an executor
+    /// thread.
+    fn upstream_header_mutations(
+        plugin_instances: &[RoutedInstance],
+    ) -> Vec<UpstreamHeaderMutation> {
+        plugin_instances
+            .iter()
+            .filter_map(|plugin_instance| plugin_instance.instance.try_lock().ok())
+            .flat_map(|plugin_instance| plugin_instance.upstream_header_mutations())
+            .collect()
+    }
+
+    /// Converts upstream request header changes into the header values to set and the header names to remove.
+    fn upstream_header_options(
+        upstream_header_mutations: &[UpstreamHeaderMutation],
+    ) -> (Vec<HeaderValueOption>, Vec<String>) {
+        let mut set_headers = vec![];
+        let mut remove_headers = vec![];
+        for mutation in upstream_header_mutations {
+            let (name, value, append) = match mutation {
+                UpstreamHeaderMutation::Add { name, value } => (name, value, true),
+                UpstreamHeaderMutation::Set { name, value } => (name, value, false),
+                UpstreamHeaderMutation::Remove { name } => {
+                    remove_headers.push(name.clone());
+                    continue;
+                }
+            };
+            // Envoy header values are strings, so anything else is dropped rather than mangled.
+            match str::from_utf8(value) {
+                Ok(value) => set_headers.push(HeaderValueOption {
+                    append: Some(append),
+                    ..Self::header_value_option(name, value)
+                }),
+                Err(_) => warn!(
+                    message = "invalid upstream header value",
+                    header = name.as_str()
+                ),
+            }
+        }
+        (set_headers, remove_headers)
+    }
+
+    fn add_set_header(cr: &mut CommonResponse, key: &str, value: &str) {
+        let new_header = Self::header_value_option(key, value);
         match &mut cr.header_mutation {
             Some(hm) => hm.set_headers.push(new_header),
             None => {
@@ -934,34 +2377,82 @@ impl BulwarkProcessor {
         }
     }
 
-    fn parse_forwarded_ip(forwarded: &str, hops: usize) -> Option<IpAddr> {
+    /// Resolves the client IP from the forwarding headers, preferring the `Forwarded` header if it's present.
+    fn resolve_forwarded_ip(
+        forwarded: Option<&str>,
+        x_forwarded_for: Option<&str>,
+        hops: usize,
+        trusted_proxies: &[IpNet],
+    ) -> Option<IpAddr> {
+        if let Some(forwarded) = forwarded {
+            Self::parse_forwarded_ip(forwarded, hops, trusted_proxies)
+        } else if let Some(forwarded) = x_forwarded_for {
+            Self::parse_x_forwarded_for_ip(forwarded, hops, trusted_proxies)
+        } else {
+            None
+        }
+    }
+
+    /// Extracts the IP address of a peer, if the peer is connected via a socket.
+    fn peer_ip(peer: &attribute_context::Peer) -> Option<IpAddr> {
+        match peer.address.as_ref()?.address.as_ref()? {
+            address::Address::SocketAddress(socket_address) => socket_address.address.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn parse_forwarded_ip(
+        forwarded: &str,
+        hops: usize,
+        trusted_proxies: &[IpNet],
+    ) -> Option<IpAddr> {
         let value = ForwardedHeaderValue::from_forwarded(forwarded).ok();
-        value.and_then(|fhv| {
-            if hops > fhv.len() {
-                None
-            } else {
-                let item = fhv.iter().nth(fhv.len() - hops);
-                item.and_then(|fs| fs.forwarded_for_ip())
-            }
-        })
+        value.and_then(|fhv| Self::select_forwarded_ip(&fhv, hops, trusted_proxies))
     }
 
-    fn parse_x_forwarded_for_ip(forwarded: &str, hops: usize) -> Option<IpAddr> {
+    fn parse_x_forwarded_for_ip(
+        forwarded: &str,
+        hops: usize,
+        trusted_proxies: &[IpNet],
+    ) -> Option<IpAddr> {
         let value = ForwardedHeaderValue::from_x_forwarded_for(forwarded).ok();
-        value.and_then(|fhv| {
-            if hops > fhv.len() {
-                None
-            } else {
-                let item = fhv.iter().nth(fhv.len() - hops);
-                item.and_then(|fs| fs.forwarded_for_ip())
+        value.and_then(|fhv| Self::select_forwarded_ip(&fhv, hops, trusted_proxies))
+    }
+
+    /// Selects the client IP from a parsed forwarding header.
+    ///
+    /// If any trusted proxies are configured, the addresses are walked from the right, skipping trusted addresses,
+    /// falling back to the leftmost address if every address is trusted, since that's the furthest hop from Envoy and
+    /// the closest one to the client. Otherwise the address is selected by counting `hops` from the right.
+    fn select_forwarded_ip(
+        fhv: &ForwardedHeaderValue,
+        hops: usize,
+        trusted_proxies: &[IpNet],
+    ) -> Option<IpAddr> {
+        if !trusted_proxies.is_empty() {
+            let stanzas: Vec<_> = fhv.iter().collect();
+            for stanza in stanzas.iter().rev() {
+                // An address that can't be parsed can't be trusted, and it can't be skipped over either.
+                let ip_addr = stanza.forwarded_for_ip()?;
+                if !trusted_proxies.iter().any(|net| net.contains(&ip_addr)) {
+                    return Some(ip_addr);
+                }
             }
-        })
+            stanzas.first().and_then(|fs| fs.forwarded_for_ip())
+        } else if hops > fhv.len() {
+            None
+        } else {
+            let item = fhv.iter().nth(fhv.len() - hops);
+            item.and_then(|fs| fs.forwarded_for_ip())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicBool;
 
     #[test]
     fn test_parse_forwarded() -> Result<(), Box<dyn std::error::Error>> {
@@ -998,7 +2489,7 @@ mod tests {
         ];
 
         for (forwarded, hops, expected) in test_cases {
-            let parsed = BulwarkProcessor::parse_forwarded_ip(forwarded, hops);
+            let parsed = BulwarkProcessor::parse_forwarded_ip(forwarded, hops, &[]);
             assert_eq!(parsed, expected);
         }
 
@@ -1036,10 +2527,562 @@ mod tests {
         ];
 
         for (forwarded, hops, expected) in test_cases {
-            let parsed = BulwarkProcessor::parse_x_forwarded_for_ip(forwarded, hops);
+            let parsed = BulwarkProcessor::parse_x_forwarded_for_ip(forwarded, hops, &[]);
+            assert_eq!(parsed, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_forwarded_trusted_proxies() -> Result<(), Box<dyn std::error::Error>> {
+        let trusted_proxies = [
+            "10.0.0.0/8".parse::<IpNet>()?,
+            "203.0.113.0/24".parse::<IpNet>()?,
+        ];
+        let test_cases = [
+            ("", None),
+            ("bogus", None),
+            (
+                "for=192.0.2.43",
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=192.0.2.43,for=203.0.113.60",
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=198.51.100.17,for=192.0.2.43,for=203.0.113.60,for=10.1.2.3",
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=10.4.5.6,for=203.0.113.60,for=10.1.2.3",
+                Some("10.4.5.6".parse::<IpAddr>().unwrap()),
+            ),
+            ("for=192.0.2.43,for=unknown,for=10.1.2.3", None),
+        ];
+
+        for (forwarded, expected) in test_cases {
+            let parsed = BulwarkProcessor::parse_forwarded_ip(forwarded, 0, &trusted_proxies);
             assert_eq!(parsed, expected);
         }
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_x_forwarded_for_trusted_proxies() -> Result<(), Box<dyn std::error::Error>> {
+        let trusted_proxies = [
+            "10.0.0.0/8".parse::<IpNet>()?,
+            "203.0.113.0/24".parse::<IpNet>()?,
+        ];
+        let test_cases = [
+            ("", None),
+            ("bogus", None),
+            ("192.0.2.43", Some("192.0.2.43".parse::<IpAddr>().unwrap())),
+            (
+                "192.0.2.43, 203.0.113.60",
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "198.51.100.17, 192.0.2.43, 203.0.113.60, 10.1.2.3",
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "10.4.5.6, 203.0.113.60, 10.1.2.3",
+                Some("10.4.5.6".parse::<IpAddr>().unwrap()),
+            ),
+        ];
+
+        for (forwarded, expected) in test_cases {
+            // Hop counting is ignored when trusted proxies are configured.
+            let parsed = BulwarkProcessor::parse_x_forwarded_for_ip(forwarded, 3, &trusted_proxies);
+            assert_eq!(parsed, expected);
+        }
+
+        // When every hop is trusted, the client is the leftmost address rather than the proxy closest to Envoy.
+        let all_trusted = ["10.0.0.0/8".parse::<IpNet>()?];
+        assert_eq!(
+            BulwarkProcessor::resolve_forwarded_ip(
+                None,
+                Some("10.9.8.7, 10.4.5.6, 10.1.2.3"),
+                1,
+                &all_trusted
+            ),
+            Some("10.9.8.7".parse::<IpAddr>()?)
+        );
+        assert_eq!(
+            BulwarkProcessor::resolve_forwarded_ip(
+                Some("for=10.9.8.7,for=10.4.5.6,for=10.1.2.3"),
+                None,
+                1,
+                &all_trusted
+            ),
+            Some("10.9.8.7".parse::<IpAddr>()?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_request_id() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            BulwarkProcessor::resolve_request_id(Some("f81d4fae-7dec-11d0-a765-00a0c91e6bf6")),
+            RequestId("f81d4fae-7dec-11d0-a765-00a0c91e6bf6".to_string())
+        );
+
+        // Missing, empty, oversized, and non-printable values are all replaced with a generated UUID.
+        let oversized = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for header_value in [None, Some(""), Some(oversized.as_str()), Some("abc\ndef")] {
+            let request_id = BulwarkProcessor::resolve_request_id(header_value);
+            Uuid::parse_str(&request_id.0)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_loggable_header() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("user-agent", http::HeaderValue::from_static("curl/7.88.1"));
+
+        assert_eq!(
+            BulwarkProcessor::loggable_header(&Redaction::default(), &headers, "user-agent"),
+            Some("curl/7.88.1".to_string())
+        );
+        let redaction = Redaction {
+            headers: vec!["user-agent".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            BulwarkProcessor::loggable_header(&redaction, &headers, "user-agent"),
+            Some(bulwark_config::REDACTION_MASK.to_string())
+        );
+        assert_eq!(
+            BulwarkProcessor::loggable_header(&redaction, &headers, "referer"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_blocking() {
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert_eq!(
+            BulwarkProcessor::execute_blocking(deadline, || 42)
+                .await
+                .ok(),
+            Some(42)
+        );
+
+        // A stuck execution is abandoned shortly after its deadline rather than being waited on.
+        let lock = Arc::new(Mutex::new(()));
+        let held_lock = lock.clone();
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(10);
+        let result = BulwarkProcessor::execute_blocking(deadline, move || {
+            let _guard = held_lock.lock().unwrap();
+            std::thread::sleep(Duration::from_millis(500));
+        })
+        .await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_millis(250));
+        // The abandoned execution still holds its lock, so it has to be detected without blocking.
+        assert!(matches!(lock.try_lock(), Err(TryLockError::WouldBlock)));
+
+        // An execution whose deadline has already passed never runs.
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran_clone = ran.clone();
+        let result = BulwarkProcessor::execute_blocking(Instant::now(), move || {
+            ran_clone.store(true, Ordering::SeqCst)
+        })
+        .await;
+        assert!(result.is_err());
+        tokio::time::sleep(TIMEOUT_GRACE_PERIOD).await;
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_resume_deadline() {
+        let mut settings = ExecutionSettings {
+            thresholds: Thresholds::default(),
+            timeout_duration: Duration::from_millis(DEFAULT_PLUGIN_TIMEOUT),
+            deadline: Some(Instant::now() + Duration::from_millis(100)),
+            failure_policy: FailurePolicy::default(),
+            combination: CombinationStrategy::Murphy,
+            combine_phases: false,
+            outcomes: Arc::new(OutcomeCounters::default()),
+        };
+        let request_phase_finished = Instant::now();
+        // A slow interior service takes longer to respond than the whole deadline.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        settings.resume_deadline(request_phase_finished);
+
+        // The response phase still gets the time the request phase left over.
+        let deadline = settings.deadline.unwrap();
+        assert!(deadline > Instant::now() + Duration::from_millis(50));
+        assert_eq!(
+            BulwarkProcessor::execute_blocking(deadline, || 42)
+                .await
+                .ok(),
+            Some(42)
+        );
+
+        // Without a deadline, there's nothing to resume.
+        settings.deadline = None;
+        settings.resume_deadline(request_phase_finished);
+        assert_eq!(settings.deadline, None);
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let limit = ConcurrencyLimit::new(Some(4));
+        let first = limit.try_acquire(3);
+        assert!(first.is_some());
+        assert_eq!(limit.in_flight(), 3);
+        assert!(limit.try_acquire(2).is_none());
+        let second = limit.try_acquire(1);
+        assert!(second.is_some());
+        assert_eq!(limit.in_flight(), 4);
+
+        drop(first);
+        assert_eq!(limit.in_flight(), 1);
+        assert!(limit.try_acquire(3).is_some());
+
+        // Without a limit, work is still counted but never shed.
+        let unlimited = ConcurrencyLimit::new(None);
+        let permit = unlimited.try_acquire(1024);
+        assert!(permit.is_some());
+        assert_eq!(unlimited.in_flight(), 1024);
+    }
+
+    #[test]
+    fn test_outcome_counters() {
+        let outcomes = OutcomeCounters::default();
+        outcomes.record(Outcome::Restricted, false);
+        outcomes.record(Outcome::Accepted, false);
+        outcomes.record(Outcome::Restricted, true);
+        outcomes.record(Outcome::Restricted, true);
+
+        let counts = |counters: &[AtomicU64; OUTCOMES.len()]| -> Vec<u64> {
+            counters
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect()
+        };
+        assert_eq!(counts(&outcomes.enforcing), vec![0, 1, 0, 1]);
+        assert_eq!(counts(&outcomes.shadow), vec![0, 0, 0, 2]);
+    }
+
+    fn shed_limits(load_shedding: LoadSheddingPolicy) -> ConcurrencyLimits {
+        ConcurrencyLimits {
+            requests: ConcurrencyLimit::new(None),
+            plugins: ConcurrencyLimit::new(None),
+            load_shedding,
+            shed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shed_request_phase() {
+        use futures::StreamExt;
+
+        // Open allows the request through, tagged as shed.
+        let (mut sender, mut receiver) = futures::channel::mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        let allowed = BulwarkProcessor::shed_request_phase(
+            &mut sender,
+            LoadSheddingPolicy::Open,
+            Thresholds::default(),
+            None,
+        )
+        .await;
+        let (decision_components, _) = allowed.expect("open load shedding allows the request");
+        assert_eq!(decision_components.tags, vec![LOAD_SHED_TAG.to_string()]);
+        let response = receiver.next().await.unwrap().unwrap();
+        match response.response {
+            Some(processing_response::Response::RequestHeaders(HeadersResponse {
+                response: Some(common_response),
+            })) => {
+                let set_headers = common_response.header_mutation.unwrap().set_headers;
+                assert!(set_headers.iter().any(|option| {
+                    let header = option.header.as_ref().unwrap();
+                    header.key == "Bulwark-Tags" && header.value.contains(LOAD_SHED_TAG)
+                }));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // Closed blocks the request.
+        let (mut sender, mut receiver) = futures::channel::mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        let allowed = BulwarkProcessor::shed_request_phase(
+            &mut sender,
+            LoadSheddingPolicy::Closed,
+            Thresholds::default(),
+            None,
+        )
+        .await;
+        assert!(allowed.is_none());
+        let response = receiver.next().await.unwrap().unwrap();
+        match response.response {
+            Some(processing_response::Response::ImmediateResponse(immediate_response)) => {
+                assert_eq!(immediate_response.status, Some(HttpStatus { code: 403 }));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // Reject fails the stream.
+        let (mut sender, mut receiver) = futures::channel::mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        let allowed = BulwarkProcessor::shed_request_phase(
+            &mut sender,
+            LoadSheddingPolicy::Reject,
+            Thresholds::default(),
+            None,
+        )
+        .await;
+        assert!(allowed.is_none());
+        let status = receiver.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn test_shed_check() {
+        let limits = shed_limits(LoadSheddingPolicy::Open);
+        let response =
+            BulwarkProcessor::shed_check(&limits, "requests", Thresholds::default(), None)
+                .unwrap()
+                .into_inner();
+        assert_eq!(response.status.unwrap().code, Code::Ok as i32);
+        assert!(matches!(
+            response.http_response,
+            Some(check_response::HttpResponse::OkResponse(_))
+        ));
+        // Every shed request is counted, whatever the policy does with it.
+        assert_eq!(limits.shed.load(Ordering::Relaxed), 1);
+
+        let response = BulwarkProcessor::shed_check(
+            &shed_limits(LoadSheddingPolicy::Closed),
+            "requests",
+            Thresholds::default(),
+            None,
+        )
+        .unwrap()
+        .into_inner();
+        assert_eq!(response.status.unwrap().code, Code::PermissionDenied as i32);
+        assert!(matches!(
+            response.http_response,
+            Some(check_response::HttpResponse::DeniedResponse(_))
+        ));
+
+        let status = BulwarkProcessor::shed_check(
+            &shed_limits(LoadSheddingPolicy::Reject),
+            "plugins",
+            Thresholds::default(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn test_upstream_header_options() {
+        let (set_headers, remove_headers) = BulwarkProcessor::upstream_header_options(&[
+            UpstreamHeaderMutation::Add {
+                name: "x-client-risk".to_string(),
+                value: b"0.25".to_vec(),
+            },
+            UpstreamHeaderMutation::Set {
+                name: "x-user-id".to_string(),
+                value: b"1234".to_vec(),
+            },
+            UpstreamHeaderMutation::Set {
+                name: "x-invalid".to_string(),
+                value: vec![0xff, 0xfe],
+            },
+            UpstreamHeaderMutation::Remove {
+                name: "x-forwarded-user".to_string(),
+            },
+        ]);
+
+        assert_eq!(
+            set_headers
+                .iter()
+                .map(|option| {
+                    let header = option.header.as_ref().unwrap();
+                    (header.key.as_str(), header.value.as_str(), option.append)
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                ("x-client-risk", "0.25", Some(true)),
+                ("x-user-id", "1234", Some(false)),
+            ]
+        );
+        assert_eq!(remove_headers, vec!["x-forwarded-user"]);
+    }
+
+    fn assert_decision(decision: Decision, accept: f64, restrict: f64, unknown: f64) {
+        assert!((decision.accept - accept).abs() < 1e-9, "{:?}", decision);
+        assert!(
+            (decision.restrict - restrict).abs() < 1e-9,
+            "{:?}",
+            decision
+        );
+        assert!((decision.unknown - unknown).abs() < 1e-9, "{:?}", decision);
+    }
+
+    fn result_context(reference: &str, failure_policy: FailurePolicy) -> PluginResultContext {
+        PluginResultContext {
+            reference: reference.to_string(),
+            weight: 1.0,
+            failure_policy,
+        }
+    }
+
+    fn decision_components(
+        accept: f64,
+        restrict: f64,
+        unknown: f64,
+        tag: &str,
+    ) -> DecisionComponents {
+        DecisionComponents {
+            decision: Decision {
+                accept,
+                restrict,
+                unknown,
+            },
+            tags: vec![tag.to_string()],
+        }
+    }
+
+    fn sorted_tags(decision_components: &DecisionComponents) -> Vec<&str> {
+        let mut tags: Vec<&str> = decision_components
+            .tags
+            .iter()
+            .map(String::as_str)
+            .collect();
+        tags.sort_unstable();
+        tags
+    }
+
+    #[test]
+    fn test_combine_plugin_results() {
+        // Without any failures, decisions are combined as usual and no failure tag is added.
+        let combined = BulwarkProcessor::combine_plugin_results(
+            vec![result_context("accepting", FailurePolicy::Open)],
+            vec![Ok(decision_components(0.5, 0.0, 0.5, "accepting"))],
+            CombinationStrategy::Conjunctive,
+            false,
+        );
+        assert_decision(combined.decision, 0.5, 0.0, 0.5);
+        assert_eq!(sorted_tags(&combined), vec!["accepting"]);
+
+        // Failing open contributes a vacuous decision, which leaves the other decisions unchanged.
+        let combined = BulwarkProcessor::combine_plugin_results(
+            vec![
+                result_context("accepting", FailurePolicy::Open),
+                result_context("failing", FailurePolicy::Open),
+            ],
+            vec![
+                Ok(decision_components(0.5, 0.0, 0.5, "accepting")),
+                Err(PluginFailure::Timeout),
+            ],
+            CombinationStrategy::Conjunctive,
+            false,
+        );
+        assert_decision(combined.decision, 0.5, 0.0, 0.5);
+        assert_eq!(
+            sorted_tags(&combined),
+            vec!["accepting", PLUGIN_FAILURE_TAG]
+        );
+
+        // Failing closed contributes the configured restrict mass.
+        let combined = BulwarkProcessor::combine_plugin_results(
+            vec![result_context("failing", FailurePolicy::Closed(0.3))],
+            vec![Err(PluginFailure::Error(PluginExecutionError::Poisoned))],
+            CombinationStrategy::Conjunctive,
+            false,
+        );
+        assert_decision(combined.decision, 0.0, 0.3, 0.7);
+        assert_eq!(sorted_tags(&combined), vec![PLUGIN_FAILURE_TAG]);
+
+        // Blocking overrides every other decision.
+        let combined = BulwarkProcessor::combine_plugin_results(
+            vec![
+                result_context("accepting", FailurePolicy::Open),
+                result_context("failing", FailurePolicy::Block),
+            ],
+            vec![
+                Ok(decision_components(0.9, 0.0, 0.1, "accepting")),
+                Err(PluginFailure::Aborted),
+            ],
+            CombinationStrategy::Murphy,
+            false,
+        );
+        assert_decision(combined.decision, 0.0, 1.0, 0.0);
+        assert_eq!(
+            sorted_tags(&combined),
+            vec!["accepting", PLUGIN_FAILURE_TAG]
+        );
+    }
+
+    #[test]
+    fn test_combine_phase_decisions() {
+        let request_decision_components = decision_components(0.5, 0.0, 0.5, "request");
+
+        // Ordinary response phase decisions are combined with the request phase decision.
+        let combined = BulwarkProcessor::combine_phase_decisions(
+            &request_decision_components,
+            decision_components(0.0, 0.0, 1.0, "response"),
+            CombinationStrategy::Conjunctive,
+        );
+        assert_decision(combined.decision, 0.5, 0.0, 0.5);
+        assert_eq!(sorted_tags(&combined), vec!["request", "response"]);
+
+        // A blocking failure in the response phase can't be softened by the request phase.
+        let combined = BulwarkProcessor::combine_phase_decisions(
+            &request_decision_components,
+            decision_components(0.0, 1.0, 0.0, PLUGIN_FAILURE_TAG),
+            CombinationStrategy::Murphy,
+        );
+        assert_decision(combined.decision, 0.0, 1.0, 0.0);
+        assert_eq!(sorted_tags(&combined), vec![PLUGIN_FAILURE_TAG, "request"]);
+    }
+
+    #[test]
+    fn test_decision_metadata() {
+        let decision_components = DecisionComponents {
+            decision: Decision {
+                accept: 0.25,
+                restrict: 0.5,
+                unknown: 0.25,
+            },
+            tags: vec!["evil-bit".to_string()],
+        };
+        let thresholds = Thresholds {
+            observe_only: true,
+            ..Default::default()
+        };
+        let metadata = BulwarkProcessor::decision_metadata(
+            &decision_components,
+            bulwark_wasm_sdk::Outcome::Suspected,
+            thresholds,
+            &["evil_bit".to_string()],
+        );
+
+        assert_eq!(
+            serde_json::to_value(metadata).unwrap(),
+            json!({
+                "bulwark": {
+                    "decision": {
+                        "accept": 0.25,
+                        "restrict": 0.5,
+                        "unknown": 0.25,
+                    },
+                    "score": 0.625,
+                    "outcome": "suspected",
+                    "tags": ["evil-bit"],
+                    "plugins": ["evil_bit"],
+                    "observe_only": true,
+                }
+            })
+        );
+    }
 }
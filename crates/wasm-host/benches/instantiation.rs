@@ -0,0 +1,131 @@
+//! Measures the per-request latency of instantiating a plugin, comparing on-demand instance allocation against
+//! the pooling instance allocator and prestarted instances.
+//!
+//! Run with `cargo bench -p bulwark-wasm-host --bench instantiation`.
+
+use {
+    bulwark_wasm_host::{
+        HttpInfo, MemoryStateBackend, Plugin, PluginEngine, PluginInstance, PrestartedInstances,
+        RequestContext, StateBackend,
+    },
+    std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+};
+
+const ITERATIONS: usize = 2000;
+const WARMUP_ITERATIONS: usize = 100;
+
+fn request() -> Arc<bulwark_wasm_sdk::Request> {
+    Arc::new(
+        http::Request::builder()
+            .method("GET")
+            .uri("/")
+            .version(http::Version::HTTP_11)
+            .body(bulwark_wasm_sdk::BodyChunk {
+                content: vec![],
+                start: 0,
+                size: 0,
+                end_of_stream: true,
+            })
+            .unwrap(),
+    )
+}
+
+/// Instantiates and starts a plugin, the same setup work done for every plugin on every request.
+///
+/// The state backend and HTTP client are shared by every request, so they're created outside of the measurement.
+fn instantiate(
+    plugin: &Arc<Plugin>,
+    state_backend: &Arc<dyn StateBackend>,
+    http_info: &Arc<HttpInfo>,
+) -> Duration {
+    let start = Instant::now();
+    let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
+    let request_context = RequestContext::new(
+        plugin.clone(),
+        state_backend.clone(),
+        http_info.clone(),
+        params,
+        request(),
+    )
+    .unwrap();
+    let mut plugin_instance = PluginInstance::new(plugin.clone(), request_context).unwrap();
+    plugin_instance.start().unwrap();
+    // Include deallocation, since that's when a pooled slot gets reset.
+    drop(plugin_instance);
+    start.elapsed()
+}
+
+/// Takes a prestarted instance, the only setup work left for a request when one is ready.
+///
+/// Replenishing happens outside of the measurement, as it would on a background thread.
+fn instantiate_prestarted(prestarted: &PrestartedInstances) -> Duration {
+    prestarted.replenish();
+    let start = Instant::now();
+    let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
+    let mut plugin_instance = prestarted.instantiate(params, request()).unwrap();
+    plugin_instance.start().unwrap();
+    drop(plugin_instance);
+    start.elapsed()
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let index = ((sorted.len() as f64 - 1.0) * p).round() as usize;
+    sorted[index]
+}
+
+fn load(pool_size: Option<u32>, prestart: Option<u32>) -> Arc<Plugin> {
+    let wasm_bytes = include_bytes!("../tests/bulwark-blank-slate.wasm");
+    let engine = PluginEngine::new(pool_size).unwrap();
+    Arc::new(
+        Plugin::from_bytes(
+            &engine,
+            "bulwark-blank-slate.wasm".to_string(),
+            wasm_bytes,
+            &bulwark_config::Plugin {
+                prestart,
+                ..Default::default()
+            },
+        )
+        .unwrap(),
+    )
+}
+
+fn bench(name: &str, mut sample: impl FnMut() -> Duration) {
+    for _ in 0..WARMUP_ITERATIONS {
+        sample();
+    }
+    let mut samples: Vec<Duration> = (0..ITERATIONS).map(|_| sample()).collect();
+    samples.sort();
+
+    println!(
+        "{:<10} p50: {:>10?}  p90: {:>10?}  p99: {:>10?}  max: {:>10?}",
+        name,
+        percentile(&samples, 0.50),
+        percentile(&samples, 0.90),
+        percentile(&samples, 0.99),
+        samples[samples.len() - 1],
+    );
+}
+
+fn main() {
+    let state_backend: Arc<dyn StateBackend> = Arc::new(MemoryStateBackend::default());
+    let http_info = Arc::new(HttpInfo::default());
+
+    let plugin = load(None, None);
+    bench("on-demand", || {
+        instantiate(&plugin, &state_backend, &http_info)
+    });
+    let plugin = load(Some(16), None);
+    bench("pooled", || {
+        instantiate(&plugin, &state_backend, &http_info)
+    });
+    let prestarted = PrestartedInstances::new(
+        load(Some(16), Some(1)),
+        state_backend.clone(),
+        http_info.clone(),
+    );
+    bench("prestarted", || instantiate_prestarted(&prestarted));
+}
@@ -2,11 +2,13 @@
 #[derive(thiserror::Error, Debug)]
 pub enum PluginLoadError {
     #[error(transparent)]
-    WasiError(#[from] wasi_common::Error),
-    #[error(transparent)]
-    StringArray(#[from] wasi_common::StringArrayError),
+    Wasmtime(#[from] wasmtime::Error),
     #[error(transparent)]
     Resolution(#[from] bulwark_config::ResolutionError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    State(#[from] StateError),
     #[error("at least one resource required")]
     ResourceMissing,
 }
@@ -15,29 +17,81 @@ pub enum PluginLoadError {
 #[derive(thiserror::Error, Debug)]
 pub enum PluginInstantiationError {
     #[error(transparent)]
-    WasiError(#[from] wasi_common::Error),
+    Wasmtime(#[from] wasmtime::Error),
     #[error(transparent)]
-    StringArray(#[from] wasi_common::StringArrayError),
+    ContextInstantiation(#[from] ContextInstantiationError),
+}
+
+/// Returned when an attempt to create and start an instance of a plugin ahead of a request fails.
+#[derive(thiserror::Error, Debug)]
+pub enum PluginPrestartError {
     #[error(transparent)]
     ContextInstantiation(#[from] ContextInstantiationError),
+    #[error(transparent)]
+    PluginInstantiation(#[from] PluginInstantiationError),
+    #[error(transparent)]
+    PluginExecution(#[from] PluginExecutionError),
 }
 
 /// Returned when an attempt to execute a function within a plugin environment fails.
 #[derive(thiserror::Error, Debug)]
 pub enum PluginExecutionError {
     #[error(transparent)]
-    WasiError(#[from] wasi_common::Error),
-    #[error(transparent)]
-    StringArray(#[from] wasi_common::StringArrayError),
+    Wasmtime(#[from] wasmtime::Error),
     #[error("function not implemented '{expected:?}'")]
     NotImplementedError { expected: String },
+    #[error("plugin exceeded its time limit")]
+    TimeLimitExceeded,
+    #[error("plugin exceeded its memory limit of {limit} bytes")]
+    MemoryLimitExceeded { limit: usize },
+    #[error("plugin exceeded its table limit of {limit} elements")]
+    TableLimitExceeded { limit: u32 },
+    #[error("plugin instance is unusable because an earlier execution panicked")]
+    Poisoned,
+    #[error("plugin instance is still running an earlier execution that timed out")]
+    Busy,
 }
 
 /// Returned when attempting to create a [`RequestContext`](crate::RequestContext) fails.
 #[derive(thiserror::Error, Debug)]
 pub enum ContextInstantiationError {
-    #[error(transparent)]
-    StringArray(#[from] wasi_common::StringArrayError),
     #[error(transparent)]
     ConfigSerialization(#[from] bulwark_config::ConfigSerializationError),
 }
+
+/// Returned when a plugin's update to a metric is rejected.
+#[derive(thiserror::Error, Debug)]
+pub enum MetricError {
+    #[error("invalid metric name '{name}'")]
+    InvalidName { name: String },
+    #[error("invalid metric label name '{name}'")]
+    InvalidLabelName { name: String },
+    #[error("metric '{name}' is already recorded as a {kind}")]
+    KindMismatch {
+        name: String,
+        kind: crate::MetricKind,
+    },
+    #[error("metric '{name}' exceeded its limit of {limit} label sets")]
+    CardinalityLimit { name: String, limit: usize },
+    #[error("plugin exceeded its limit of {limit} metrics")]
+    MetricLimit { limit: usize },
+}
+
+/// Returned when a [`StateBackend`](crate::StateBackend) can't be opened or fails to perform an operation.
+#[derive(thiserror::Error, Debug)]
+pub enum StateError {
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    Pool(#[from] r2d2::Error),
+    #[error(transparent)]
+    Sled(#[from] sled::Error),
+    #[error("unsupported remote state scheme '{scheme}'")]
+    UnsupportedScheme { scheme: String },
+    #[error("state value for '{key}' is not an integer or would overflow")]
+    InvalidCounter { key: String },
+    #[error("state value for '{key}' holds the wrong kind of value for the operation")]
+    WrongType { key: String },
+    #[error("remote state store is unavailable")]
+    Unavailable,
+}
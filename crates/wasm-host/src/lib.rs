@@ -1,5 +1,9 @@
 mod errors;
+mod metrics;
 mod plugin;
+mod state;
 
 pub use errors::*;
+pub use metrics::*;
 pub use plugin::*;
+pub use state::*;
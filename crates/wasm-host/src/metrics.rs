@@ -0,0 +1,568 @@
+use {
+    crate::MetricError,
+    std::{
+        collections::BTreeMap,
+        fmt::{self, Write},
+        sync::Mutex,
+    },
+};
+
+/// The prefix of every metric recorded by a plugin.
+///
+/// The full name of a metric is the prefix followed by the name the plugin gave it.
+pub const PLUGIN_METRIC_PREFIX: &str = "bulwark_plugin";
+/// The label identifying the plugin that recorded a series. Plugins may not set it themselves.
+pub const PLUGIN_METRIC_LABEL: &str = "plugin";
+/// The largest number of metrics a single plugin may record. Further metrics are dropped.
+pub const MAX_PLUGIN_METRICS: usize = 100;
+/// The largest number of distinct label sets a single metric may have. Further label sets are dropped.
+pub const MAX_METRIC_LABEL_SETS: usize = 1000;
+/// The labels reserved for the series of histograms and summaries, which plugins may not set on histograms.
+pub const RESERVED_HISTOGRAM_LABELS: [&str; 2] = ["le", "quantile"];
+/// The upper bounds of the buckets observations are sorted into for histograms.
+pub const HISTOGRAM_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The type of a metric, which determines how it may be updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A value that only ever increases.
+    Counter,
+    /// A value that may be set, increased, or decreased.
+    Gauge,
+    /// A distribution of observed values.
+    Histogram,
+}
+
+impl fmt::Display for MetricKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        })
+    }
+}
+
+/// A change to a metric recorded by a plugin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricUpdate {
+    /// Increases a counter.
+    Increment(u64),
+    /// Replaces the value of a gauge.
+    Set(f64),
+    /// Adds to the value of a gauge, which may be negative.
+    Add(f64),
+    /// Records an observation in a histogram.
+    Observe(f64),
+}
+
+impl MetricUpdate {
+    /// The kind of metric this update applies to.
+    fn kind(&self) -> MetricKind {
+        match self {
+            MetricUpdate::Increment(_) => MetricKind::Counter,
+            MetricUpdate::Set(_) | MetricUpdate::Add(_) => MetricKind::Gauge,
+            MetricUpdate::Observe(_) => MetricKind::Histogram,
+        }
+    }
+}
+
+/// The labels identifying one series of a metric, sorted by name.
+type LabelSet = Vec<(String, String)>;
+
+/// The current value of one series of a metric.
+enum SeriesValue {
+    Counter(u64),
+    Gauge(f64),
+    Histogram {
+        /// The number of observations in each of the [`HISTOGRAM_BUCKETS`], not including smaller buckets.
+        buckets: [u64; HISTOGRAM_BUCKETS.len()],
+        sum: f64,
+        count: u64,
+    },
+}
+
+impl SeriesValue {
+    fn new(kind: MetricKind) -> Self {
+        match kind {
+            MetricKind::Counter => SeriesValue::Counter(0),
+            MetricKind::Gauge => SeriesValue::Gauge(0.0),
+            MetricKind::Histogram => SeriesValue::Histogram {
+                buckets: [0; HISTOGRAM_BUCKETS.len()],
+                sum: 0.0,
+                count: 0,
+            },
+        }
+    }
+
+    fn update(&mut self, update: MetricUpdate) {
+        match (self, update) {
+            (SeriesValue::Counter(value), MetricUpdate::Increment(delta)) => {
+                *value = value.saturating_add(delta);
+            }
+            (SeriesValue::Gauge(value), MetricUpdate::Set(new_value)) => *value = new_value,
+            (SeriesValue::Gauge(value), MetricUpdate::Add(delta)) => *value += delta,
+            (
+                SeriesValue::Histogram {
+                    buckets,
+                    sum,
+                    count,
+                },
+                MetricUpdate::Observe(observation),
+            ) => {
+                if let Some(index) = HISTOGRAM_BUCKETS
+                    .iter()
+                    .position(|bound| observation <= *bound)
+                {
+                    buckets[index] += 1;
+                }
+                *sum += observation;
+                *count += 1;
+            }
+            // The kind is checked before a series is updated.
+            _ => unreachable!("metric update does not match the metric kind"),
+        }
+    }
+}
+
+/// A metric recorded by a plugin and all of its series.
+struct Metric {
+    kind: MetricKind,
+    series: BTreeMap<LabelSet, SeriesValue>,
+}
+
+/// Identifies a plugin's metric.
+///
+/// Keys are ordered by name first so that the metrics sharing a name are rendered together.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct MetricKey {
+    /// The name the plugin gave the metric.
+    name: String,
+    /// The reference of the plugin that recorded the metric.
+    plugin: String,
+}
+
+/// The metrics of every plugin along with how many each plugin has recorded.
+#[derive(Default)]
+struct Metrics {
+    metrics: BTreeMap<MetricKey, Metric>,
+    /// The number of metrics each plugin has recorded, keyed by plugin reference.
+    plugin_metric_counts: BTreeMap<String, usize>,
+}
+
+/// The metrics recorded by every plugin, exported in the Prometheus text format.
+///
+/// Each plugin's series carry a [`PLUGIN_METRIC_LABEL`] label with its reference so that plugins can't interfere with
+/// one another. Metrics that share a name form a single family, so plugins must agree on the kind of a shared name.
+#[derive(Default)]
+pub struct MetricsRegistry {
+    metrics: Mutex<Metrics>,
+}
+
+impl MetricsRegistry {
+    /// Applies an update to a plugin's metric, creating the metric if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin` - The reference of the plugin recording the metric.
+    /// * `name` - The name of the metric, without the plugin's namespace.
+    /// * `labels` - The label names and values identifying the series to update.
+    /// * `update` - The change to make to the series.
+    pub fn record(
+        &self,
+        plugin: &str,
+        name: &str,
+        labels: &[(&str, &str)],
+        update: MetricUpdate,
+    ) -> Result<(), MetricError> {
+        if !is_valid_name(name) {
+            return Err(MetricError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+        let mut label_set: LabelSet = Vec::with_capacity(labels.len());
+        for (label_name, label_value) in labels {
+            if !is_valid_name(label_name)
+                || label_name.starts_with("__")
+                || *label_name == PLUGIN_METRIC_LABEL
+                || (update.kind() == MetricKind::Histogram
+                    && RESERVED_HISTOGRAM_LABELS.contains(label_name))
+            {
+                return Err(MetricError::InvalidLabelName {
+                    name: label_name.to_string(),
+                });
+            }
+            label_set.push((label_name.to_string(), label_value.to_string()));
+        }
+        label_set.sort();
+        label_set.dedup_by(|a, b| a.0 == b.0);
+
+        let key = MetricKey {
+            name: name.to_string(),
+            plugin: plugin.to_string(),
+        };
+        let mut guard = self.metrics.lock().unwrap();
+        let Metrics {
+            metrics,
+            plugin_metric_counts,
+        } = &mut *guard;
+        if !metrics.contains_key(&key) {
+            // Any other plugin's metric with the same name is in the same family and must have the same kind.
+            let family_kind = metrics
+                .range(
+                    MetricKey {
+                        name: name.to_string(),
+                        plugin: String::new(),
+                    }..,
+                )
+                .next()
+                .filter(|(other, _)| other.name == name)
+                .map(|(_, metric)| metric.kind);
+            if let Some(kind) = family_kind.filter(|kind| *kind != update.kind()) {
+                return Err(MetricError::KindMismatch {
+                    name: name.to_string(),
+                    kind,
+                });
+            }
+            let count = plugin_metric_counts.entry(plugin.to_string()).or_default();
+            if *count >= MAX_PLUGIN_METRICS {
+                return Err(MetricError::MetricLimit {
+                    limit: MAX_PLUGIN_METRICS,
+                });
+            }
+            *count += 1;
+        }
+        let metric = metrics.entry(key).or_insert_with(|| Metric {
+            kind: update.kind(),
+            series: BTreeMap::new(),
+        });
+        if metric.kind != update.kind() {
+            return Err(MetricError::KindMismatch {
+                name: name.to_string(),
+                kind: metric.kind,
+            });
+        }
+        if !metric.series.contains_key(&label_set) && metric.series.len() >= MAX_METRIC_LABEL_SETS {
+            return Err(MetricError::CardinalityLimit {
+                name: name.to_string(),
+                limit: MAX_METRIC_LABEL_SETS,
+            });
+        }
+        metric
+            .series
+            .entry(label_set)
+            .or_insert_with(|| SeriesValue::new(update.kind()))
+            .update(update);
+        Ok(())
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let guard = self.metrics.lock().unwrap();
+        let mut output = String::new();
+        let mut family: Option<&str> = None;
+        for (key, metric) in guard.metrics.iter() {
+            let name = format!("{}_{}", PLUGIN_METRIC_PREFIX, key.name);
+            // Writing to a String can't fail.
+            if family != Some(key.name.as_str()) {
+                writeln!(output, "# TYPE {} {}", name, metric.kind).unwrap();
+                family = Some(key.name.as_str());
+            }
+            for (labels, value) in metric.series.iter() {
+                let labels = plugin_labels(&key.plugin, labels);
+                match value {
+                    SeriesValue::Counter(value) => {
+                        writeln!(output, "{}{} {}", name, render_labels(&labels, None), value)
+                            .unwrap();
+                    }
+                    SeriesValue::Gauge(value) => {
+                        writeln!(
+                            output,
+                            "{}{} {}",
+                            name,
+                            render_labels(&labels, None),
+                            render_float(*value)
+                        )
+                        .unwrap();
+                    }
+                    SeriesValue::Histogram {
+                        buckets,
+                        sum,
+                        count,
+                    } => {
+                        // Buckets are cumulative in the exposition format.
+                        let mut cumulative = 0;
+                        for (bound, bucket) in HISTOGRAM_BUCKETS.iter().zip(buckets.iter()) {
+                            cumulative += bucket;
+                            writeln!(
+                                output,
+                                "{}_bucket{} {}",
+                                name,
+                                render_labels(&labels, Some(&render_float(*bound))),
+                                cumulative
+                            )
+                            .unwrap();
+                        }
+                        writeln!(
+                            output,
+                            "{}_bucket{} {}",
+                            name,
+                            render_labels(&labels, Some("+Inf")),
+                            count
+                        )
+                        .unwrap();
+                        writeln!(
+                            output,
+                            "{}_sum{} {}",
+                            name,
+                            render_labels(&labels, None),
+                            render_float(*sum)
+                        )
+                        .unwrap();
+                        writeln!(
+                            output,
+                            "{}_count{} {}",
+                            name,
+                            render_labels(&labels, None),
+                            count
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Returns true if the name is a valid Prometheus metric or label name, excluding colons.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Prepends the label identifying the plugin that recorded a series to its label set.
+fn plugin_labels(plugin: &str, labels: &LabelSet) -> LabelSet {
+    let mut plugin_labels = Vec::with_capacity(labels.len() + 1);
+    plugin_labels.push((PLUGIN_METRIC_LABEL.to_string(), plugin.to_string()));
+    plugin_labels.extend(labels.iter().cloned());
+    plugin_labels
+}
+
+/// Renders a label set, with an optional `le` label for histogram buckets.
+fn render_labels(labels: &LabelSet, le: Option<&str>) -> String {
+    let mut rendered: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    if let Some(le) = le {
+        rendered.push(format!("le=\"{}\"", le));
+    }
+    if rendered.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", rendered.join(","))
+    }
+}
+
+/// Escapes a label value for the Prometheus text format.
+pub fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Renders a floating point value for the Prometheus text format.
+fn render_float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_rendering() -> Result<(), MetricError> {
+        let registry = MetricsRegistry::default();
+        registry.record(
+            "evil-bit",
+            "login_failures",
+            &[("method", "post")],
+            MetricUpdate::Increment(2),
+        )?;
+        registry.record(
+            "evil-bit",
+            "login_failures",
+            &[("method", "post")],
+            MetricUpdate::Increment(1),
+        )?;
+        registry.record("evil-bit", "queue_depth", &[], MetricUpdate::Set(4.0))?;
+        registry.record("evil-bit", "queue_depth", &[], MetricUpdate::Add(-1.5))?;
+        registry.record("evil-bit", "latency", &[], MetricUpdate::Observe(0.02))?;
+        registry.record("evil-bit", "latency", &[], MetricUpdate::Observe(20.0))?;
+
+        let rendered = registry.render();
+        assert!(rendered.contains("# TYPE bulwark_plugin_login_failures counter\n"));
+        assert!(rendered
+            .contains("bulwark_plugin_login_failures{plugin=\"evil-bit\",method=\"post\"} 3\n"));
+        assert!(rendered.contains("bulwark_plugin_queue_depth{plugin=\"evil-bit\"} 2.5\n"));
+        assert!(
+            rendered.contains("bulwark_plugin_latency_bucket{plugin=\"evil-bit\",le=\"0.01\"} 0\n")
+        );
+        assert!(rendered
+            .contains("bulwark_plugin_latency_bucket{plugin=\"evil-bit\",le=\"0.025\"} 1\n"));
+        assert!(
+            rendered.contains("bulwark_plugin_latency_bucket{plugin=\"evil-bit\",le=\"10\"} 1\n")
+        );
+        assert!(
+            rendered.contains("bulwark_plugin_latency_bucket{plugin=\"evil-bit\",le=\"+Inf\"} 2\n")
+        );
+        assert!(rendered.contains("bulwark_plugin_latency_sum{plugin=\"evil-bit\"} 20.02\n"));
+        assert!(rendered.contains("bulwark_plugin_latency_count{plugin=\"evil-bit\"} 2\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_per_plugin() -> Result<(), MetricError> {
+        let registry = MetricsRegistry::default();
+        // References that would once have collided once sanitized are kept apart.
+        registry.record("evil-bit", "hits", &[], MetricUpdate::Increment(1))?;
+        registry.record("evil_bit", "hits", &[], MetricUpdate::Increment(2))?;
+        assert!(matches!(
+            registry.record(
+                "evil-bit",
+                "hits",
+                &[("plugin", "evil_bit")],
+                MetricUpdate::Increment(1)
+            ),
+            Err(MetricError::InvalidLabelName { .. })
+        ));
+        // Plugins share a family, so they can't disagree on its kind.
+        assert!(matches!(
+            registry.record("blank-slate", "hits", &[], MetricUpdate::Set(1.0)),
+            Err(MetricError::KindMismatch {
+                kind: MetricKind::Counter,
+                ..
+            })
+        ));
+
+        let rendered = registry.render();
+        assert_eq!(
+            rendered
+                .matches("# TYPE bulwark_plugin_hits counter\n")
+                .count(),
+            1
+        );
+        assert!(rendered.contains("bulwark_plugin_hits{plugin=\"evil-bit\"} 1\n"));
+        assert!(rendered.contains("bulwark_plugin_hits{plugin=\"evil_bit\"} 2\n"));
+
+        // The metric limit applies to each plugin separately.
+        for i in 1..MAX_PLUGIN_METRICS {
+            registry.record(
+                "evil-bit",
+                &format!("metric_{}", i),
+                &[],
+                MetricUpdate::Increment(1),
+            )?;
+        }
+        assert!(matches!(
+            registry.record("evil-bit", "overflow", &[], MetricUpdate::Increment(1)),
+            Err(MetricError::MetricLimit { .. })
+        ));
+        // Existing metrics may still be updated at the limit, and other plugins are unaffected.
+        registry.record("evil-bit", "hits", &[], MetricUpdate::Increment(1))?;
+        registry.record("evil_bit", "overflow", &[], MetricUpdate::Increment(1))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_limits() {
+        let registry = MetricsRegistry::default();
+        assert!(matches!(
+            registry.record("plugin", "bad-name", &[], MetricUpdate::Increment(1)),
+            Err(MetricError::InvalidName { .. })
+        ));
+        assert!(matches!(
+            registry.record(
+                "plugin",
+                "hits",
+                &[("__name__", "x")],
+                MetricUpdate::Increment(1)
+            ),
+            Err(MetricError::InvalidLabelName { .. })
+        ));
+        // Histograms render their own bucket labels, so plugins can't set them.
+        for label_name in RESERVED_HISTOGRAM_LABELS {
+            assert!(matches!(
+                registry.record(
+                    "plugin",
+                    "latency",
+                    &[(label_name, "0.5")],
+                    MetricUpdate::Observe(0.1)
+                ),
+                Err(MetricError::InvalidLabelName { .. })
+            ));
+        }
+        registry
+            .record("plugin", "sizes", &[("le", "x")], MetricUpdate::Set(1.0))
+            .unwrap();
+
+        registry
+            .record("plugin", "hits", &[], MetricUpdate::Increment(1))
+            .unwrap();
+        assert!(matches!(
+            registry.record("plugin", "hits", &[], MetricUpdate::Set(1.0)),
+            Err(MetricError::KindMismatch {
+                kind: MetricKind::Counter,
+                ..
+            })
+        ));
+
+        for i in 0..MAX_METRIC_LABEL_SETS - 1 {
+            registry
+                .record(
+                    "plugin",
+                    "hits",
+                    &[("client", &i.to_string())],
+                    MetricUpdate::Increment(1),
+                )
+                .unwrap();
+        }
+        assert!(matches!(
+            registry.record(
+                "plugin",
+                "hits",
+                &[("client", "overflow")],
+                MetricUpdate::Increment(1)
+            ),
+            Err(MetricError::CardinalityLimit { .. })
+        ));
+        // Existing label sets may still be updated at the limit.
+        registry
+            .record(
+                "plugin",
+                "hits",
+                &[("client", "0")],
+                MetricUpdate::Increment(1),
+            )
+            .unwrap();
+    }
+}
@@ -1,29 +1,81 @@
-// TODO: switch to wasmtime::component::bindgen!
-wit_bindgen_wasmtime::export!("../../bulwark-host.wit");
+wasmtime::component::bindgen!({
+    path: "../../bulwark-plugin.wit",
+    world: "bulwark-plugin",
+});
+
+// The interface version is only named here, so that the rest of the host doesn't change when it's bumped.
+use bulwark::plugin::host as bulwark_host;
 
 use {
     crate::{
-        ContextInstantiationError, PluginExecutionError, PluginInstantiationError, PluginLoadError,
+        Allowance, Breaker, ContextInstantiationError, MetricUpdate, MetricsRegistry,
+        PluginExecutionError, PluginInstantiationError, PluginLoadError, PluginPrestartError, Rate,
+        StateBackend, StateError,
     },
     bulwark_config::ConfigSerializationError,
     bulwark_host::{DecisionInterface, HeaderInterface, OutcomeInterface},
     bulwark_wasm_sdk::{Decision, Outcome},
+    bytes::Bytes,
     chrono::Utc,
-    redis::Commands,
+    sha2::{Digest, Sha256},
     std::{
-        collections::{BTreeSet, HashMap},
+        borrow::Cow,
+        collections::HashMap,
         convert::From,
+        fs,
+        io::{Read, Write},
         net::IpAddr,
-        ops::DerefMut,
-        path::Path,
-        sync::{Arc, Mutex, MutexGuard},
+        path::{Path, PathBuf},
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Mutex, MutexGuard, PoisonError,
+        },
+        task::{Context, Poll},
+        time::{Duration, Instant},
     },
+    tokio::io::AsyncWrite,
+    tracing::{info, warn},
     url::Url,
-    wasmtime::{AsContextMut, Config, Engine, Instance, Linker, Module, Store},
-    wasmtime_wasi::{WasiCtx, WasiCtxBuilder},
+    wasmtime::{
+        component::{Component, HasSelf, Instance, InstancePre, Linker, ResourceTable},
+        Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig, ResourceLimiter,
+        Store, Trap,
+    },
+    wasmtime_wasi::{
+        cli::{IsTerminal, StdoutStream},
+        p2::{OutputStream as WasiOutputStream, Pollable, StreamError, StreamResult},
+        WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView,
+    },
 };
 
-extern crate redis;
+/// The interval at which a plugin's epoch must be incremented for its time limits to be enforced.
+///
+/// See [`PluginEngine::increment_epoch`].
+pub const EPOCH_TICK: Duration = Duration::from_millis(1);
+/// The epoch deadline used when a plugin has no time limit.
+///
+/// This is far enough in the future to never be reached without overflowing when added to the current epoch.
+const NO_EPOCH_DEADLINE: u64 = u64::MAX / 2;
+/// The most core instances, linear memories, or tables that a single plugin instance may take from the instance
+/// pool.
+///
+/// Components built with the SDK instantiate a handful of core modules, but only one of them has a linear memory.
+const MAX_POOLED_RESOURCES_PER_PLUGIN: u32 = 8;
+/// The sustained number of lines per second that a plugin may write to stdout and stderr, across all of its
+/// instances.
+///
+/// Lines written faster than this are dropped rather than logged, and the number dropped is reported once lines are
+/// logged again.
+pub const GUEST_OUTPUT_LINES_PER_SECOND: f64 = 50.0;
+/// The number of lines a plugin may write in a burst before being held to [`GUEST_OUTPUT_LINES_PER_SECOND`].
+const GUEST_OUTPUT_BURST: f64 = 100.0;
+/// The longest line of plugin output that is logged as a single event. Longer lines are split.
+const MAX_GUEST_OUTPUT_LINE_LENGTH: usize = 4096;
+/// The number of bytes a plugin may write to stdout or stderr in a single write.
+const GUEST_OUTPUT_WRITE_PERMIT: usize = 65536;
+/// The target of the events logged by plugins, which allows them to be filtered separately from Bulwark's own.
+pub const PLUGIN_LOG_TARGET: &str = "bulwark_plugin";
 
 /// Wraps an [`IpAddr`] representing the remote IP for the incoming request.
 ///
@@ -35,6 +87,22 @@ pub struct RemoteIP(pub IpAddr);
 /// In an architecture with proxies or load balancers in front of Bulwark, this IP will belong to the IP address
 /// of the client that originated the request rather than the immediately exterior proxy or load balancer.
 pub struct ForwardedIP(pub IpAddr);
+/// Wraps the identifier used to correlate a request across log events, plugins, and the interior service.
+///
+/// The identifier is either read from an incoming request header or generated by Bulwark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// A change a plugin has requested to the request headers forwarded to the interior service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpstreamHeaderMutation {
+    /// Appends a header value, keeping any existing values for the same header name.
+    Add { name: String, value: Vec<u8> },
+    /// Replaces any existing values for the header name with a single value.
+    Set { name: String, value: Vec<u8> },
+    /// Removes all values for the header name.
+    Remove { name: String },
+}
 
 impl From<Arc<bulwark_wasm_sdk::Request>> for bulwark_host::RequestInterface {
     fn from(request: Arc<bulwark_wasm_sdk::Request>) -> Self {
@@ -136,6 +204,38 @@ impl From<Outcome> for OutcomeInterface {
     }
 }
 
+impl From<Rate> for bulwark_host::RateInterface {
+    fn from(rate: Rate) -> Self {
+        bulwark_host::RateInterface {
+            attempts: rate.attempts,
+            expiration: rate.expiration,
+        }
+    }
+}
+
+impl From<Breaker> for bulwark_host::BreakerInterface {
+    fn from(breaker: Breaker) -> Self {
+        bulwark_host::BreakerInterface {
+            generation: breaker.generation,
+            successes: breaker.successes,
+            failures: breaker.failures,
+            consecutive_successes: breaker.consecutive_successes,
+            consecutive_failures: breaker.consecutive_failures,
+            expiration: breaker.expiration,
+        }
+    }
+}
+
+impl From<Allowance> for bulwark_host::AllowanceInterface {
+    fn from(allowance: Allowance) -> Self {
+        bulwark_host::AllowanceInterface {
+            allowed: allowance.allowed,
+            remaining: allowance.remaining,
+            retry_after: allowance.retry_after,
+        }
+    }
+}
+
 /// The primary output of a [`PluginInstance`]'s execution. Combines a [`Decision`] and a list of tags together.
 ///
 /// Both the output of individual plugins as well as the combined decision output of a group of plugins may be
@@ -149,158 +249,81 @@ pub struct DecisionComponents {
     pub tags: Vec<String>,
 }
 
-/// Wraps a Redis connection pool and a registry of predefined Lua scripts.
-pub struct RedisInfo {
-    /// The connection pool
-    pub pool: r2d2::Pool<redis::Client>,
-    /// A Lua script registry
-    pub registry: ScriptRegistry,
+/// Wraps the HTTP client used for outbound requests from plugins, along with the limits placed on those requests.
+///
+/// A single `HttpInfo` is shared by every plugin instance so that connections to third-party services are pooled.
+pub struct HttpInfo {
+    /// The HTTP client and its connection pool
+    pub client: reqwest::blocking::Client,
+    /// The maximum amount of time an outbound request may take
+    pub timeout: Duration,
+    /// The largest response body that will be read, in bytes
+    pub max_response_size: usize,
 }
 
-/// A registry of predefined Lua scripts for execution within Redis.
-pub struct ScriptRegistry {
-    /// Increments a Redis key's counter value if it has not yet expired.
+impl HttpInfo {
+    /// Creates a new `HttpInfo`.
     ///
-    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
-    increment_rate_limit: redis::Script,
-    /// Checks a Redis key's counter value if it has not yet expired.
-    ///
-    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
-    check_rate_limit: redis::Script,
-    /// Increments a Redis key's counter value, corresponding to either success or failure, if it has not yet expired.
-    ///
-    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
-    increment_breaker: redis::Script,
-    /// Checks a Redis key's counter value, corresponding to either success or failure, if it has not yet expired.
+    /// # Arguments
     ///
-    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
-    check_breaker: redis::Script,
+    /// * `timeout` - The maximum amount of time an outbound request may take.
+    /// * `max_response_size` - The largest response body that will be read, in bytes.
+    pub fn new(timeout: Duration, max_response_size: usize) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            timeout,
+            max_response_size,
+        }
+    }
 }
 
-impl Default for ScriptRegistry {
-    fn default() -> ScriptRegistry {
-        ScriptRegistry {
-            // TODO: handle overflow errors by expiring everything on overflow and returning nil?
-            increment_rate_limit: redis::Script::new(
-                r#"
-                local counter_key = "rl:" .. KEYS[1]
-                local increment_delta = tonumber(ARGV[1])
-                local expiration_window = tonumber(ARGV[2])
-                local timestamp = tonumber(ARGV[3])
-                local expiration_key = counter_key .. ":ex"
-                local expiration = tonumber(redis.call("get", expiration_key))
-                local next_expiration = timestamp + expiration_window
-                if not expiration or timestamp > expiration then
-                    redis.call("set", expiration_key, next_expiration)
-                    redis.call("set", counter_key, 0)
-                    redis.call("expireat", expiration_key, next_expiration + 1)
-                    redis.call("expireat", counter_key, next_expiration + 1)
-                    expiration = next_expiration
-                end
-                local attempts = redis.call("incrby", counter_key, increment_delta)
-                return { attempts, expiration }
-                "#,
-            ),
-            check_rate_limit: redis::Script::new(
-                r#"
-                local counter_key = "rl:" .. KEYS[1]
-                local expiration_key = counter_key .. ":exp"
-                local timestamp = tonumber(ARGV[1])
-                local attempts = tonumber(redis.call("get", counter_key))
-                local expiration = nil
-                if attempts then
-                    expiration = tonumber(redis.call("get", expiration_key))
-                    if not expiration or timestamp > expiration then
-                        attempts = nil
-                        expiration = nil
-                    end
-                end
-                return { attempts, expiration }
-                "#,
-            ),
-            increment_breaker: redis::Script::new(
-                r#"
-                local generation_key = "bk:g:" .. KEYS[1]
-                local success_key = "bk:s:" .. KEYS[1]
-                local failure_key = "bk:f:" .. KEYS[1]
-                local consec_success_key = "bk:cs:" .. KEYS[1]
-                local consec_failure_key = "bk:cf:" .. KEYS[1]
-                local success_delta = tonumber(ARGV[1])
-                local failure_delta = tonumber(ARGV[2])
-                local expiration_window = tonumber(ARGV[3])
-                local timestamp = tonumber(ARGV[4])
-                local expiration = timestamp + expiration_window
-                local generation = redis.call("incrby", generation_key, 1)
-                local successes = 0
-                local failures = 0
-                local consec_successes = 0
-                local consec_failures = 0
-                if success_delta > 0 then
-                    successes = redis.call("incrby", success_key, success_delta)
-                    failures = tonumber(redis.call("get", failure_key)) or 0
-                    consec_successes = redis.call("incrby", consec_success_key, success_delta)
-                    redis.call("set", consec_failure_key, 0)
-                    consec_failures = 0
-                else
-                    successes = tonumber(redis.call("get", success_key))
-                    failures = redis.call("incrby", failure_key, failure_delta) or 0
-                    redis.call("set", consec_success_key, 0)
-                    consec_successes = 0
-                    consec_failures = redis.call("incrby", consec_failure_key, failure_delta)
-                end
-                redis.call("expireat", generation_key, expiration + 1)
-                redis.call("expireat", success_key, expiration + 1)
-                redis.call("expireat", failure_key, expiration + 1)
-                redis.call("expireat", consec_success_key, expiration + 1)
-                redis.call("expireat", consec_failure_key, expiration + 1)
-                return { generation, successes, failures, consec_successes, consec_failures, expiration }
-                "#,
-            ),
-            check_breaker: redis::Script::new(
-                r#"
-                local generation_key = "bk:g:" .. KEYS[1]
-                local success_key = "bk:s:" .. KEYS[1]
-                local failure_key = "bk:f:" .. KEYS[1]
-                local consec_success_key = "bk:cs:" .. KEYS[1]
-                local consec_failure_key = "bk:cf:" .. KEYS[1]
-                local generation = tonumber(redis.call("get", generation_key))
-                if not generation then
-                    return { nil, nil, nil, nil, nil, nil }
-                end
-                local successes = tonumber(redis.call("get", success_key)) or 0
-                local failures = tonumber(redis.call("get", failure_key)) or 0
-                local consec_successes = tonumber(redis.call("get", consec_success_key)) or 0
-                local consec_failures = tonumber(redis.call("get", consec_failure_key)) or 0
-                local expiration = tonumber(redis.call("expiretime", success_key)) - 1
-                return { generation, successes, failures, consec_successes, consec_failures, expiration }
-                "#,
-            ),
-        }
+impl Default for HttpInfo {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(bulwark_config::DEFAULT_OUTBOUND_TIMEOUT),
+            bulwark_config::DEFAULT_MAX_OUTBOUND_RESPONSE_SIZE,
+        )
     }
 }
 
 /// The RequestContext provides a store of information that needs to cross the plugin sandbox boundary.
 pub struct RequestContext {
     wasi: WasiCtx,
+    /// The resources, such as output streams, that the guest environment holds handles to.
+    table: ResourceTable,
 
     config: Arc<Vec<u8>>,
+    /// The reference of the plugin, used to attribute permission denials.
+    plugin_reference: String,
     /// The set of permissions granted to a plugin.
     permissions: bulwark_config::Permissions,
+    /// The plugin's count of permission denials, shared with the [`Plugin`].
+    permission_denials: Arc<AtomicU64>,
+    /// The registry that the plugin's custom metrics are recorded in.
+    metrics: Arc<MetricsRegistry>,
     /// The `params` are a key-value map shared between all plugin instances for a single request.
     params: Arc<Mutex<bulwark_wasm_sdk::Map<String, bulwark_wasm_sdk::Value>>>, // TODO: remove Arc? move to host mutable context?
     /// The HTTP request that the plugin is processing.
     request: bulwark_host::RequestInterface,
     /// The IP address of the client that originated the request, if available.
     client_ip: Option<bulwark_host::IpInterface>,
-    /// The Redis connection pool and its associated Lua scripts.
-    redis_info: Option<Arc<RedisInfo>>,
+    /// The identifier used to correlate the request across log events, plugins, and the interior service.
+    request_id: String,
+    /// The request identifier that the guest's output is attributed to, shared with its output streams.
+    output_request_id: Arc<Mutex<String>>,
+    /// The redaction rules applied to the request before it's exposed to the guest environment.
+    redaction: bulwark_config::Redaction,
+    /// The store of state shared between plugin instances.
+    state_backend: Arc<dyn StateBackend>,
     /// A store of outbound requests being assembled by a plugin.
     ///
     /// Due to apparent limitations in WIT, a full request structure cannot be easily sent by a plugin as a single
     /// record. This is a work-around, but there may be better alternatives to achieve the same effect.
     outbound_http: Arc<Mutex<HashMap<u64, reqwest::blocking::RequestBuilder>>>,
-    /// The HTTP client used to send outbound requests from plugins.
-    http_client: reqwest::blocking::Client,
+    /// The ID given to the next outbound request, so that IDs are never reused while a request is being assembled.
+    next_outbound_request_id: u64,
+    /// The HTTP client used to send outbound requests from plugins and the limits placed on those requests.
+    http_info: Arc<HttpInfo>,
 
     // TODO: wrap these with `DecisionComponents`
     /// The `accept` component of a [`Decision`].
@@ -311,6 +334,10 @@ pub struct RequestContext {
     unknown: f64,
     /// The tags annotating a plugins decision.
     tags: Vec<String>,
+    /// The changes the plugin wants made to the request headers forwarded to the interior service.
+    upstream_header_mutations: Vec<UpstreamHeaderMutation>,
+    /// The limits on the plugin's linear memory and tables.
+    limiter: ExecutionLimiter,
 
     // TODO: should there be read-only context and guest-mutable context structs as well?
     /// Context values that will be mutated by the host environment.
@@ -323,47 +350,275 @@ impl RequestContext {
     /// # Arguments
     ///
     /// * `plugin` - The [`Plugin`] and its associated configuration.
-    /// * `redis_info` - The Redis connection pool.
+    /// * `state_backend` - The store of state shared between plugin instances.
+    /// * `http_info` - The HTTP client shared by all plugins for outbound requests.
     /// * `params` - A key-value map that plugins use to pass values within the context of a request.
     ///     Any parameters captured by the router will be added to this before plugin execution.
     /// * `request` - The [`Request`](bulwark_wasm_sdk::Request) that plugins will be operating on.
     pub fn new(
         plugin: Arc<Plugin>,
-        redis_info: Option<Arc<RedisInfo>>,
+        state_backend: Arc<dyn StateBackend>,
+        http_info: Arc<HttpInfo>,
         params: Arc<Mutex<bulwark_wasm_sdk::Map<String, bulwark_wasm_sdk::Value>>>,
         request: Arc<bulwark_wasm_sdk::Request>,
     ) -> Result<RequestContext, ContextInstantiationError> {
+        let mut request_context = Self::unbound(&plugin, state_backend, http_info)?;
+        request_context.bind(params, request);
+        Ok(request_context)
+    }
+
+    /// Creates a `RequestContext` that isn't associated with a request yet.
+    ///
+    /// Until [`bind`](RequestContext::bind) is called, the guest environment sees an empty request.
+    fn unbound(
+        plugin: &Plugin,
+        state_backend: Arc<dyn StateBackend>,
+        http_info: Arc<HttpInfo>,
+    ) -> Result<RequestContext, ContextInstantiationError> {
+        let output_request_id = Arc::new(Mutex::new(String::new()));
+        // Guest output is logged with attribution instead of being interleaved with Bulwark's own log stream.
         let wasi = WasiCtxBuilder::new()
-            .inherit_stdio()
-            .inherit_args()?
+            .stdout(plugin.guest_output(OutputStream::Stdout, output_request_id.clone()))
+            .stderr(plugin.guest_output(OutputStream::Stderr, output_request_id.clone()))
+            .inherit_args()
             .build();
-        let client_ip = request
-            .extensions()
-            .get::<ForwardedIP>()
-            .map(|forwarded_ip| bulwark_host::IpInterface::from(forwarded_ip.0));
 
         Ok(RequestContext {
             wasi,
-            redis_info,
+            table: ResourceTable::new(),
+            state_backend,
             config: Arc::new(plugin.guest_config()?),
+            plugin_reference: plugin.reference.clone(),
             permissions: plugin.permissions(),
-            params,
-            request: bulwark_host::RequestInterface::from(request),
-            client_ip,
+            permission_denials: plugin.permission_denials.clone(),
+            metrics: plugin.engine.metrics.clone(),
+            params: Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new())),
+            request: bulwark_host::RequestInterface {
+                method: String::new(),
+                uri: String::new(),
+                version: String::new(),
+                headers: vec![],
+                chunk_start: 0,
+                chunk_length: 0,
+                end_of_stream: true,
+                chunk: vec![],
+            },
+            client_ip: None,
+            request_id: String::new(),
+            output_request_id,
+            redaction: plugin.redaction(),
             outbound_http: Arc::new(Mutex::new(HashMap::new())),
-            http_client: reqwest::blocking::Client::new(),
+            next_outbound_request_id: 0,
+            http_info,
             accept: 0.0,
             restrict: 0.0,
             unknown: 1.0,
             tags: vec![],
+            upstream_header_mutations: vec![],
+            limiter: plugin.limiter(),
             host_mutable_context: HostMutableContext {
                 response: Arc::new(Mutex::new(None)),
                 combined_decision: Arc::new(Mutex::new(None)),
                 outcome: Arc::new(Mutex::new(None)),
                 combined_tags: Arc::new(Mutex::new(None)),
+                deadline: Arc::new(Mutex::new(None)),
             },
         })
     }
+
+    /// Associates the context with the request that its plugin instance will process.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - A key-value map that plugins use to pass values within the context of a request.
+    /// * `request` - The [`Request`](bulwark_wasm_sdk::Request) that plugins will be operating on.
+    fn bind(
+        &mut self,
+        params: Arc<Mutex<bulwark_wasm_sdk::Map<String, bulwark_wasm_sdk::Value>>>,
+        request: Arc<bulwark_wasm_sdk::Request>,
+    ) {
+        // Fall back to the peer address if the client IP couldn't be resolved from forwarding headers.
+        self.client_ip = request
+            .extensions()
+            .get::<ForwardedIP>()
+            .map(|forwarded_ip| forwarded_ip.0)
+            .or_else(|| {
+                request
+                    .extensions()
+                    .get::<RemoteIP>()
+                    .map(|remote_ip| remote_ip.0)
+            })
+            .map(bulwark_host::IpInterface::from);
+        self.request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .map(|request_id| request_id.0.clone())
+            .unwrap_or_default();
+        *self
+            .output_request_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = self.request_id.clone();
+        // Sensitive headers and cookies are redacted before the request is copied into the guest environment.
+        let mut request = bulwark_host::RequestInterface::from(request);
+        for header in request.headers.iter_mut() {
+            if let Cow::Owned(value) = self.redaction.redact_header(&header.name, &header.value) {
+                header.value = value;
+            }
+        }
+        self.request = request;
+        self.params = params;
+    }
+}
+
+impl WasiView for RequestContext {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+/// The compilation and execution environment shared by every [`Plugin`].
+///
+/// Plugins loaded with the same engine share its epoch and, if pooling is enabled, its pool of instance slots.
+/// Loading every plugin with one engine also means host functions are only linked once.
+#[derive(Clone)]
+pub struct PluginEngine {
+    engine: Engine,
+    /// The linker is shared by every plugin so that host functions are only defined once.
+    linker: Arc<Linker<RequestContext>>,
+    /// Describes the engine configuration so that cached modules compiled under a different one are not reused.
+    fingerprint: String,
+    /// The directory compiled modules are cached in, if any.
+    module_cache_dir: Option<PathBuf>,
+    /// The registry that custom metrics recorded by plugins are stored in.
+    metrics: Arc<MetricsRegistry>,
+}
+
+impl PluginEngine {
+    /// Creates a new [`PluginEngine`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool_size` - The number of instance slots to preallocate with a pooling instance allocator. The pool is
+    ///     shared by every plugin loaded with the engine, so it must cover every instance of every plugin that may
+    ///     exist at once, including prestarted instances. If `None`, instances are allocated on demand.
+    pub fn new(pool_size: Option<u32>) -> Result<Self, PluginLoadError> {
+        let mut wasm_config = Config::new();
+        wasm_config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
+        wasm_config.wasm_multi_memory(true);
+        // Timeouts can only interrupt guest code if the guest checks the epoch as it runs.
+        wasm_config.epoch_interruption(true);
+        if let Some(pool_size) = pool_size {
+            // Each slot's memory is reset when an instance is deallocated, so no state leaks between requests.
+            let mut pooling_config = PoolingAllocationConfig::default();
+            pooling_config
+                .total_component_instances(pool_size)
+                .total_core_instances(pool_size * MAX_POOLED_RESOURCES_PER_PLUGIN)
+                .total_memories(pool_size * MAX_POOLED_RESOURCES_PER_PLUGIN)
+                .total_tables(pool_size * MAX_POOLED_RESOURCES_PER_PLUGIN)
+                .max_core_instances_per_component(MAX_POOLED_RESOURCES_PER_PLUGIN)
+                .max_memories_per_component(MAX_POOLED_RESOURCES_PER_PLUGIN)
+                .max_tables_per_component(MAX_POOLED_RESOURCES_PER_PLUGIN);
+            wasm_config.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
+        }
+
+        let engine = Engine::new(&wasm_config)?;
+        let mut linker: Linker<RequestContext> = Linker::new(&engine);
+        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
+        bulwark_host::add_to_linker::<RequestContext, HasSelf<RequestContext>>(
+            &mut linker,
+            |ctx| ctx,
+        )?;
+
+        Ok(PluginEngine {
+            engine,
+            linker: Arc::new(linker),
+            fingerprint: format!(
+                "bulwark-wasm-host-{}-{}-pool-{:?}",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::ARCH,
+                pool_size
+            ),
+            module_cache_dir: None,
+            metrics: Arc::new(MetricsRegistry::default()),
+        })
+    }
+
+    /// Caches compiled modules in the given directory so that loading the same plugin again skips compilation.
+    ///
+    /// Cached modules are loaded without being validated, so the directory must only be writable by Bulwark.
+    pub fn with_module_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.module_cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Records custom metrics from plugins in the given registry so that they can be exported.
+    ///
+    /// By default, each engine records metrics in its own registry.
+    pub fn with_metrics(mut self, metrics: Arc<MetricsRegistry>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns the registry that custom metrics recorded by plugins are stored in.
+    pub fn metrics(&self) -> Arc<MetricsRegistry> {
+        self.metrics.clone()
+    }
+
+    /// Advances the engine's epoch by one tick.
+    ///
+    /// This must be called every [`EPOCH_TICK`] for plugin time limits to be enforced. A plugin instance that runs
+    /// past its deadline will trap the next time the epoch is advanced.
+    pub fn increment_epoch(&self) {
+        self.engine.increment_epoch();
+    }
+
+    /// Compiles `*.wasm` or `*.wat` bytes into a [`Component`], reusing a cached copy if one is available.
+    ///
+    /// Failing to read from or write to the module cache is not an error. The component is compiled as though the
+    /// cache did not exist.
+    fn compile(&self, bytes: &[u8]) -> Result<Component, PluginLoadError> {
+        let cache_path = self
+            .module_cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.cwasm", self.cache_key(bytes))));
+        if let Some(cache_path) = cache_path.as_ref().filter(|path| path.exists()) {
+            // SAFETY: Deserialized components are trusted to have been compiled by this engine configuration, which
+            // is why the cache directory must only be writable by Bulwark. Wasmtime itself rejects components compiled
+            // by an incompatible version or configuration, in which case the component is simply recompiled.
+            if let Ok(component) = unsafe { Component::deserialize_file(&self.engine, cache_path) }
+            {
+                return Ok(component);
+            }
+        }
+
+        let component = Component::new(&self.engine, bytes)?;
+        if let Some(cache_path) = cache_path {
+            if let Ok(serialized) = component.serialize() {
+                // Write to a temporary file first so that a concurrent load never reads a partially written component.
+                let temp_path = cache_path.with_extension(format!("{}.tmp", std::process::id()));
+                let written = cache_path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(&temp_path, serialized))
+                    .and_then(|_| fs::rename(&temp_path, &cache_path));
+                if written.is_err() {
+                    fs::remove_file(&temp_path).ok();
+                }
+            }
+        }
+        Ok(component)
+    }
+
+    /// Derives the cache file name for a module from its bytes and the engine configuration.
+    fn cache_key(&self, bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.fingerprint.as_bytes());
+        hasher.update(bytes);
+        format!("{:x}", hasher.finalize())
+    }
 }
 
 /// A singular detection plugin and provides the interface between WASM host and guest.
@@ -373,77 +628,101 @@ impl RequestContext {
 pub struct Plugin {
     reference: String,
     config: Arc<bulwark_config::Plugin>,
-    engine: Engine,
-    module: Module,
+    engine: PluginEngine,
+    /// The component with its imports already resolved, so that each instance only has to be allocated.
+    instance_pre: InstancePre<RequestContext>,
+    /// The number of times instances of the plugin have been denied an operation for lack of a permission.
+    permission_denials: Arc<AtomicU64>,
+    /// Limits how quickly output from instances of the plugin is logged.
+    output_limiter: Arc<Mutex<OutputRateLimiter>>,
 }
 
 impl Plugin {
     /// Creates and compiles a new [`Plugin`] from a [`String`] of
     /// [WAT](https://webassembly.github.io/spec/core/text/index.html)-formatted WASM.
     pub fn from_wat(
+        engine: &PluginEngine,
         name: String,
         wat: &str,
         config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
-        Self::from_module(name, config, |engine| -> Result<Module, PluginLoadError> {
-            let module = Module::new(engine, wat.as_bytes())?;
-            Ok(module)
-        })
+        let component = Component::new(&engine.engine, wat.as_bytes())?;
+        Self::from_component(engine, name, config, component)
     }
 
     /// Creates and compiles a new [`Plugin`] from a byte slice of WASM.
     ///
-    /// The bytes it expects are what you'd get if you read in a `*.wasm` file.
-    /// See [`Module::from_binary`].
+    /// The bytes it expects are what you'd get if you read in a `*.wasm` file. If the engine has a module cache,
+    /// a previously compiled copy of the same bytes is reused.
     pub fn from_bytes(
+        engine: &PluginEngine,
         name: String,
         bytes: &[u8],
         config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
-        Self::from_module(name, config, |engine| -> Result<Module, PluginLoadError> {
-            let module = Module::from_binary(engine, bytes)?;
-            Ok(module)
-        })
+        let component = engine.compile(bytes)?;
+        Self::from_component(engine, name, config, component)
     }
 
     /// Creates and compiles a new [`Plugin`] by reading in a file in either `*.wasm` or `*.wat` format.
     ///
-    /// See [`Module::from_file`].
+    /// If the engine has a module cache, a previously compiled copy of the same file contents is reused.
     pub fn from_file(
+        engine: &PluginEngine,
         path: impl AsRef<Path>,
         config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
         let name = config.reference.clone();
-        Self::from_module(name, config, |engine| -> Result<Module, PluginLoadError> {
-            let module = Module::from_file(engine, &path)?;
-            Ok(module)
-        })
+        let bytes = fs::read(path)?;
+        let component = engine.compile(&bytes)?;
+        Self::from_component(engine, name, config, component)
     }
 
     /// Helper method for the other `from_*` functions.
-    fn from_module<F>(
+    ///
+    /// Fails if the component imports anything that the host doesn't provide.
+    fn from_component(
+        engine: &PluginEngine,
         reference: String,
         config: &bulwark_config::Plugin,
-        mut get_module: F,
-    ) -> Result<Self, PluginLoadError>
-    where
-        F: FnMut(&Engine) -> Result<Module, PluginLoadError>,
-    {
-        let mut wasm_config = Config::new();
-        wasm_config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
-        wasm_config.wasm_multi_memory(true);
-
-        let engine = Engine::new(&wasm_config)?;
-        let module = get_module(&engine)?;
-
+        component: Component,
+    ) -> Result<Self, PluginLoadError> {
+        let instance_pre = engine.linker.instantiate_pre(&component)?;
         Ok(Plugin {
             reference,
             config: Arc::new(config.clone()),
-            engine,
-            module,
+            engine: engine.clone(),
+            instance_pre,
+            permission_denials: Arc::new(AtomicU64::new(0)),
+            output_limiter: Arc::new(Mutex::new(OutputRateLimiter::new())),
         })
     }
 
+    /// Returns the plugin's identifier.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// Returns the configured weight value for tuning [`Decision`] values.
+    pub fn weight(&self) -> f64 {
+        self.config.weight
+    }
+
+    /// Returns the configured failure policy, if the plugin overrides the resource's failure policy.
+    pub fn failure_policy(&self) -> Option<bulwark_config::FailurePolicy> {
+        self.config.failure_policy
+    }
+
+    /// Returns the configured timeout in milliseconds, if the plugin overrides the resource's timeout.
+    pub fn timeout(&self) -> Option<u64> {
+        self.config.timeout
+    }
+
+    /// Returns the number of times instances of the plugin have been denied an operation for lack of a permission.
+    pub fn permission_denials(&self) -> u64 {
+        self.permission_denials.load(Ordering::Relaxed)
+    }
+
     /// Makes the guest's configuration available as serialized JSON bytes.
     fn guest_config(&self) -> Result<Vec<u8>, ConfigSerializationError> {
         // TODO: should guest config be required or optional?
@@ -454,6 +733,289 @@ impl Plugin {
     fn permissions(&self) -> bulwark_config::Permissions {
         self.config.permissions.clone()
     }
+
+    /// Creates a capture for one of the output streams of an instance of the plugin.
+    fn guest_output(
+        &self,
+        stream: OutputStream,
+        request_id: Arc<Mutex<String>>,
+    ) -> SharedGuestOutput {
+        SharedGuestOutput(Arc::new(Mutex::new(GuestOutput {
+            plugin_reference: self.reference.clone(),
+            request_id,
+            stream,
+            limiter: self.output_limiter.clone(),
+            partial_line: Vec::new(),
+        })))
+    }
+
+    /// Creates the limiter that caps the linear memory and tables of an instance of the plugin.
+    fn limiter(&self) -> ExecutionLimiter {
+        ExecutionLimiter {
+            max_memory: self
+                .config
+                .max_memory
+                .unwrap_or(bulwark_config::DEFAULT_PLUGIN_MAX_MEMORY),
+            max_table_elements: self
+                .config
+                .max_table_elements
+                .unwrap_or(bulwark_config::DEFAULT_PLUGIN_MAX_TABLE_ELEMENTS),
+            exceeded: None,
+        }
+    }
+
+    /// Returns the redaction rules for requests exposed to the guest environment, less any headers and cookies
+    /// the plugin has been granted access to.
+    fn redaction(&self) -> bulwark_config::Redaction {
+        self.config
+            .redaction
+            .without_permitted(&self.config.permissions)
+    }
+}
+
+/// One of the output streams of a guest environment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A token bucket limiting how quickly a plugin's output is logged, shared by every instance of the plugin.
+struct OutputRateLimiter {
+    /// The number of lines that may currently be logged.
+    tokens: f64,
+    /// The last time tokens were added to the bucket.
+    refilled_at: Instant,
+    /// The number of lines dropped since a line was last logged.
+    suppressed: u64,
+}
+
+impl OutputRateLimiter {
+    fn new() -> Self {
+        OutputRateLimiter {
+            tokens: GUEST_OUTPUT_BURST,
+            refilled_at: Instant::now(),
+            suppressed: 0,
+        }
+    }
+
+    /// Attempts to take a token for a line of output.
+    ///
+    /// Returns `None` if the line must be dropped. Otherwise, returns the number of lines dropped since a line was
+    /// last logged.
+    fn acquire(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * GUEST_OUTPUT_LINES_PER_SECOND).min(GUEST_OUTPUT_BURST);
+        self.refilled_at = now;
+        if self.tokens < 1.0 {
+            self.suppressed += 1;
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+/// Captures one of a plugin instance's output streams, logging each line as a tracing event attributed to the
+/// plugin and request.
+struct GuestOutput {
+    plugin_reference: String,
+    /// The request the output is attributed to, which is empty while a prestarted instance is initialized.
+    request_id: Arc<Mutex<String>>,
+    stream: OutputStream,
+    limiter: Arc<Mutex<OutputRateLimiter>>,
+    /// The bytes written since the last complete line.
+    partial_line: Vec<u8>,
+}
+
+impl GuestOutput {
+    /// Logs a line of output, unless the plugin has exceeded its output rate.
+    fn emit(&self, line: &[u8]) {
+        let suppressed = match self.limiter.lock().unwrap().acquire() {
+            Some(suppressed) => suppressed,
+            None => return,
+        };
+        let request_id = self
+            .request_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if suppressed > 0 {
+            warn!(
+                message = "plugin output suppressed",
+                plugin = self.plugin_reference,
+                request_id,
+                lines = suppressed
+            );
+        }
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        match self.stream {
+            OutputStream::Stdout => info!(
+                message = "plugin output",
+                plugin = self.plugin_reference,
+                request_id,
+                stream = "stdout",
+                line = %line
+            ),
+            OutputStream::Stderr => warn!(
+                message = "plugin output",
+                plugin = self.plugin_reference,
+                request_id,
+                stream = "stderr",
+                line = %line
+            ),
+        }
+    }
+}
+
+impl Write for GuestOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            if byte == b'\n' {
+                self.emit(&self.partial_line);
+                self.partial_line.clear();
+            } else {
+                self.partial_line.push(byte);
+                if self.partial_line.len() >= MAX_GUEST_OUTPUT_LINE_LENGTH {
+                    self.emit(&self.partial_line);
+                    self.partial_line.clear();
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for GuestOutput {
+    /// Logs any output left without a trailing newline once the instance is done with the stream.
+    fn drop(&mut self) {
+        if !self.partial_line.is_empty() {
+            self.emit(&self.partial_line);
+        }
+    }
+}
+
+/// Shares a [`GuestOutput`] between every handle the guest environment opens to the same output stream.
+///
+/// Output is captured synchronously as it's written, rather than through an adapter that drains it on another task,
+/// so that it's logged in order with the plugin's other events.
+#[derive(Clone)]
+struct SharedGuestOutput(Arc<Mutex<GuestOutput>>);
+
+impl SharedGuestOutput {
+    fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write(buf)
+    }
+}
+
+impl IsTerminal for SharedGuestOutput {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdoutStream for SharedGuestOutput {
+    fn p2_stream(&self) -> Box<dyn WasiOutputStream> {
+        Box::new(self.clone())
+    }
+
+    fn async_stream(&self) -> Box<dyn AsyncWrite + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl WasiOutputStream for SharedGuestOutput {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        SharedGuestOutput::write(self, &bytes)
+            .map(|_| ())
+            .map_err(|err| StreamError::LastOperationFailed(err.into()))
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        Ok(GUEST_OUTPUT_WRITE_PERMIT)
+    }
+}
+
+#[wasmtime_wasi::async_trait]
+impl Pollable for SharedGuestOutput {
+    /// Writes never block, so the stream is always ready.
+    async fn ready(&mut self) {}
+}
+
+impl AsyncWrite for SharedGuestOutput {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(SharedGuestOutput::write(&self, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Limits the linear memory and tables of a plugin instance.
+///
+/// A guest that fails to grow its memory will usually trap shortly afterwards, so the limiter remembers which limit
+/// was exceeded in order for the trap to be reported accurately.
+struct ExecutionLimiter {
+    /// The largest size in bytes that linear memory may grow to.
+    max_memory: usize,
+    /// The largest number of elements that a table may grow to.
+    max_table_elements: u32,
+    /// The error describing the limit exceeded during the current call, if any.
+    exceeded: Option<PluginExecutionError>,
+}
+
+impl ResourceLimiter for ExecutionLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if desired > self.max_memory {
+            self.exceeded = Some(PluginExecutionError::MemoryLimitExceeded {
+                limit: self.max_memory,
+            });
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if desired > self.max_table_elements as usize {
+            self.exceeded = Some(PluginExecutionError::TableLimitExceeded {
+                limit: self.max_table_elements,
+            });
+            return Ok(false);
+        }
+        Ok(true)
+    }
 }
 
 /// A collection of values that the host environment will mutate over the lifecycle of a request/response.
@@ -469,6 +1031,8 @@ struct HostMutableContext {
     combined_tags: Arc<Mutex<Option<Vec<String>>>>,
     /// The decision outcome after the decision has been checked against configured thresholds.
     outcome: Arc<Mutex<Option<bulwark_host::OutcomeInterface>>>,
+    /// The time by which the plugin's current execution phase must finish.
+    deadline: Arc<Mutex<Option<Instant>>>,
 }
 
 /// An instance of a [`Plugin`], associated with a [`RequestContext`].
@@ -481,6 +1045,8 @@ pub struct PluginInstance {
     instance: Instance,
     /// All plugin-visible state that the host environment will mutate over the lifecycle of a request/response.
     host_mutable_context: HostMutableContext,
+    /// Whether the guest's `on-init` function has already run.
+    started: bool,
 }
 
 impl PluginInstance {
@@ -497,27 +1063,41 @@ impl PluginInstance {
         // Clone the host mutable context so that we can make changes to the interior of our request context from the parent.
         let host_mutable_context = request_context.host_mutable_context.clone();
 
-        // TODO: do we need to retain a reference to the linker value anywhere? explore how other wasm-based systems use it.
-        // convert from normal request struct to wasm request interface
-        let mut linker: Linker<RequestContext> = Linker::new(&plugin.engine);
-        wasmtime_wasi::add_to_linker(&mut linker, |s| &mut s.wasi)?;
-
-        let mut store = Store::new(&plugin.engine, request_context);
-        bulwark_host::add_to_linker(&mut linker, |ctx: &mut RequestContext| ctx)?;
-
-        let instance = linker.instantiate(&mut store, &plugin.module)?;
+        // Each instance gets a fresh store, so per-request state never outlives the request.
+        let mut store = Store::new(&plugin.engine.engine, request_context);
+        store.limiter(|ctx| &mut ctx.limiter);
+        store.set_epoch_deadline(NO_EPOCH_DEADLINE);
+        let instance = plugin.instance_pre.instantiate(&mut store)?;
 
         Ok(PluginInstance {
             plugin,
             store,
             instance,
             host_mutable_context,
+            started: false,
         })
     }
 
     /// Returns the configured weight value for tuning [`Decision`] values.
     pub fn weight(&self) -> f64 {
-        self.plugin.config.weight
+        self.plugin.weight()
+    }
+
+    /// Returns the configured failure policy, if the plugin overrides the resource's failure policy.
+    pub fn failure_policy(&self) -> Option<bulwark_config::FailurePolicy> {
+        self.plugin.failure_policy()
+    }
+
+    /// Returns the configured timeout in milliseconds, if the plugin overrides the resource's timeout.
+    pub fn timeout(&self) -> Option<u64> {
+        self.plugin.timeout()
+    }
+
+    /// Records the time by which the next execution phase must finish so that the plugin guest environment can
+    /// check its remaining budget.
+    pub fn record_deadline(&mut self, deadline: Instant) {
+        let mut interior_deadline = self.host_mutable_context.deadline.lock().unwrap();
+        *interior_deadline = Some(deadline);
     }
 
     /// Records a [`Response`](bulwark_wasm_sdk::Response) so that it will be accessible to the plugin guest
@@ -536,6 +1116,8 @@ impl PluginInstance {
     ) {
         let mut interior_decision = self.host_mutable_context.combined_decision.lock().unwrap();
         *interior_decision = Some(decision_components.decision.into());
+        let mut interior_tags = self.host_mutable_context.combined_tags.lock().unwrap();
+        *interior_tags = Some(decision_components.tags.clone());
         let mut interior_outcome = self.host_mutable_context.outcome.lock().unwrap();
         *interior_outcome = Some(outcome.into());
     }
@@ -545,100 +1127,117 @@ impl PluginInstance {
         self.plugin.reference.clone()
     }
 
-    /// Executes the `_start` WASM initialization function in the guest environment.
+    /// Executes the guest's `on-init` function.
     ///
-    /// This will generally be the `main()` function for WASI. Unlike the other handler functions,
-    /// there is no `has_start` function because it is required by the WASI specification.
+    /// Unlike the other handler functions, there is no `has_init_handler` function because the plugin world
+    /// requires it to be exported. Does nothing if the function has already run, such as for an instance taken from
+    /// [`PrestartedInstances`].
     pub fn start(&mut self) -> Result<(), PluginExecutionError> {
-        const FN_NAME: &str = "_start";
-        let fn_ref = self.instance.get_func(self.store.as_context_mut(), FN_NAME);
-        fn_ref
-            .ok_or(PluginExecutionError::NotImplementedError {
-                expected: FN_NAME.to_string(),
-            })?
-            .call(self.store.as_context_mut(), &[], &mut [])?;
-
+        const FN_NAME: &str = "on-init";
+        if self.started {
+            return Ok(());
+        }
+        self.call_guest_function(FN_NAME)?;
+        self.started = true;
         Ok(())
     }
 
-    /// Returns true if the guest environment has declared an `on_request` function.
+    /// Returns true if the guest environment has declared an `on-request` function.
     pub fn has_request_handler(&mut self) -> bool {
         self.instance
-            .get_func(self.store.as_context_mut(), "on_request")
+            .get_func(&mut self.store, "on-request")
             .is_some()
     }
 
-    /// Executes the guest's `on_request` function.
+    /// Executes the guest's `on-request` function.
     pub fn handle_request(&mut self) -> Result<(), PluginExecutionError> {
-        const FN_NAME: &str = "on_request";
-        let fn_ref = self.instance.get_func(self.store.as_context_mut(), FN_NAME);
-        fn_ref
-            .ok_or(PluginExecutionError::NotImplementedError {
-                expected: FN_NAME.to_string(),
-            })?
-            .call(self.store.as_context_mut(), &[], &mut [])?;
-
-        Ok(())
+        const FN_NAME: &str = "on-request";
+        self.call_guest_function(FN_NAME)
     }
 
-    /// Returns true if the guest environment has declared an `on_request_decision` function.
+    /// Returns true if the guest environment has declared an `on-request-decision` function.
     pub fn has_request_decision_handler(&mut self) -> bool {
         self.instance
-            .get_func(self.store.as_context_mut(), "on_request_decision")
+            .get_func(&mut self.store, "on-request-decision")
             .is_some()
     }
 
-    /// Executes the guest's `on_request_decision` function.
+    /// Executes the guest's `on-request-decision` function.
     pub fn handle_request_decision(&mut self) -> Result<(), PluginExecutionError> {
-        const FN_NAME: &str = "on_request_decision";
-        let fn_ref = self.instance.get_func(self.store.as_context_mut(), FN_NAME);
-        fn_ref
-            .ok_or(PluginExecutionError::NotImplementedError {
-                expected: FN_NAME.to_string(),
-            })?
-            .call(self.store.as_context_mut(), &[], &mut [])?;
-
-        Ok(())
+        const FN_NAME: &str = "on-request-decision";
+        self.call_guest_function(FN_NAME)
     }
 
-    /// Returns true if the guest environment has declared an `on_response_decision` function.
+    /// Returns true if the guest environment has declared an `on-response-decision` function.
     pub fn has_response_decision_handler(&mut self) -> bool {
         self.instance
-            .get_func(self.store.as_context_mut(), "on_response_decision")
+            .get_func(&mut self.store, "on-response-decision")
             .is_some()
     }
 
-    /// Executes the guest's `on_response_decision` function.
+    /// Executes the guest's `on-response-decision` function.
     pub fn handle_response_decision(&mut self) -> Result<(), PluginExecutionError> {
-        const FN_NAME: &str = "on_response_decision";
-        let fn_ref = self.instance.get_func(self.store.as_context_mut(), FN_NAME);
-        fn_ref
-            .ok_or(PluginExecutionError::NotImplementedError {
-                expected: FN_NAME.to_string(),
-            })?
-            .call(self.store.as_context_mut(), &[], &mut [])?;
-
-        Ok(())
+        const FN_NAME: &str = "on-response-decision";
+        self.call_guest_function(FN_NAME)
     }
 
-    /// Returns true if the guest environment has declared an `on_decision_feedback` function.
+    /// Returns true if the guest environment has declared an `on-decision-feedback` function.
     pub fn has_decision_feedback_handler(&mut self) -> bool {
         self.instance
-            .get_func(self.store.as_context_mut(), "on_decision_feedback")
+            .get_func(&mut self.store, "on-decision-feedback")
             .is_some()
     }
 
-    /// Executes the guest's `on_decision_feedback` function.
+    /// Executes the guest's `on-decision-feedback` function.
     pub fn handle_decision_feedback(&mut self) -> Result<(), PluginExecutionError> {
-        const FN_NAME: &str = "on_decision_feedback";
-        let fn_ref = self.instance.get_func(self.store.as_context_mut(), FN_NAME);
+        const FN_NAME: &str = "on-decision-feedback";
+        self.call_guest_function(FN_NAME)
+    }
+
+    /// Calls a function exported by the guest environment, enforcing the plugin's time and resource limits.
+    fn call_guest_function(&mut self, name: &str) -> Result<(), PluginExecutionError> {
+        let fn_ref = self
+            .instance
+            .get_typed_func::<(), ()>(&mut self.store, name)
+            .map_err(|_| PluginExecutionError::NotImplementedError {
+                expected: name.to_string(),
+            })?;
+        let epoch_deadline = self.epoch_deadline();
+        self.store.set_epoch_deadline(epoch_deadline);
+        self.store.data_mut().limiter.exceeded = None;
         fn_ref
-            .ok_or(PluginExecutionError::NotImplementedError {
-                expected: FN_NAME.to_string(),
-            })?
-            .call(self.store.as_context_mut(), &[], &mut [])?;
+            .call(&mut self.store, ())
+            .and_then(|()| fn_ref.post_return(&mut self.store))
+            .map_err(|err| self.classify_error(err))
+    }
 
-        Ok(())
+    /// Converts the recorded deadline into the number of epoch ticks the guest may run for.
+    fn epoch_deadline(&self) -> u64 {
+        let deadline: MutexGuard<Option<Instant>> =
+            self.host_mutable_context.deadline.lock().unwrap();
+        match *deadline {
+            Some(deadline) => {
+                let remaining = deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_nanos();
+                let tick = EPOCH_TICK.as_nanos();
+                // One extra tick is allowed because the current tick may be nearly over.
+                let ticks = remaining.div_ceil(tick) + 1;
+                ticks.try_into().unwrap_or(NO_EPOCH_DEADLINE)
+            }
+            None => NO_EPOCH_DEADLINE,
+        }
+    }
+
+    /// Identifies errors caused by the guest exceeding one of its limits.
+    fn classify_error(&mut self, err: wasmtime::Error) -> PluginExecutionError {
+        if let Some(exceeded) = self.store.data_mut().limiter.exceeded.take() {
+            return exceeded;
+        }
+        if err.downcast_ref::<Trap>() == Some(&Trap::Interrupt) {
+            return PluginExecutionError::TimeLimitExceeded;
+        }
+        err.into()
     }
 
     /// Returns the decision components from the [`RequestContext`].
@@ -654,24 +1253,319 @@ impl PluginInstance {
             tags: ctx.tags.clone(),
         }
     }
-}
 
-impl bulwark_host::BulwarkHost for RequestContext {
-    /// Returns the guest environment's configuration value as serialized JSON.
-    fn get_config(&mut self) -> Vec<u8> {
-        self.config.to_vec()
+    /// Returns the upstream request header changes recorded by the plugin, in the order they were made.
+    pub fn upstream_header_mutations(&self) -> Vec<UpstreamHeaderMutation> {
+        self.store.data().upstream_header_mutations.clone()
     }
+}
 
-    /// Returns a named value from the request context's params.
+/// Keeps instances of a [`Plugin`] created and initialized ahead of the requests that will use them.
+///
+/// Taking a prestarted instance skips instantiation and the guest's `on-init` function, leaving only the request's
+/// details to be handed over. Each instance is used by a single request and then dropped, so no state leaks between
+/// requests.
+pub struct PrestartedInstances {
+    plugin: Arc<Plugin>,
+    /// The store of state shared between plugin instances.
+    state_backend: Arc<dyn StateBackend>,
+    /// The HTTP client shared by all plugins for outbound requests.
+    http_info: Arc<HttpInfo>,
+    /// The number of instances to keep ready.
+    target: usize,
+    /// The instances that are ready to be taken.
+    ready: Mutex<Vec<PluginInstance>>,
+    /// Whether a thread is currently replenishing the ready instances.
+    replenishing: AtomicBool,
+}
+
+impl PrestartedInstances {
+    /// Creates a new, empty `PrestartedInstances`, which keeps the plugin's configured
+    /// [`prestart`](bulwark_config::Plugin::prestart) number of instances once replenished.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key name corresponding to the param value.
-    fn get_param_value(&mut self, key: &str) -> Vec<u8> {
-        let params = self.params.lock().unwrap();
-        let value = params.get(key).unwrap_or(&bulwark_wasm_sdk::Value::Null);
-        serde_json::to_vec(value).unwrap()
-    }
+    /// * `plugin` - The [`Plugin`] to create instances of.
+    /// * `state_backend` - The store of state shared between plugin instances.
+    /// * `http_info` - The HTTP client shared by all plugins for outbound requests.
+    pub fn new(
+        plugin: Arc<Plugin>,
+        state_backend: Arc<dyn StateBackend>,
+        http_info: Arc<HttpInfo>,
+    ) -> Self {
+        let target = plugin
+            .config
+            .prestart
+            .and_then(|prestart| usize::try_from(prestart).ok())
+            .unwrap_or(0);
+        PrestartedInstances {
+            plugin,
+            state_backend,
+            http_info,
+            target,
+            ready: Mutex::new(Vec::with_capacity(target)),
+            replenishing: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the plugin that instances are created from.
+    pub fn plugin(&self) -> &Arc<Plugin> {
+        &self.plugin
+    }
+
+    /// Creates an instance of the plugin for a request, taking a prestarted instance if one is ready.
+    ///
+    /// Otherwise, a new instance is created, which still needs to be [`start`](PluginInstance::start)ed.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - A key-value map that plugins use to pass values within the context of a request.
+    /// * `request` - The [`Request`](bulwark_wasm_sdk::Request) that plugins will be operating on.
+    pub fn instantiate(
+        &self,
+        params: Arc<Mutex<bulwark_wasm_sdk::Map<String, bulwark_wasm_sdk::Value>>>,
+        request: Arc<bulwark_wasm_sdk::Request>,
+    ) -> Result<PluginInstance, PluginInstantiationError> {
+        let prestarted = self
+            .ready
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        match prestarted {
+            Some(mut plugin_instance) => {
+                plugin_instance.store.data_mut().bind(params, request);
+                Ok(plugin_instance)
+            }
+            None => {
+                let request_context = RequestContext::new(
+                    self.plugin.clone(),
+                    self.state_backend.clone(),
+                    self.http_info.clone(),
+                    params,
+                    request,
+                )?;
+                PluginInstance::new(self.plugin.clone(), request_context)
+            }
+        }
+    }
+
+    /// Returns true if fewer instances are ready than the plugin is configured to keep.
+    pub fn needs_replenishing(&self) -> bool {
+        self.ready
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+            < self.target
+    }
+
+    /// Creates and starts instances until the configured number are ready.
+    ///
+    /// This blocks while guest code runs, so it should be called from a thread that's allowed to block. Each
+    /// instance's `on-init` function is bound by the plugin's timeout. If another thread is already replenishing,
+    /// this returns immediately. Failures are logged and stop replenishment until the next call.
+    pub fn replenish(&self) {
+        if self
+            .replenishing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+        while self.needs_replenishing() {
+            match self.prestart() {
+                Ok(plugin_instance) => self
+                    .ready
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(plugin_instance),
+                Err(err) => {
+                    warn!(
+                        message = "plugin prestart failed",
+                        plugin = self.plugin.reference,
+                        error_message = %err
+                    );
+                    break;
+                }
+            }
+        }
+        self.replenishing.store(false, Ordering::Release);
+    }
+
+    /// Creates an instance of the plugin that isn't associated with a request yet and runs its `on-init` function.
+    fn prestart(&self) -> Result<PluginInstance, PluginPrestartError> {
+        let request_context = RequestContext::unbound(
+            &self.plugin,
+            self.state_backend.clone(),
+            self.http_info.clone(),
+        )?;
+        let mut plugin_instance = PluginInstance::new(self.plugin.clone(), request_context)?;
+        let timeout = self
+            .plugin
+            .config
+            .timeout
+            .unwrap_or(bulwark_config::DEFAULT_PLUGIN_TIMEOUT);
+        plugin_instance.record_deadline(Instant::now() + Duration::from_millis(timeout));
+        plugin_instance.start()?;
+        Ok(plugin_instance)
+    }
+}
+
+impl RequestContext {
+    /// Returns the time remaining before the current execution phase times out, if a deadline has been recorded.
+    fn remaining_budget(&self) -> Option<Duration> {
+        let deadline: MutexGuard<Option<Instant>> =
+            self.host_mutable_context.deadline.lock().unwrap();
+        deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Records an attempt to perform an operation the plugin lacks the permission for.
+    ///
+    /// Emits an audit event naming the plugin, the missing permission, and the resource it tried to access, and
+    /// returns the error handed back to the guest.
+    ///
+    /// # Arguments
+    ///
+    /// * `permission` - The name of the missing permission, as it appears in the plugin's configuration.
+    /// * `resource` - The environment variable, domain, state key, or header the plugin attempted to access.
+    fn deny(&self, permission: &str, resource: &str) -> bulwark_host::PermissionError {
+        self.permission_denials.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            message = "permission denied",
+            plugin = self.plugin_reference,
+            permission,
+            resource
+        );
+        bulwark_host::PermissionError {
+            permission: permission.to_string(),
+            resource: resource.to_string(),
+        }
+    }
+
+    /// Records an update to one of the plugin's custom metrics.
+    ///
+    /// Rejected updates, such as those that would exceed a cardinality limit, are logged and dropped rather than
+    /// failing the plugin.
+    fn record_metric(
+        &self,
+        name: &str,
+        labels: &[bulwark_host::MetricLabelInterface],
+        update: MetricUpdate,
+    ) {
+        let labels: Vec<(&str, &str)> = labels
+            .iter()
+            .map(|label| (label.name.as_str(), label.value.as_str()))
+            .collect();
+        if let Err(err) = self
+            .metrics
+            .record(&self.plugin_reference, name, &labels, update)
+        {
+            warn!(
+                message = "metric update dropped",
+                plugin = self.plugin_reference,
+                metric = name,
+                error = %err
+            );
+        }
+    }
+
+    /// Converts the result of a state backend operation into the result returned to the guest environment.
+    ///
+    /// Failures, such as the state backend's store being unreachable, are logged and returned to the plugin rather
+    /// than trapping, so that the plugin can decide whether to fail open or closed while the state backend is
+    /// degraded.
+    fn state_result<T>(
+        &self,
+        operation: &str,
+        key: &str,
+        result: Result<T, StateError>,
+    ) -> Result<T, bulwark_host::StateError> {
+        result.map_err(|err| {
+            warn!(
+                message = "state backend failure",
+                plugin = self.plugin_reference,
+                request_id = self.request_id,
+                operation = operation,
+                key = key,
+                error = %err
+            );
+            match err {
+                StateError::Redis(err) if err.is_timeout() => bulwark_host::StateError::Timeout,
+                StateError::Redis(err)
+                    if err.kind() == redis::ErrorKind::TypeError
+                        || err.code() == Some("WRONGTYPE") =>
+                {
+                    bulwark_host::StateError::WrongType(key.to_string())
+                }
+                // The pool only fails to hand out a connection once it has waited for one.
+                StateError::Pool(_) => bulwark_host::StateError::Timeout,
+                StateError::InvalidCounter { key } | StateError::WrongType { key } => {
+                    bulwark_host::StateError::WrongType(key)
+                }
+                StateError::Redis(_)
+                | StateError::Sled(_)
+                | StateError::UnsupportedScheme { .. }
+                | StateError::Unavailable => bulwark_host::StateError::Unavailable,
+            }
+        })
+    }
+
+    /// Verifies that the plugin may access a state value under the given key.
+    fn check_state_key(&self, key: &str) -> Result<(), bulwark_host::StateError> {
+        if self
+            .permissions
+            .state
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        {
+            Ok(())
+        } else {
+            Err(bulwark_host::StateError::Permission(
+                self.deny("state", key),
+            ))
+        }
+    }
+
+    /// Verifies that the plugin may modify a header on the request forwarded to the interior service and returns
+    /// the normalized header name.
+    ///
+    /// Names reserved for Bulwark's own use are denied even if they have been granted. Names that are not valid
+    /// header names are rejected without being checked against the plugin's permissions.
+    fn check_upstream_header(&self, name: &str) -> Result<String, bulwark_host::HeaderError> {
+        let name = match http::header::HeaderName::from_bytes(name.as_bytes()) {
+            Ok(name) => name.as_str().to_string(),
+            Err(_) => return Err(bulwark_host::HeaderError::InvalidName(name.to_string())),
+        };
+        if name.starts_with("bulwark-")
+            || !self
+                .permissions
+                .upstream_headers
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&name))
+        {
+            return Err(bulwark_host::HeaderError::Permission(
+                self.deny("upstream_headers", &name),
+            ));
+        }
+        Ok(name)
+    }
+}
+
+impl bulwark_host::Host for RequestContext {
+    /// Returns the guest environment's configuration value as serialized JSON.
+    fn get_config(&mut self) -> Vec<u8> {
+        self.config.to_vec()
+    }
+
+    /// Returns a named value from the request context's params.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the param value.
+    fn get_param_value(&mut self, key: String) -> Vec<u8> {
+        let params = self.params.lock().unwrap();
+        let value = params.get(&key).unwrap_or(&bulwark_wasm_sdk::Value::Null);
+        serde_json::to_vec(value).unwrap()
+    }
 
     /// Set a named value in the request context's params.
     ///
@@ -679,10 +1573,16 @@ impl bulwark_host::BulwarkHost for RequestContext {
     ///
     /// * `key` - The key name corresponding to the param value.
     /// * `value` - The value to record. Values are serialized JSON.
-    fn set_param_value(&mut self, key: &str, value: &[u8]) {
+    fn set_param_value(
+        &mut self,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), bulwark_host::ParamError> {
+        let value: bulwark_wasm_sdk::Value = serde_json::from_slice(&value)
+            .map_err(|err| bulwark_host::ParamError::InvalidJson(err.to_string()))?;
         let mut params = self.params.lock().unwrap();
-        let value: bulwark_wasm_sdk::Value = serde_json::from_slice(value).unwrap();
-        params.insert(key.to_string(), value);
+        params.insert(key, value);
+        Ok(())
     }
 
     /// Returns a named environment variable value as bytes.
@@ -690,18 +1590,19 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// # Arguments
     ///
     /// * `key` - The environment variable name. Case-sensitive.
-    fn get_env_bytes(&mut self, key: &str) -> Vec<u8> {
-        let allowed_env_vars = self
-            .permissions
-            .env
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_env_vars.contains(&key.to_string()) {
-            panic!("access to environment variable denied");
+    fn get_env_bytes(&mut self, key: String) -> Result<Vec<u8>, bulwark_host::EnvError> {
+        if !self.permissions.env.contains(&key) {
+            return Err(bulwark_host::EnvError::Permission(self.deny("env", &key)));
+        }
+        match std::env::var(&key) {
+            Ok(value) => Ok(value.into_bytes()),
+            Err(std::env::VarError::NotPresent) => {
+                Err(bulwark_host::EnvError::Missing(key.to_string()))
+            }
+            Err(std::env::VarError::NotUnicode(_)) => {
+                Err(bulwark_host::EnvError::NotUnicode(key.to_string()))
+            }
         }
-        // TODO: return result instead of panic due to OsString/String stuff
-        std::env::var(key).unwrap().as_bytes().to_vec()
     }
 
     /// Returns the incoming request associated with the request context.
@@ -709,11 +1610,13 @@ impl bulwark_host::BulwarkHost for RequestContext {
         self.request.clone()
     }
 
-    /// Returns the response received from the interior service.
-    fn get_response(&mut self) -> bulwark_host::ResponseInterface {
+    /// Returns the response received from the interior service, if available.
+    ///
+    /// The response is only available once the interior service has responded.
+    fn get_response(&mut self) -> Option<bulwark_host::ResponseInterface> {
         let response: MutexGuard<Option<bulwark_host::ResponseInterface>> =
             self.host_mutable_context.response.lock().unwrap();
-        response.to_owned().unwrap()
+        response.to_owned()
     }
 
     /// Returns the originating client's IP address, if available.
@@ -721,25 +1624,215 @@ impl bulwark_host::BulwarkHost for RequestContext {
         self.client_ip
     }
 
+    /// Returns the identifier used to correlate the request across log events, plugins, and the interior service.
+    fn get_request_id(&mut self) -> String {
+        self.request_id.clone()
+    }
+
+    /// Returns the number of milliseconds remaining before the current execution phase times out.
+    ///
+    /// Returns zero if the deadline has already passed and [`u64::MAX`] if no deadline has been recorded.
+    fn get_remaining_budget(&mut self) -> u64 {
+        self.remaining_budget().map_or(u64::MAX, |remaining| {
+            remaining.as_millis().try_into().unwrap_or(u64::MAX)
+        })
+    }
+
+    /// Logs an event on behalf of the plugin.
+    ///
+    /// The event is attributed to the plugin and the request being processed. Field values are logged as a single
+    /// JSON object because event field names can't be chosen at runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The verbosity level of the event.
+    /// * `message` - The message describing the event.
+    /// * `fields` - Additional named values describing the event.
+    fn log(
+        &mut self,
+        level: bulwark_host::LogLevelInterface,
+        message: String,
+        fields: Vec<bulwark_host::LogFieldInterface>,
+    ) {
+        let fields = serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.name.to_string(),
+                        serde_json::Value::String(field.value.to_string()),
+                    )
+                })
+                .collect(),
+        );
+        // Event levels must be constant, so each level needs its own call site.
+        macro_rules! plugin_event {
+            ($level:expr) => {
+                tracing::event!(
+                    target: PLUGIN_LOG_TARGET,
+                    $level,
+                    message = "plugin log",
+                    plugin = self.plugin_reference,
+                    request_id = self.request_id,
+                    log_message = message,
+                    fields = %fields
+                )
+            };
+        }
+        match level {
+            bulwark_host::LogLevelInterface::Error => plugin_event!(tracing::Level::ERROR),
+            bulwark_host::LogLevelInterface::Warn => plugin_event!(tracing::Level::WARN),
+            bulwark_host::LogLevelInterface::Info => plugin_event!(tracing::Level::INFO),
+            bulwark_host::LogLevelInterface::Debug => plugin_event!(tracing::Level::DEBUG),
+            bulwark_host::LogLevelInterface::Trace => plugin_event!(tracing::Level::TRACE),
+        }
+    }
+
+    /// Increases a counter owned by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the counter.
+    /// * `labels` - The label names and values identifying the series to increase.
+    /// * `delta` - The amount to increase the counter by.
+    fn increment_counter(
+        &mut self,
+        name: String,
+        labels: Vec<bulwark_host::MetricLabelInterface>,
+        delta: u64,
+    ) {
+        self.record_metric(&name, &labels, MetricUpdate::Increment(delta));
+    }
+
+    /// Sets the value of a gauge owned by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the gauge.
+    /// * `labels` - The label names and values identifying the series to set.
+    /// * `value` - The new value of the gauge.
+    fn set_gauge(
+        &mut self,
+        name: String,
+        labels: Vec<bulwark_host::MetricLabelInterface>,
+        value: f64,
+    ) {
+        self.record_metric(&name, &labels, MetricUpdate::Set(value));
+    }
+
+    /// Adds to the value of a gauge owned by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the gauge.
+    /// * `labels` - The label names and values identifying the series to change.
+    /// * `delta` - The amount to add to the gauge, which may be negative.
+    fn add_gauge(
+        &mut self,
+        name: String,
+        labels: Vec<bulwark_host::MetricLabelInterface>,
+        delta: f64,
+    ) {
+        self.record_metric(&name, &labels, MetricUpdate::Add(delta));
+    }
+
+    /// Records an observation in a histogram owned by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the histogram.
+    /// * `labels` - The label names and values identifying the series to record in.
+    /// * `value` - The observed value.
+    fn observe_histogram(
+        &mut self,
+        name: String,
+        labels: Vec<bulwark_host::MetricLabelInterface>,
+        value: f64,
+    ) {
+        self.record_metric(&name, &labels, MetricUpdate::Observe(value));
+    }
+
+    /// Adds a header value to the request forwarded to the interior service.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    /// * `value` - The header value bytes.
+    fn add_upstream_header(
+        &mut self,
+        name: String,
+        value: Vec<u8>,
+    ) -> Result<(), bulwark_host::HeaderError> {
+        let name = self.check_upstream_header(&name)?;
+        self.upstream_header_mutations
+            .push(UpstreamHeaderMutation::Add {
+                name,
+                value: value.to_vec(),
+            });
+        Ok(())
+    }
+
+    /// Replaces any values for a header on the request forwarded to the interior service.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    /// * `value` - The header value bytes.
+    fn set_upstream_header(
+        &mut self,
+        name: String,
+        value: Vec<u8>,
+    ) -> Result<(), bulwark_host::HeaderError> {
+        let name = self.check_upstream_header(&name)?;
+        self.upstream_header_mutations
+            .push(UpstreamHeaderMutation::Set {
+                name,
+                value: value.to_vec(),
+            });
+        Ok(())
+    }
+
+    /// Removes a header from the request forwarded to the interior service.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    fn remove_upstream_header(&mut self, name: String) -> Result<(), bulwark_host::HeaderError> {
+        let name = self.check_upstream_header(&name)?;
+        self.upstream_header_mutations
+            .push(UpstreamHeaderMutation::Remove { name });
+        Ok(())
+    }
+
     /// Begins an outbound request. Returns a request ID used by `add_request_header` and `set_request_body`.
     ///
+    /// Returns an error to the plugin if the URI is not an absolute URI with a host, if the plugin has not been
+    /// granted the host, or if the method is not supported.
+    ///
     /// # Arguments
     ///
     /// * `method` - The HTTP method
     /// * `uri` - The absolute URI of the resource to request
-    fn prepare_request(&mut self, method: &str, uri: &str) -> u64 {
-        let allowed_http_domains = self
+    fn prepare_request(
+        &mut self,
+        method: String,
+        uri: String,
+    ) -> Result<u64, bulwark_host::OutboundError> {
+        let parsed_uri = Url::parse(&uri)
+            .map_err(|_| bulwark_host::OutboundError::InvalidUri(uri.to_string()))?;
+        let requested_domain = parsed_uri
+            .host_str()
+            .ok_or_else(|| bulwark_host::OutboundError::InvalidUri(uri.to_string()))?;
+        if !self
             .permissions
             .http
             .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        let parsed_uri = Url::parse(uri).unwrap();
-        let requested_domain = parsed_uri.domain().unwrap();
-        if !allowed_http_domains.contains(&requested_domain.to_string()) {
-            panic!("access to http resource denied");
+            .any(|allowed| allowed.eq_ignore_ascii_case(requested_domain))
+        {
+            return Err(bulwark_host::OutboundError::Permission(
+                self.deny("http", requested_domain),
+            ));
         }
-        let mut outbound_requests = self.outbound_http.lock().unwrap();
         let method = match method.to_ascii_uppercase().as_str() {
             "GET" => reqwest::Method::GET,
             "HEAD" => reqwest::Method::HEAD,
@@ -749,12 +1842,20 @@ impl bulwark_host::BulwarkHost for RequestContext {
             "DELETE" => reqwest::Method::DELETE,
             "OPTIONS" => reqwest::Method::OPTIONS,
             "TRACE" => reqwest::Method::TRACE,
-            _ => panic!("unsupported http method"),
+            _ => {
+                return Err(bulwark_host::OutboundError::UnsupportedMethod(
+                    method.to_string(),
+                ))
+            }
         };
-        let builder = self.http_client.request(method, uri);
-        let index: u64 = outbound_requests.len().try_into().unwrap();
-        outbound_requests.insert(index, builder);
-        (outbound_requests.len() - 1).try_into().unwrap()
+        let builder = self.http_info.client.request(method, &uri);
+        let request_id = self.next_outbound_request_id;
+        self.next_outbound_request_id += 1;
+        self.outbound_http
+            .lock()
+            .unwrap()
+            .insert(request_id, builder);
+        Ok(request_id)
     }
 
     /// Adds a request header to an outbound HTTP request.
@@ -764,18 +1865,34 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// * `request_id` - The request ID received from `prepare_request`.
     /// * `name` - The header name.
     /// * `value` - The header value bytes.
-    fn add_request_header(&mut self, request_id: u64, name: &str, value: &[u8]) {
+    fn add_request_header(
+        &mut self,
+        request_id: u64,
+        name: String,
+        value: Vec<u8>,
+    ) -> Result<(), bulwark_host::OutboundError> {
+        let name = http::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| bulwark_host::OutboundError::InvalidHeader(name.to_string()))?;
+        let value = http::header::HeaderValue::from_bytes(&value)
+            .map_err(|_| bulwark_host::OutboundError::InvalidHeader(name.to_string()))?;
         let mut outbound_requests = self.outbound_http.lock().unwrap();
         // remove/insert to avoid move issues
-        let mut builder = outbound_requests.remove(&request_id).unwrap();
+        let mut builder = outbound_requests
+            .remove(&request_id)
+            .ok_or(bulwark_host::OutboundError::UnknownRequest(request_id))?;
         builder = builder.header(name, value);
         outbound_requests.insert(request_id, builder);
+        Ok(())
     }
 
     /// Sets the request body, if any. Returns the response.
     ///
     /// This function is still required even if the request does not have a body. An empty body is acceptable.
     ///
+    /// Returns an error to the plugin if the request fails, including when it takes longer than either the
+    /// configured outbound timeout or the plugin's remaining budget, or if the response body exceeds the configured
+    /// size limit.
+    ///
     /// # Arguments
     ///
     /// * `request_id` - The request ID received from `prepare_request`.
@@ -783,15 +1900,28 @@ impl bulwark_host::BulwarkHost for RequestContext {
     fn set_request_body(
         &mut self,
         request_id: u64,
-        body: &[u8],
-    ) -> bulwark_host::ResponseInterface {
-        // TODO: handle basic error scenarios like timeouts
+        body: Vec<u8>,
+    ) -> Result<bulwark_host::ResponseInterface, bulwark_host::OutboundError> {
         let mut outbound_requests = self.outbound_http.lock().unwrap();
         // remove/insert to avoid move issues
-        let builder = outbound_requests.remove(&request_id).unwrap();
-        let builder = builder.body(body.to_vec());
-
-        let response = builder.send().unwrap();
+        let builder = outbound_requests
+            .remove(&request_id)
+            .ok_or(bulwark_host::OutboundError::UnknownRequest(request_id))?;
+        // There's no point waiting on a response after the plugin has run out of time to use it.
+        let timeout = self
+            .remaining_budget()
+            .map_or(self.http_info.timeout, |remaining| {
+                remaining.min(self.http_info.timeout)
+            });
+        let builder = builder.body(body.to_vec()).timeout(timeout);
+
+        let response = builder.send().map_err(|err| {
+            if err.is_timeout() {
+                bulwark_host::OutboundError::Timeout
+            } else {
+                bulwark_host::OutboundError::Transport(err.to_string())
+            }
+        })?;
         let status: u32 = response.status().as_u16().try_into().unwrap();
         // need to read headers before body because retrieving body bytes will move the response
         let headers: Vec<HeaderInterface> = response
@@ -802,16 +1932,32 @@ impl bulwark_host::BulwarkHost for RequestContext {
                 value: value.as_bytes().to_vec(),
             })
             .collect();
-        let body = response.bytes().unwrap().to_vec();
+        let max_response_size = self.http_info.max_response_size;
+        let limit: u64 = max_response_size.try_into().unwrap_or(u64::MAX);
+        if response.content_length().unwrap_or(0) > limit {
+            return Err(bulwark_host::OutboundError::ResponseTooLarge(limit));
+        }
+        // The content length may be missing or wrong, so the body is read up to one byte past the limit.
+        let mut body = Vec::new();
+        response
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut body)
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::TimedOut => bulwark_host::OutboundError::Timeout,
+                _ => bulwark_host::OutboundError::Transport(err.to_string()),
+            })?;
+        if body.len() > max_response_size {
+            return Err(bulwark_host::OutboundError::ResponseTooLarge(limit));
+        }
         let content_length: u64 = body.len().try_into().unwrap();
-        bulwark_host::ResponseInterface {
+        Ok(bulwark_host::ResponseInterface {
             status,
             headers,
             chunk: body,
             chunk_start: 0,
             chunk_length: content_length,
             end_of_stream: true,
-        }
+        })
     }
 
     /// Records the decision value the plugin wants to return.
@@ -831,221 +1977,356 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// # Arguments
     ///
     /// * `tags` - The list of tags to associate with a [`Decision`].
-    fn set_tags(&mut self, tags: Vec<&str>) {
-        self.tags = tags
-            .iter()
-            .map(|s| String::from(*s))
-            .collect::<Vec<String>>();
+    fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
     }
 
     /// Returns the combined decision, if available.
     ///
     /// Typically used in the feedback phase.
-    fn get_combined_decision(&mut self) -> bulwark_host::DecisionInterface {
+    fn get_combined_decision(&mut self) -> Option<bulwark_host::DecisionInterface> {
         let combined_decision: MutexGuard<Option<bulwark_host::DecisionInterface>> =
             self.host_mutable_context.combined_decision.lock().unwrap();
-        // TODO: this should probably be an Option return type rather than unwrapping here
-        combined_decision.to_owned().unwrap()
+        combined_decision.to_owned()
     }
 
     /// Returns the combined set of tags associated with a decision, if available.
     ///
     /// Typically used in the feedback phase.
-    fn get_combined_tags(&mut self) -> Vec<String> {
+    fn get_combined_tags(&mut self) -> Option<Vec<String>> {
         let combined_tags: MutexGuard<Option<Vec<String>>> =
             self.host_mutable_context.combined_tags.lock().unwrap();
-        // TODO: this should probably be an Option return type rather than unwrapping here
-        combined_tags.to_owned().unwrap()
+        combined_tags.to_owned()
     }
 
     /// Returns the outcome of the combined decision, if available.
     ///
     /// Typically used in the feedback phase.
-    fn get_outcome(&mut self) -> bulwark_host::OutcomeInterface {
+    fn get_outcome(&mut self) -> Option<bulwark_host::OutcomeInterface> {
         let outcome: MutexGuard<Option<bulwark_host::OutcomeInterface>> =
             self.host_mutable_context.outcome.lock().unwrap();
-        // TODO: this should probably be an Option return type rather than unwrapping here
-        outcome.to_owned().unwrap()
+        outcome.to_owned()
     }
 
-    /// Returns the named state value retrieved from Redis.
+    /// Returns the named state value retrieved from the state backend.
     ///
     /// Also used to retrieve a counter value.
     ///
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state value.
-    fn get_remote_state(&mut self, key: &str) -> Vec<u8> {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    fn get_remote_state(&mut self, key: String) -> Result<Vec<u8>, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
 
-        let pool = &self.redis_info.clone().unwrap().pool;
-        let mut conn = pool.get().unwrap();
-        conn.get(key).unwrap()
+        let result = self.state_backend.get(&key);
+        self.state_result("get", &key, result)
     }
 
-    /// Set a named value in Redis.
+    /// Set a named value in the state backend.
     ///
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state value.
-    /// * `value` - The value to record. Values are byte strings, but may be interpreted differently by Redis depending on context.
-    fn set_remote_state(&mut self, key: &str, value: &[u8]) {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    /// * `value` - The value to record. Values are byte strings, but may be interpreted differently by the state backend depending on context.
+    fn set_remote_state(
+        &mut self,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<(), bulwark_host::StateError> {
+        self.check_state_key(&key)?;
 
-        let pool = &self.redis_info.clone().unwrap().pool;
-        let mut conn = pool.get().unwrap();
-        conn.set(key, value.to_vec()).unwrap()
+        let result = self.state_backend.set(&key, &value);
+        self.state_result("set", &key, result)
     }
 
-    /// Increments a named counter in Redis.
+    /// Increments a named counter in the state backend.
     ///
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state counter.
-    fn increment_remote_state(&mut self, key: &str) -> i64 {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    fn increment_remote_state(&mut self, key: String) -> Result<i64, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
 
-        let pool = &self.redis_info.clone().unwrap().pool;
-        let mut conn = pool.get().unwrap();
-        conn.incr(key, 1).unwrap()
+        let result = self.state_backend.increment(&key, 1);
+        self.state_result("increment", &key, result)
     }
 
-    /// Increments a named counter in Redis by a specified delta value.
+    /// Increments a named counter in the state backend by a specified delta value.
     ///
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state counter.
     /// * `delta` - The amount to increase the counter by.
-    fn increment_remote_state_by(&mut self, key: &str, delta: i64) -> i64 {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    fn increment_remote_state_by(
+        &mut self,
+        key: String,
+        delta: i64,
+    ) -> Result<i64, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
 
-        let pool = &self.redis_info.clone().unwrap().pool;
-        let mut conn = pool.get().unwrap();
-        conn.incr(key, delta).unwrap()
+        let result = self.state_backend.increment(&key, delta);
+        self.state_result("increment", &key, result)
     }
 
-    /// Sets an expiration on a named value in Redis.
+    /// Sets an expiration on a named value in the state backend.
     ///
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state value.
     /// * `ttl` - The time-to-live for the value in seconds.
-    fn set_remote_ttl(&mut self, key: &str, ttl: i64) {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    fn set_remote_ttl(&mut self, key: String, ttl: i64) -> Result<(), bulwark_host::StateError> {
+        self.check_state_key(&key)?;
 
-        let pool = &self.redis_info.clone().unwrap().pool;
-        let mut conn = pool.get().unwrap();
-        conn.expire(key, ttl.try_into().unwrap()).unwrap()
+        let result = self.state_backend.expire(&key, ttl);
+        self.state_result("expire", &key, result)
     }
 
-    /// Increments a rate limit, returning the number of attempts so far and the expiration time.
-    ///
-    /// The rate limiter is a counter over a period of time. At the end of the period, it will expire,
-    /// beginning a new period. Window periods should be set to the longest amount of time that a client should
-    /// be locked out for. The plugin is responsible for performing all rate-limiting logic with the counter
-    /// value it receives.
+    /// Sets a named value in the state backend that expires after a time-to-live, as a single atomic operation.
     ///
     /// # Arguments
     ///
-    /// * `key` - The key name corresponding to the state counter.
-    /// * `delta` - The amount to increase the counter by.
-    /// * `window` - How long each period should be in seconds.
-    fn increment_rate_limit(
+    /// * `key` - The key name corresponding to the state value.
+    /// * `value` - The value to record.
+    /// * `ttl` - The time-to-live for the value in seconds.
+    fn set_remote_state_with_ttl(
         &mut self,
-        key: &str,
-        delta: i64,
-        window: i64,
-    ) -> bulwark_host::RateInterface {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+        key: String,
+        value: Vec<u8>,
+        ttl: i64,
+    ) -> Result<(), bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.set_with_ttl(&key, &value, ttl);
+        self.state_result("set_with_ttl", &key, result)
+    }
 
-        let redis_info = self.redis_info.clone().unwrap();
-        let mut conn = redis_info.pool.get().unwrap();
-        let dt = Utc::now();
-        let timestamp: i64 = dt.timestamp();
-        let script = redis_info.registry.increment_rate_limit.clone();
-        let (attempts, expiration) = script
-            .key(key)
-            .arg(delta)
-            .arg(window)
-            .arg(timestamp)
-            .invoke::<(i64, i64)>(conn.deref_mut())
-            .unwrap();
-        bulwark_host::RateInterface {
-            attempts,
-            expiration,
+    /// Deletes a named value, set, or sorted set from the state backend, returning true if it existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the state value.
+    fn delete_remote_state(&mut self, key: String) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.delete(&key);
+        self.state_result("delete", &key, result)
+    }
+
+    /// Returns several named state values from the state backend in order.
+    ///
+    /// Permission must have been granted for every key.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The key names corresponding to the state values.
+    fn get_remote_states(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<Vec<Vec<u8>>, bulwark_host::StateError> {
+        for key in &keys {
+            self.check_state_key(key)?;
         }
+
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let result = self.state_backend.get_many(&keys);
+        self.state_result("get_many", &keys.join(" "), result)
+    }
+
+    /// Sets a named value in the state backend only if its current value matches an expected value, returning true
+    /// if it was set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the state value.
+    /// * `expected` - The expected current value. An empty value matches a value that doesn't exist.
+    /// * `value` - The value to record.
+    fn compare_and_set_remote_state(
+        &mut self,
+        key: String,
+        expected: Vec<u8>,
+        value: Vec<u8>,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.compare_and_set(&key, &expected, &value);
+        self.state_result("compare_and_set", &key, result)
+    }
+
+    /// Adds a member to a named set in the state backend, returning true if it wasn't already a member.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the set.
+    /// * `member` - The member to add.
+    fn add_remote_set_member(
+        &mut self,
+        key: String,
+        member: Vec<u8>,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.add_set_member(&key, &member);
+        self.state_result("add_set_member", &key, result)
+    }
+
+    /// Removes a member from a named set in the state backend, returning true if it was a member.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the set.
+    /// * `member` - The member to remove.
+    fn remove_remote_set_member(
+        &mut self,
+        key: String,
+        member: Vec<u8>,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.remove_set_member(&key, &member);
+        self.state_result("remove_set_member", &key, result)
+    }
+
+    /// Returns true if a value is a member of a named set in the state backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the set.
+    /// * `member` - The value to look for.
+    fn is_remote_set_member(
+        &mut self,
+        key: String,
+        member: Vec<u8>,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.is_set_member(&key, &member);
+        self.state_result("is_set_member", &key, result)
+    }
+
+    /// Returns the members of a named set in the state backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the set.
+    fn get_remote_set_members(
+        &mut self,
+        key: String,
+    ) -> Result<Vec<Vec<u8>>, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.set_members(&key);
+        self.state_result("set_members", &key, result)
+    }
+
+    /// Adds a member to a named sorted set in the state backend or updates its score, returning true if it wasn't
+    /// already a member.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the sorted set.
+    /// * `member` - The member to add.
+    /// * `score` - The score the sorted set is ordered by.
+    fn add_remote_sorted_set_member(
+        &mut self,
+        key: String,
+        member: Vec<u8>,
+        score: f64,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self
+            .state_backend
+            .add_sorted_set_member(&key, &member, score);
+        self.state_result("add_sorted_set_member", &key, result)
+    }
+
+    /// Removes a member from a named sorted set in the state backend, returning true if it was a member.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the sorted set.
+    /// * `member` - The member to remove.
+    fn remove_remote_sorted_set_member(
+        &mut self,
+        key: String,
+        member: Vec<u8>,
+    ) -> Result<bool, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.remove_sorted_set_member(&key, &member);
+        self.state_result("remove_sorted_set_member", &key, result)
+    }
+
+    /// Returns the members of a named sorted set in the state backend with scores between `min` and `max`
+    /// inclusive, ordered by score.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the sorted set.
+    /// * `min` - The lowest score to include.
+    /// * `max` - The highest score to include.
+    fn get_remote_sorted_set_range(
+        &mut self,
+        key: String,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<bulwark_host::ScoredMemberInterface>, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.sorted_set_range(&key, min, max);
+        Ok(self
+            .state_result("sorted_set_range", &key, result)?
+            .into_iter()
+            .map(|(member, score)| bulwark_host::ScoredMemberInterface { member, score })
+            .collect())
+    }
+
+    /// Removes the members of a named sorted set in the state backend with scores between `min` and `max`
+    /// inclusive, returning how many were removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the sorted set.
+    /// * `min` - The lowest score to remove.
+    /// * `max` - The highest score to remove.
+    fn remove_remote_sorted_set_range(
+        &mut self,
+        key: String,
+        min: f64,
+        max: f64,
+    ) -> Result<u64, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let result = self.state_backend.remove_sorted_set_range(&key, min, max);
+        self.state_result("remove_sorted_set_range", &key, result)
+    }
+
+    /// Increments a rate limit, returning the number of attempts so far and the expiration time.
+    ///
+    /// The rate limiter is a counter over a period of time. At the end of the period, it will expire,
+    /// beginning a new period. Window periods should be set to the longest amount of time that a client should
+    /// be locked out for. The plugin is responsible for performing all rate-limiting logic with the counter
+    /// value it receives.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the state counter.
+    /// * `delta` - The amount to increase the counter by.
+    /// * `window` - How long each period should be in seconds.
+    fn increment_rate_limit(
+        &mut self,
+        key: String,
+        delta: i64,
+        window: i64,
+    ) -> Result<bulwark_host::RateInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp();
+        let result = self
+            .state_backend
+            .increment_rate_limit(&key, delta, window, timestamp);
+        self.state_result("increment_rate_limit", &key, result)
+            .map(Into::into)
     }
 
     /// Checks a rate limit, returning the number of attempts so far and the expiration time.
@@ -1055,35 +2336,16 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state counter.
-    fn check_rate_limit(&mut self, key: &str) -> bulwark_host::RateInterface {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
-
-        let redis_info = self.redis_info.clone().unwrap();
-        let mut conn = redis_info.pool.get().unwrap();
-        let dt = Utc::now();
-        let timestamp: i64 = dt.timestamp();
-        let script = redis_info.registry.check_rate_limit.clone();
-        let (attempts, expiration) = script
-            .key(key)
-            .arg(timestamp)
-            .invoke::<(i64, i64)>(conn.deref_mut())
-            .unwrap();
-        bulwark_host::RateInterface {
-            attempts,
-            expiration,
-        }
+    fn check_rate_limit(
+        &mut self,
+        key: String,
+    ) -> Result<bulwark_host::RateInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp();
+        let result = self.state_backend.check_rate_limit(&key, timestamp);
+        self.state_result("check_rate_limit", &key, result)
+            .map(Into::into)
     }
 
     /// Increments a circuit breaker, returning the generation count, success count, failure count,
@@ -1101,53 +2363,23 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// * `window` - How long each period should be in seconds.
     fn increment_breaker(
         &mut self,
-        key: &str,
+        key: String,
         success_delta: i64,
         failure_delta: i64,
         window: i64,
-    ) -> bulwark_host::BreakerInterface {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
-
-        let redis_info = self.redis_info.clone().unwrap();
-        let mut conn = redis_info.pool.get().unwrap();
-        let dt = Utc::now();
-        let timestamp: i64 = dt.timestamp();
-        let script = redis_info.registry.increment_breaker.clone();
-        let (
-            generation,
-            successes,
-            failures,
-            consecutive_successes,
-            consecutive_failures,
-            expiration,
-        ) = script
-            .key(key)
-            .arg(success_delta)
-            .arg(failure_delta)
-            .arg(window)
-            .arg(timestamp)
-            .invoke::<(i64, i64, i64, i64, i64, i64)>(conn.deref_mut())
-            .unwrap();
-        bulwark_host::BreakerInterface {
-            generation,
-            successes,
-            failures,
-            consecutive_successes,
-            consecutive_failures,
-            expiration,
-        }
+    ) -> Result<bulwark_host::BreakerInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp();
+        let result = self.state_backend.increment_breaker(
+            &key,
+            success_delta,
+            failure_delta,
+            window,
+            timestamp,
+        );
+        self.state_result("increment_breaker", &key, result)
+            .map(Into::into)
     }
 
     /// Checks a circuit breaker, returning the generation count, success count, failure count,
@@ -1158,75 +2390,130 @@ impl bulwark_host::BulwarkHost for RequestContext {
     /// # Arguments
     ///
     /// * `key` - The key name corresponding to the state counter.
-    fn check_breaker(&mut self, key: &str) -> bulwark_host::BreakerInterface {
-        // TODO: figure out how to extract to a helper function?
-        let allowed_key_prefixes = self
-            .permissions
-            .state
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<String>>();
-        if !allowed_key_prefixes
-            .iter()
-            .any(|prefix| key.starts_with(prefix))
-        {
-            panic!("access to state value by prefix denied");
-        }
+    fn check_breaker(
+        &mut self,
+        key: String,
+    ) -> Result<bulwark_host::BreakerInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp();
+        let result = self.state_backend.check_breaker(&key, timestamp);
+        self.state_result("check_breaker", &key, result)
+            .map(Into::into)
+    }
 
-        let redis_info = self.redis_info.clone().unwrap();
-        let mut conn = redis_info.pool.get().unwrap();
-        let dt = Utc::now();
-        let timestamp: i64 = dt.timestamp();
-        let script = redis_info.registry.check_breaker.clone();
-        let (
-            generation,
-            successes,
-            failures,
-            consecutive_successes,
-            consecutive_failures,
-            expiration,
-        ) = script
-            .key(key)
-            .arg(timestamp)
-            .invoke::<(i64, i64, i64, i64, i64, i64)>(conn.deref_mut())
-            .unwrap();
-        bulwark_host::BreakerInterface {
-            generation,
-            successes,
-            failures,
-            consecutive_successes,
-            consecutive_failures,
-            expiration,
-        }
+    /// Counts attempts against a sliding window rate limit, returning whether the attempt is allowed, how many
+    /// further attempts are allowed, and how many seconds until another attempt would be allowed.
+    ///
+    /// Unlike `increment_rate_limit`, the window slides continuously rather than resetting at fixed intervals, so
+    /// clients can't burst to twice the limit around the end of a period. Every attempt is counted, including those
+    /// over the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the rate limit.
+    /// * `delta` - The number of attempts to count. Zero checks the rate limit without counting an attempt.
+    /// * `limit` - The number of attempts allowed within the window.
+    /// * `window` - How long the window is in seconds.
+    fn increment_sliding_window(
+        &mut self,
+        key: String,
+        delta: i64,
+        limit: i64,
+        window: i64,
+    ) -> Result<bulwark_host::AllowanceInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp_millis();
+        let window = window.max(1).saturating_mul(1000);
+        let result = self
+            .state_backend
+            .increment_sliding_window(&key, delta, limit, window, timestamp);
+        self.state_result("increment_sliding_window", &key, result)
+            .map(Into::into)
+    }
+
+    /// Takes tokens from a token bucket, returning whether they were taken, how many tokens remain, and how many
+    /// seconds until the same number of tokens could be taken again.
+    ///
+    /// The bucket allows bursts of up to `capacity` tokens while limiting the sustained rate to `refill` tokens per
+    /// `window`. Tokens are only taken if enough are available.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key name corresponding to the token bucket.
+    /// * `tokens` - The number of tokens to take. Zero checks the bucket without taking any tokens.
+    /// * `capacity` - The maximum number of tokens the bucket holds.
+    /// * `refill` - The number of tokens added to the bucket per window.
+    /// * `window` - How long it takes to add `refill` tokens, in seconds.
+    fn take_token_bucket(
+        &mut self,
+        key: String,
+        tokens: i64,
+        capacity: i64,
+        refill: i64,
+        window: i64,
+    ) -> Result<bulwark_host::AllowanceInterface, bulwark_host::StateError> {
+        self.check_state_key(&key)?;
+
+        let timestamp = Utc::now().timestamp_millis();
+        let window = window.max(1).saturating_mul(1000);
+        let result = self
+            .state_backend
+            .take_token_bucket(&key, tokens, capacity, refill, window, timestamp);
+        self.state_result("take_token_bucket", &key, result)
+            .map(Into::into)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::MemoryStateBackend;
 
-    #[test]
-    fn test_wasm_execution() -> Result<(), Box<dyn std::error::Error>> {
-        let wasm_bytes = include_bytes!("../tests/bulwark-blank-slate.wasm");
-        let plugin = Arc::new(Plugin::from_bytes(
-            "bulwark-blank-slate.wasm".to_string(),
-            wasm_bytes,
-            &bulwark_config::Plugin::default(),
-        )?);
-        let request = Arc::new(
+    /// A `GET /` request without a body.
+    fn get_request() -> Arc<bulwark_wasm_sdk::Request> {
+        Arc::new(
             http::Request::builder()
                 .method("GET")
                 .uri("/")
                 .version(http::Version::HTTP_11)
-                .body(bulwark_wasm_sdk::BodyChunk {
-                    content: vec![],
-                    start: 0,
-                    size: 0,
-                    end_of_stream: true,
-                })?,
-        );
-        let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
-        let request_context = RequestContext::new(plugin.clone(), None, params, request)?;
+                .body(bulwark_wasm_sdk::NO_BODY)
+                .unwrap(),
+        )
+    }
+
+    /// Creates a context for a plugin to process the request with, backed by its own params and in-memory state.
+    fn request_context(
+        plugin: &Arc<Plugin>,
+        request: Arc<bulwark_wasm_sdk::Request>,
+    ) -> Result<RequestContext, ContextInstantiationError> {
+        RequestContext::new(
+            plugin.clone(),
+            Arc::new(MemoryStateBackend::default()),
+            Arc::new(HttpInfo::default()),
+            Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new())),
+            request,
+        )
+    }
+
+    /// Loads the blank slate plugin with the given configuration and creates a context for a `GET /` request.
+    fn context(
+        plugin_config: bulwark_config::Plugin,
+    ) -> Result<(Arc<Plugin>, RequestContext), Box<dyn std::error::Error>> {
+        let plugin = Arc::new(Plugin::from_bytes(
+            &PluginEngine::new(None)?,
+            "bulwark-blank-slate.wasm".to_string(),
+            include_bytes!("../tests/bulwark-blank-slate.wasm"),
+            &plugin_config,
+        )?);
+        let request_context = request_context(&plugin, get_request())?;
+        Ok((plugin, request_context))
+    }
+
+    #[test]
+    fn test_wasm_execution() -> Result<(), Box<dyn std::error::Error>> {
+        let (plugin, request_context) = context(bulwark_config::Plugin::default())?;
         let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
         plugin_instance.start()?;
         let decision_components = plugin_instance.decision();
@@ -1238,10 +2525,356 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_request_redaction() -> Result<(), Box<dyn std::error::Error>> {
+        let (_, mut request_context) = context(bulwark_config::Plugin {
+            permissions: bulwark_config::Permissions {
+                cookies: vec!["theme".to_string()],
+                ..Default::default()
+            },
+            redaction: bulwark_config::Redaction {
+                cookies: vec!["session".to_string(), "theme".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+        let request = Arc::new(
+            http::Request::builder()
+                .method("GET")
+                .uri("/")
+                .version(http::Version::HTTP_11)
+                .header("Authorization", "Bearer secret")
+                .header("Cookie", "session=secret; theme=dark")
+                .header("User-Agent", "curl/7.88.1")
+                .body(bulwark_wasm_sdk::NO_BODY)?,
+        );
+        request_context.bind(Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new())), request);
+
+        let headers = request_context
+            .request
+            .headers
+            .iter()
+            .map(|header| (header.name.as_str(), header.value.as_slice()))
+            .collect::<Vec<(&str, &[u8])>>();
+        assert_eq!(
+            headers,
+            vec![
+                ("authorization", bulwark_config::REDACTION_MASK.as_bytes()),
+                ("cookie", b"session=[redacted]; theme=dark".as_slice()),
+                ("user-agent", b"curl/7.88.1".as_slice()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upstream_header_permissions() -> Result<(), Box<dyn std::error::Error>> {
+        use bulwark_host::Host;
+
+        let (plugin, mut request_context) = context(bulwark_config::Plugin {
+            permissions: bulwark_config::Permissions {
+                upstream_headers: vec!["X-Client-Risk".to_string(), "Bulwark-Decision".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        request_context.add_upstream_header("X-Client-Risk".to_string(), b"0.25".to_vec())?;
+        request_context.set_upstream_header("x-client-risk".to_string(), b"0.5".to_vec())?;
+        request_context.remove_upstream_header("x-client-risk".to_string())?;
+        assert_eq!(
+            request_context.upstream_header_mutations,
+            vec![
+                UpstreamHeaderMutation::Add {
+                    name: "x-client-risk".to_string(),
+                    value: b"0.25".to_vec(),
+                },
+                UpstreamHeaderMutation::Set {
+                    name: "x-client-risk".to_string(),
+                    value: b"0.5".to_vec(),
+                },
+                UpstreamHeaderMutation::Remove {
+                    name: "x-client-risk".to_string(),
+                },
+            ]
+        );
+
+        // Ungranted and reserved header names are both denied, even if explicitly granted.
+        match request_context.set_upstream_header("X-User-Id".to_string(), b"1234".to_vec()) {
+            Err(bulwark_host::HeaderError::Permission(denied)) => {
+                assert_eq!(denied.permission, "upstream_headers");
+                assert_eq!(denied.resource, "x-user-id");
+            }
+            other => panic!("expected a permission error, got {:?}", other),
+        }
+        match request_context
+            .set_upstream_header("Bulwark-Decision".to_string(), b"accept=1".to_vec())
+        {
+            Err(bulwark_host::HeaderError::Permission(reserved)) => {
+                assert_eq!(reserved.resource, "bulwark-decision");
+            }
+            other => panic!("expected a permission error, got {:?}", other),
+        }
+        // Invalid names are rejected rather than trapping, and aren't counted as permission denials.
+        match request_context.remove_upstream_header("X Client Risk".to_string()) {
+            Err(bulwark_host::HeaderError::InvalidName(name)) => {
+                assert_eq!(name, "X Client Risk");
+            }
+            other => panic!("expected an invalid name error, got {:?}", other),
+        }
+        assert_eq!(request_context.upstream_header_mutations.len(), 3);
+        assert_eq!(plugin.permission_denials(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_outbound_request_errors() -> Result<(), Box<dyn std::error::Error>> {
+        use {
+            bulwark_host::{Host, OutboundError},
+            std::{io::BufRead, net::TcpListener},
+        };
+
+        // Serves each connection with the given raw response, or holds it open without responding if there is none.
+        fn serve(response: Option<&'static [u8]>) -> std::io::Result<u16> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let port = listener.local_addr()?.port();
+            std::thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                        line.clear();
+                    }
+                    match response {
+                        Some(response) => stream.write_all(response).unwrap(),
+                        None => std::thread::sleep(Duration::from_secs(1)),
+                    }
+                }
+            });
+            Ok(port)
+        }
+
+        let (_, mut request_context) = context(bulwark_config::Plugin {
+            permissions: bulwark_config::Permissions {
+                http: vec!["localhost".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+        request_context.http_info = Arc::new(HttpInfo::new(Duration::from_millis(100), 4));
+
+        assert!(matches!(
+            request_context.set_request_body(42, b"".to_vec()),
+            Err(OutboundError::UnknownRequest(42))
+        ));
+        assert!(matches!(
+            request_context.add_request_header(42, "accept".to_string(), b"*/*".to_vec()),
+            Err(OutboundError::UnknownRequest(42))
+        ));
+        assert!(matches!(
+            request_context.prepare_request("GET".to_string(), "not a uri".to_string()),
+            Err(OutboundError::InvalidUri(uri)) if uri == "not a uri"
+        ));
+        assert!(matches!(
+            request_context.prepare_request("GET".to_string(), "data:text/plain,hello".to_string()),
+            Err(OutboundError::InvalidUri(_))
+        ));
+        assert!(matches!(
+            request_context.prepare_request("CONNECT".to_string(), "http://localhost/".to_string()),
+            Err(OutboundError::UnsupportedMethod(method)) if method == "CONNECT"
+        ));
+        assert!(matches!(
+            request_context.prepare_request("GET".to_string(), "http://example.com/".to_string()),
+            Err(OutboundError::Permission(denied)) if denied.resource == "example.com"
+        ));
+
+        let port = serve(Some(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"))?;
+        let pending =
+            request_context.prepare_request("GET".to_string(), "http://localhost/".to_string())?;
+        let request_id = request_context
+            .prepare_request("GET".to_string(), format!("http://localhost:{}/", port))?;
+        assert!(matches!(
+            request_context.add_request_header(request_id, "not a header".to_string(), b"*/*".to_vec()),
+            Err(OutboundError::InvalidHeader(name)) if name == "not a header"
+        ));
+        assert!(matches!(
+            request_context.add_request_header(request_id, "accept".to_string(), b"\n".to_vec()),
+            Err(OutboundError::InvalidHeader(name)) if name == "accept"
+        ));
+        request_context.add_request_header(
+            request_id,
+            "accept".to_string(),
+            b"text/plain".to_vec(),
+        )?;
+        let response = request_context.set_request_body(request_id, b"".to_vec());
+        assert!(
+            matches!(response, Ok(response) if response.status == 200 && response.chunk == b"ok")
+        );
+        // Sending a request doesn't free its ID for reuse by a request that's still being assembled.
+        let next =
+            request_context.prepare_request("GET".to_string(), "http://localhost/".to_string())?;
+        assert_ne!(next, pending);
+        assert_ne!(next, request_id);
+        request_context.add_request_header(
+            pending,
+            "accept".to_string(),
+            b"text/plain".to_vec(),
+        )?;
+
+        let port = serve(Some(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789",
+        ))?;
+        let request_id = request_context
+            .prepare_request("GET".to_string(), format!("http://localhost:{}/", port))?;
+        assert!(matches!(
+            request_context.set_request_body(request_id, b"".to_vec()),
+            Err(OutboundError::ResponseTooLarge(4))
+        ));
+
+        let port = serve(None)?;
+        let request_id = request_context
+            .prepare_request("GET".to_string(), format!("http://localhost:{}/", port))?;
+        assert!(matches!(
+            request_context.set_request_body(request_id, b"".to_vec()),
+            Err(OutboundError::Timeout)
+        ));
+
+        // Nothing is listening on a port once its listener has been dropped.
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let request_id = request_context
+            .prepare_request("GET".to_string(), format!("http://localhost:{}/", port))?;
+        assert!(matches!(
+            request_context.set_request_body(request_id, b"".to_vec()),
+            Err(OutboundError::Transport(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unavailable_host_values() -> Result<(), Box<dyn std::error::Error>> {
+        use bulwark_host::{EnvError, Host, ParamError};
+
+        let (_, mut request_context) = context(bulwark_config::Plugin {
+            permissions: bulwark_config::Permissions {
+                env: vec!["BULWARK_TEST_UNSET_VARIABLE".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+        let params = request_context.params.clone();
+
+        // Nothing is available until the interior service responds and the decisions are combined.
+        assert!(request_context.get_response().is_none());
+        assert!(request_context.get_combined_decision().is_none());
+        assert!(request_context.get_combined_tags().is_none());
+        assert!(request_context.get_outcome().is_none());
+
+        assert!(matches!(
+            request_context.get_env_bytes("BULWARK_TEST_UNSET_VARIABLE".to_string()),
+            Err(EnvError::Missing(key)) if key == "BULWARK_TEST_UNSET_VARIABLE"
+        ));
+        assert!(matches!(
+            request_context.get_env_bytes("HOME".to_string()),
+            Err(EnvError::Permission(denied)) if denied.resource == "HOME"
+        ));
+
+        assert!(matches!(
+            request_context.set_param_value("risk".to_string(), b"{".to_vec()),
+            Err(ParamError::InvalidJson(_))
+        ));
+        assert!(params.lock().unwrap().is_empty());
+        request_context.set_param_value("risk".to_string(), b"0.5".to_vec())?;
+        assert_eq!(request_context.get_param_value("risk".to_string()), b"0.5");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_decision() -> Result<(), Box<dyn std::error::Error>> {
+        use bulwark_host::Host;
+
+        let (plugin, request_context) = context(bulwark_config::Plugin::default())?;
+        let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
+        plugin_instance.record_combined_decision(
+            &DecisionComponents {
+                decision: Decision {
+                    accept: 0.25,
+                    restrict: 0.5,
+                    unknown: 0.25,
+                },
+                tags: vec!["evil".to_string(), "bot".to_string()],
+            },
+            Outcome::Suspected,
+        );
+
+        let request_context = plugin_instance.store.data_mut();
+        assert_eq!(
+            request_context.get_combined_tags(),
+            Some(vec!["evil".to_string(), "bot".to_string()])
+        );
+        assert!(matches!(
+            request_context.get_combined_decision(),
+            Some(DecisionInterface { restrict, .. }) if restrict == 0.5
+        ));
+        assert_eq!(
+            request_context.get_outcome(),
+            Some(OutcomeInterface::Suspected)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_errors() -> Result<(), Box<dyn std::error::Error>> {
+        use bulwark_host::{Host, StateError};
+
+        let (_, mut request_context) = context(bulwark_config::Plugin {
+            permissions: bulwark_config::Permissions {
+                state: vec!["test:".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        assert!(matches!(
+            request_context.get_remote_state("other:key".to_string()),
+            Err(StateError::Permission(denied)) if denied.resource == "other:key"
+        ));
+        request_context.set_remote_state("test:key".to_string(), b"value".to_vec())?;
+        assert!(matches!(
+            request_context.add_remote_set_member("test:key".to_string(), b"member".to_vec()),
+            Err(StateError::WrongType(key)) if key == "test:key"
+        ));
+
+        // Nothing listens on port 1, so the plugin sees the state backend fail rather than an empty value.
+        request_context.state_backend = Arc::new(crate::RedisStateBackend::new(
+            "redis://127.0.0.1:1",
+            &bulwark_config::State {
+                connect_timeout: 100,
+                command_timeout: 100,
+                ..Default::default()
+            },
+        )?);
+        assert!(matches!(
+            request_context.get_remote_state("test:key".to_string()),
+            Err(StateError::Timeout)
+        ));
+        assert!(matches!(
+            request_context.increment_remote_state("test:key".to_string()),
+            Err(StateError::Unavailable)
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_wasm_logic() -> Result<(), Box<dyn std::error::Error>> {
         let wasm_bytes = include_bytes!("../tests/bulwark-evil-bit.wasm");
         let plugin = Arc::new(Plugin::from_bytes(
+            &PluginEngine::new(None)?,
             "bulwark-evil-bit.wasm".to_string(),
             wasm_bytes,
             &bulwark_config::Plugin::default(),
@@ -1260,9 +2893,8 @@ mod tests {
                     end_of_stream: true,
                 })?,
         );
-        let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
-        let request_context = RequestContext::new(plugin.clone(), None, params, request)?;
-        let mut typical_plugin_instance = PluginInstance::new(plugin.clone(), request_context)?;
+        let typical_context = request_context(&plugin, request)?;
+        let mut typical_plugin_instance = PluginInstance::new(plugin.clone(), typical_context)?;
         typical_plugin_instance.start()?;
         typical_plugin_instance.handle_request_decision()?;
         let typical_decision = typical_plugin_instance.decision();
@@ -1285,9 +2917,8 @@ mod tests {
                     end_of_stream: true,
                 })?,
         );
-        let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
-        let request_context = RequestContext::new(plugin.clone(), None, params, request)?;
-        let mut evil_plugin_instance = PluginInstance::new(plugin, request_context)?;
+        let evil_context = request_context(&plugin, request)?;
+        let mut evil_plugin_instance = PluginInstance::new(plugin, evil_context)?;
         evil_plugin_instance.start()?;
         evil_plugin_instance.handle_request_decision()?;
         let evil_decision = evil_plugin_instance.decision();
@@ -1298,4 +2929,240 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_pooled_instance_reuse() -> Result<(), Box<dyn std::error::Error>> {
+        let wasm_bytes = include_bytes!("../tests/bulwark-evil-bit.wasm");
+        let plugin = Arc::new(Plugin::from_bytes(
+            &PluginEngine::new(Some(1))?,
+            "bulwark-evil-bit.wasm".to_string(),
+            wasm_bytes,
+            &bulwark_config::Plugin::default(),
+        )?);
+
+        // With a single slot, every instance after the first reuses the same slot.
+        for (evil, expected_restrict) in [(true, 1.0), (false, 0.0), (true, 1.0)] {
+            let mut request = http::Request::builder()
+                .method("POST")
+                .uri("/example")
+                .version(http::Version::HTTP_11)
+                .header("Content-Type", "application/json");
+            if evil {
+                request = request.header("Evil", "true");
+            }
+            let request = Arc::new(request.body(bulwark_wasm_sdk::BodyChunk {
+                content: "{\"number\": 42}".as_bytes().to_vec(),
+                start: 0,
+                size: 14,
+                end_of_stream: true,
+            })?);
+            let request_context = request_context(&plugin, request)?;
+            let mut plugin_instance = PluginInstance::new(plugin.clone(), request_context)?;
+            plugin_instance.start()?;
+            plugin_instance.handle_request_decision()?;
+            let decision_components = plugin_instance.decision();
+            assert_eq!(decision_components.decision.restrict, expected_restrict);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prestarted_instances() -> Result<(), Box<dyn std::error::Error>> {
+        let wasm_bytes = include_bytes!("../tests/bulwark-evil-bit.wasm");
+        let plugin = Arc::new(Plugin::from_bytes(
+            &PluginEngine::new(Some(3))?,
+            "bulwark-evil-bit.wasm".to_string(),
+            wasm_bytes,
+            &bulwark_config::Plugin {
+                prestart: Some(2),
+                ..Default::default()
+            },
+        )?);
+        let prestarted = PrestartedInstances::new(
+            plugin,
+            Arc::new(MemoryStateBackend::default()),
+            Arc::new(HttpInfo::default()),
+        );
+        assert!(prestarted.needs_replenishing());
+        prestarted.replenish();
+        assert!(!prestarted.needs_replenishing());
+
+        // Each prestarted instance sees only the request it's bound to, and the third falls back to instantiation.
+        for (evil, expected_restrict) in [(true, 1.0), (false, 0.0), (true, 1.0)] {
+            let mut request = http::Request::builder()
+                .method("POST")
+                .uri("/example")
+                .version(http::Version::HTTP_11)
+                .header("Content-Type", "application/json")
+                .extension(RequestId(
+                    "f81d4fae-7dec-11d0-a765-00a0c91e6bf6".to_string(),
+                ));
+            if evil {
+                request = request.header("Evil", "true");
+            }
+            let request = Arc::new(request.body(bulwark_wasm_sdk::BodyChunk {
+                content: "{\"number\": 42}".as_bytes().to_vec(),
+                start: 0,
+                size: 14,
+                end_of_stream: true,
+            })?);
+            let params = Arc::new(Mutex::new(bulwark_wasm_sdk::Map::new()));
+            let mut plugin_instance = prestarted.instantiate(params, request)?;
+            assert_eq!(
+                plugin_instance.store.data().request_id,
+                "f81d4fae-7dec-11d0-a765-00a0c91e6bf6"
+            );
+            plugin_instance.start()?;
+            plugin_instance.handle_request_decision()?;
+            let decision_components = plugin_instance.decision();
+            assert_eq!(decision_components.decision.restrict, expected_restrict);
+        }
+        assert!(prestarted.needs_replenishing());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remaining_budget() -> Result<(), Box<dyn std::error::Error>> {
+        use bulwark_host::Host;
+
+        let (plugin, request_context) = context(bulwark_config::Plugin {
+            timeout: Some(50),
+            ..Default::default()
+        })?;
+        let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
+        assert_eq!(plugin_instance.timeout(), Some(50));
+        assert_eq!(
+            plugin_instance.store.data_mut().get_remaining_budget(),
+            u64::MAX
+        );
+
+        plugin_instance.record_deadline(Instant::now() + std::time::Duration::from_secs(60));
+        let remaining = plugin_instance.store.data_mut().get_remaining_budget();
+        assert!(remaining > 59_000 && remaining <= 60_000);
+
+        plugin_instance.record_deadline(Instant::now() - std::time::Duration::from_millis(1));
+        assert_eq!(plugin_instance.store.data_mut().get_remaining_budget(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let plugin = Arc::new(Plugin::from_wat(
+            &PluginEngine::new(None)?,
+            "grow-memory".to_string(),
+            r#"(component
+                (core module $m
+                    (memory (export "memory") 1)
+                    (func (export "on-request")
+                        (drop (memory.grow (i32.const 10)))
+                        (if (i32.eq (memory.size) (i32.const 1)) (then unreachable))))
+                (core instance $i (instantiate $m))
+                (func (export "on-request") (canon lift (core func $i "on-request"))))"#,
+            &bulwark_config::Plugin {
+                max_memory: Some(2 * 65536),
+                ..Default::default()
+            },
+        )?);
+        let request_context = request_context(&plugin, get_request())?;
+        let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
+        let result = plugin_instance.handle_request();
+        assert!(matches!(
+            result,
+            Err(PluginExecutionError::MemoryLimitExceeded { limit: 131072 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let engine = PluginEngine::new(None)?;
+        let plugin = Arc::new(Plugin::from_wat(
+            &engine,
+            "infinite-loop".to_string(),
+            r#"(component
+                (core module $m
+                    (func (export "on-request")
+                        (loop $forever (br $forever))))
+                (core instance $i (instantiate $m))
+                (func (export "on-request") (canon lift (core func $i "on-request"))))"#,
+            &bulwark_config::Plugin::default(),
+        )?);
+        let request_context = request_context(&plugin, get_request())?;
+        let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
+        plugin_instance.record_deadline(Instant::now() + Duration::from_millis(10));
+
+        let ticker = std::thread::spawn(move || {
+            for _ in 0..1000 {
+                std::thread::sleep(EPOCH_TICK);
+                engine.increment_epoch();
+            }
+        });
+        let result = plugin_instance.handle_request();
+        assert!(matches!(
+            result,
+            Err(PluginExecutionError::TimeLimitExceeded)
+        ));
+        ticker.join().unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir =
+            std::env::temp_dir().join(format!("bulwark-module-cache-test-{}", std::process::id()));
+        let wasm_bytes = include_bytes!("../tests/bulwark-blank-slate.wasm");
+        let engine = PluginEngine::new(None)?.with_module_cache(&cache_dir);
+        let cache_path = cache_dir.join(format!("{}.cwasm", engine.cache_key(wasm_bytes)));
+
+        Plugin::from_bytes(
+            &engine,
+            "bulwark-blank-slate.wasm".to_string(),
+            wasm_bytes,
+            &bulwark_config::Plugin::default(),
+        )?;
+        assert!(cache_path.exists());
+
+        // A differently configured engine must not share cache entries.
+        let pooled_engine = PluginEngine::new(Some(1))?.with_module_cache(&cache_dir);
+        assert_ne!(
+            pooled_engine.cache_key(wasm_bytes),
+            engine.cache_key(wasm_bytes)
+        );
+
+        // The second load is served from the cache and behaves the same as a freshly compiled plugin.
+        let plugin = Arc::new(Plugin::from_bytes(
+            &engine,
+            "bulwark-blank-slate.wasm".to_string(),
+            wasm_bytes,
+            &bulwark_config::Plugin::default(),
+        )?);
+        let request_context = request_context(&plugin, get_request())?;
+        let mut plugin_instance = PluginInstance::new(plugin, request_context)?;
+        plugin_instance.start()?;
+        assert_eq!(plugin_instance.decision().decision.unknown, 1.0);
+
+        fs::remove_dir_all(&cache_dir).ok();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_rate_limit() {
+        let mut limiter = OutputRateLimiter::new();
+        for _ in 0..GUEST_OUTPUT_BURST as usize {
+            assert_eq!(limiter.acquire(), Some(0));
+        }
+        assert_eq!(limiter.acquire(), None);
+        assert_eq!(limiter.acquire(), None);
+
+        // Once the bucket refills, the next line reports how many were dropped.
+        limiter.refilled_at -= Duration::from_secs(1);
+        assert_eq!(limiter.acquire(), Some(2));
+        assert_eq!(limiter.acquire(), Some(0));
+    }
 }
@@ -0,0 +1,2669 @@
+use {
+    crate::StateError,
+    chrono::Utc,
+    redis::IntoConnectionInfo,
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+            mpsc, Arc, Mutex, Once, Weak,
+        },
+        time::Duration,
+    },
+    tracing::warn,
+};
+
+extern crate redis;
+
+/// How often, in seconds, the in-memory and sled backends sweep expired entries out of their stores.
+const SWEEP_INTERVAL: i64 = 60;
+
+/// Sweeps one store given the current unix timestamp in seconds, returning false once the store has been dropped.
+type Sweep = Box<dyn Fn(i64) -> bool + Send>;
+
+/// The stores swept by the sweeper thread.
+static SWEEPS: Mutex<Vec<Sweep>> = Mutex::new(Vec::new());
+/// Starts the sweeper thread the first time a store needs sweeping.
+static SWEEPER: Once = Once::new();
+
+/// Sweeps expired entries out of a backend's store every [`SWEEP_INTERVAL`] on a background thread, so that requests
+/// never pay for a sweep. The store stops being swept once it has been dropped.
+///
+/// Every store in the process is swept by the same thread, which is started by the first call.
+///
+/// # Arguments
+///
+/// * `store` - The store to sweep.
+/// * `sweep` - Sweeps the store, given the current unix timestamp in seconds.
+fn sweep_periodically<T: Send + Sync + 'static>(store: Weak<T>, sweep: fn(&T, i64)) {
+    SWEEPER.call_once(|| {
+        std::thread::Builder::new()
+            .name("bulwark-state-sweeper".to_string())
+            .spawn(|| loop {
+                std::thread::sleep(Duration::from_secs(SWEEP_INTERVAL as u64));
+                // Sweeping happens outside the lock so that a slow sweep doesn't hold up new backends.
+                let mut sweeps = std::mem::take(&mut *SWEEPS.lock().unwrap());
+                let now = Utc::now().timestamp();
+                sweeps.retain(|sweep| sweep(now));
+                SWEEPS.lock().unwrap().extend(sweeps);
+            })
+            .expect("could not spawn state sweeper thread");
+    });
+    SWEEPS
+        .lock()
+        .unwrap()
+        .push(Box::new(move |now| match store.upgrade() {
+            Some(store) => {
+                sweep(&store, now);
+                true
+            }
+            None => false,
+        }));
+}
+
+/// The state of a rate limit, as seen by the plugin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rate {
+    /// The number of attempts made in the current period.
+    pub attempts: i64,
+    /// The unix timestamp, in seconds, at which the current period ends.
+    pub expiration: i64,
+}
+
+/// The state of a circuit breaker, as seen by the plugin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Breaker {
+    /// The number of times the breaker has been incremented in the current period.
+    pub generation: i64,
+    /// The number of successes in the current period.
+    pub successes: i64,
+    /// The number of failures in the current period.
+    pub failures: i64,
+    /// The number of successes since the last failure.
+    pub consecutive_successes: i64,
+    /// The number of failures since the last success.
+    pub consecutive_failures: i64,
+    /// The unix timestamp, in seconds, at which the current period ends.
+    pub expiration: i64,
+}
+
+impl Rate {
+    /// Returns the rate limit after an increment, starting a new period if the current one has ended.
+    ///
+    /// Mirrors the `increment_rate_limit` script in the [`ScriptRegistry`].
+    fn incremented(current: Option<Rate>, delta: i64, window: i64, timestamp: i64) -> Rate {
+        let mut rate = match current {
+            Some(rate) if timestamp <= rate.expiration => rate,
+            _ => Rate {
+                attempts: 0,
+                expiration: timestamp + window,
+            },
+        };
+        rate.attempts += delta;
+        rate
+    }
+
+    /// Returns the rate limit if its period hasn't ended, otherwise an empty rate limit.
+    fn checked(current: Option<Rate>, timestamp: i64) -> Rate {
+        current
+            .filter(|rate| timestamp <= rate.expiration)
+            .unwrap_or_default()
+    }
+}
+
+impl Breaker {
+    /// Returns the circuit breaker after an increment, extending its period to `window` seconds from `timestamp`.
+    ///
+    /// Mirrors the `increment_breaker` script in the [`ScriptRegistry`].
+    fn incremented(
+        current: Option<Breaker>,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Breaker {
+        let mut breaker = Self::checked(current, timestamp);
+        breaker.generation += 1;
+        if success_delta > 0 {
+            breaker.successes += success_delta;
+            breaker.consecutive_successes += success_delta;
+            breaker.consecutive_failures = 0;
+        } else {
+            breaker.failures += failure_delta;
+            breaker.consecutive_successes = 0;
+            breaker.consecutive_failures += failure_delta;
+        }
+        breaker.expiration = timestamp + window;
+        breaker
+    }
+
+    /// Lists the circuit breaker's fields in the order they're stored in.
+    fn to_integers(self) -> [i64; 6] {
+        [
+            self.generation,
+            self.successes,
+            self.failures,
+            self.consecutive_successes,
+            self.consecutive_failures,
+            self.expiration,
+        ]
+    }
+
+    /// Builds a circuit breaker from its fields in the order they're stored in.
+    fn from_integers(
+        [generation, successes, failures, consecutive_successes, consecutive_failures, expiration]: [i64; 6],
+    ) -> Breaker {
+        Breaker {
+            generation,
+            successes,
+            failures,
+            consecutive_successes,
+            consecutive_failures,
+            expiration,
+        }
+    }
+
+    /// Returns the circuit breaker if its period hasn't ended, otherwise an empty circuit breaker.
+    fn checked(current: Option<Breaker>, timestamp: i64) -> Breaker {
+        current
+            .filter(|breaker| timestamp <= breaker.expiration)
+            .unwrap_or_default()
+    }
+}
+
+/// The outcome of an attempt against a sliding window or token bucket rate limit, as seen by the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allowance {
+    /// True if the attempt is within the limit.
+    pub allowed: bool,
+    /// The number of further attempts that would be allowed right now.
+    pub remaining: i64,
+    /// The number of seconds until another attempt of the same size would be allowed, or zero if it would be
+    /// allowed now.
+    pub retry_after: i64,
+}
+
+impl Default for Allowance {
+    /// The default allowance permits the attempt.
+    fn default() -> Self {
+        Allowance {
+            allowed: true,
+            remaining: 0,
+            retry_after: 0,
+        }
+    }
+}
+
+/// The counts behind an approximated sliding window rate limit.
+///
+/// Attempts are counted in fixed windows. The number of attempts in the sliding window is estimated by weighting the
+/// previous fixed window's count by how much of it the sliding window still overlaps, which smooths out the bursts
+/// at window boundaries that fixed windows allow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SlidingWindow {
+    /// The unix timestamp, in milliseconds, at which the current fixed window started.
+    start: i64,
+    /// The number of attempts in the previous fixed window.
+    previous: i64,
+    /// The number of attempts in the current fixed window.
+    current: i64,
+}
+
+impl SlidingWindow {
+    /// Returns the counts after an increment, moving on to a new fixed window if the current one has ended.
+    ///
+    /// Mirrors the `increment_sliding_window` script in the [`ScriptRegistry`].
+    fn incremented(
+        current: Option<SlidingWindow>,
+        delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> SlidingWindow {
+        let start = timestamp - timestamp.rem_euclid(window);
+        let mut counts = match current {
+            Some(counts) if counts.start >= start => counts,
+            Some(counts) if counts.start + window >= start => SlidingWindow {
+                start,
+                previous: counts.current,
+                current: 0,
+            },
+            _ => SlidingWindow {
+                start,
+                previous: 0,
+                current: 0,
+            },
+        };
+        counts.current += delta;
+        counts
+    }
+
+    /// Returns the unix timestamp, in milliseconds, after which the counts no longer affect the rate limit.
+    fn expiration(&self, window: i64) -> i64 {
+        self.start.saturating_add(window.saturating_mul(2))
+    }
+
+    /// Returns the allowance for an attempt of `delta` that has already been counted.
+    fn allowance(&self, delta: i64, limit: i64, window: i64, timestamp: i64) -> Allowance {
+        let window = i128::from(window);
+        let elapsed = i128::from(timestamp - self.start).clamp(0, window);
+        let previous = i128::from(self.previous);
+        let current = i128::from(self.current);
+        let limit = i128::from(limit);
+        let size = i128::from(delta.max(1));
+        let estimate = previous * (window - elapsed) / window + current;
+        let before = estimate - i128::from(delta);
+        let wait = if estimate + size <= limit {
+            0
+        } else if current + size <= limit {
+            // Wait until enough of the previous window has slid out of the sliding window.
+            ceil_div(
+                previous * (window - elapsed) - (limit - current - size) * window,
+                previous,
+            )
+        } else {
+            // Wait for the current window to end, then for enough of it to slide out of the sliding window.
+            let overlap = if current > 0 {
+                ceil_div((current + size - limit) * window, current).min(window)
+            } else {
+                window
+            };
+            window - elapsed + overlap
+        };
+        Allowance {
+            allowed: before + size <= limit,
+            remaining: saturate((limit - estimate).max(0)),
+            retry_after: seconds_from_millis(saturate(wait)),
+        }
+    }
+}
+
+/// A token bucket rate limit, implemented as a generic cell rate algorithm.
+///
+/// Rather than a token count, the bucket is stored as the unix timestamp, in milliseconds, at which it will be full
+/// again. A single value is enough to track it and nothing needs to refill the bucket periodically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TokenBucket {
+    /// The number of milliseconds it takes to refill a single token.
+    interval: i64,
+    /// The number of milliseconds it takes to refill the whole bucket.
+    tolerance: i64,
+}
+
+impl TokenBucket {
+    /// Creates a bucket holding up to `capacity` tokens that refills at `refill` tokens per `window` milliseconds.
+    fn new(capacity: i64, refill: i64, window: i64) -> TokenBucket {
+        let interval = (window / refill.max(1)).max(1);
+        TokenBucket {
+            interval,
+            tolerance: interval.saturating_mul(capacity.max(0)),
+        }
+    }
+
+    /// Takes tokens from the bucket if enough are available.
+    ///
+    /// Returns the time at which the bucket will be full if it changed, and the allowance for the attempt. Taking
+    /// zero tokens checks whether a single token is available without changing the bucket.
+    ///
+    /// Mirrors the `take_token_bucket` script in the [`ScriptRegistry`].
+    fn take(&self, current: Option<i64>, tokens: i64, timestamp: i64) -> (Option<i64>, Allowance) {
+        let size = tokens.max(1);
+        let mut full_at = current.unwrap_or(timestamp).max(timestamp);
+        let allowed = full_at.saturating_add(self.interval.saturating_mul(size)) - self.tolerance
+            <= timestamp;
+        let taken = allowed && tokens > 0;
+        if taken {
+            full_at = full_at.saturating_add(self.interval.saturating_mul(tokens));
+        }
+        let wait =
+            full_at.saturating_add(self.interval.saturating_mul(size)) - self.tolerance - timestamp;
+        (
+            Some(full_at).filter(|_| taken),
+            Allowance {
+                allowed,
+                remaining: ((timestamp + self.tolerance - full_at) / self.interval).max(0),
+                retry_after: seconds_from_millis(wait.max(0)),
+            },
+        )
+    }
+}
+
+/// Divides, rounding up. The divisor must be positive.
+fn ceil_div(dividend: i128, divisor: i128) -> i128 {
+    -((-dividend).div_euclid(divisor))
+}
+
+/// Converts a wide integer back to an `i64`, saturating at the bounds.
+fn saturate(value: i128) -> i64 {
+    i64::try_from(value).unwrap_or(if value < 0 { i64::MIN } else { i64::MAX })
+}
+
+/// Converts a non-negative number of milliseconds to seconds, rounding up.
+fn seconds_from_millis(millis: i64) -> i64 {
+    millis / 1000 + i64::from(millis % 1000 != 0)
+}
+
+/// A store of state shared between plugin instances, and possibly between Bulwark nodes.
+///
+/// Rate limits and circuit breakers are evaluated against the timestamps Bulwark passes in rather than the
+/// backend's clock. Sliding windows and token buckets use timestamps and windows in milliseconds, and their windows
+/// must be positive. Plugins are responsible for all rate-limiting and circuit-breaking logic with the values they
+/// receive.
+pub trait StateBackend: Send + Sync {
+    /// Returns the named state value, or an empty value if it doesn't exist.
+    fn get(&self, key: &str) -> Result<Vec<u8>, StateError>;
+
+    /// Sets a named state value, clearing any expiration.
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), StateError>;
+
+    /// Increments a named counter by a delta value, returning the new value.
+    ///
+    /// Counters that don't exist start at zero.
+    fn increment(&self, key: &str, delta: i64) -> Result<i64, StateError>;
+
+    /// Sets an expiration on a named value, in seconds from now.
+    fn expire(&self, key: &str, ttl: i64) -> Result<(), StateError>;
+
+    /// Sets a named state value that expires `ttl` seconds from now, as a single atomic operation.
+    ///
+    /// A non-positive TTL deletes the value.
+    fn set_with_ttl(&self, key: &str, value: &[u8], ttl: i64) -> Result<(), StateError>;
+
+    /// Deletes a named value of any kind, returning true if it existed.
+    fn delete(&self, key: &str) -> Result<bool, StateError>;
+
+    /// Returns several named state values in order, with an empty value for each one that doesn't exist.
+    fn get_many(&self, keys: &[&str]) -> Result<Vec<Vec<u8>>, StateError>;
+
+    /// Sets a named state value only if its current value is `expected`, returning true if it was set.
+    ///
+    /// An empty `expected` value matches a value that doesn't exist. Like `set`, a successful write clears any
+    /// expiration.
+    fn compare_and_set(&self, key: &str, expected: &[u8], value: &[u8])
+        -> Result<bool, StateError>;
+
+    /// Adds a member to a named set, returning true if it wasn't already a member.
+    fn add_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError>;
+
+    /// Removes a member from a named set, returning true if it was a member.
+    fn remove_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError>;
+
+    /// Returns true if a value is a member of a named set.
+    fn is_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError>;
+
+    /// Returns the members of a named set, in no particular order.
+    fn set_members(&self, key: &str) -> Result<Vec<Vec<u8>>, StateError>;
+
+    /// Adds a member to a named sorted set or updates its score, returning true if it wasn't already a member.
+    fn add_sorted_set_member(
+        &self,
+        key: &str,
+        member: &[u8],
+        score: f64,
+    ) -> Result<bool, StateError>;
+
+    /// Removes a member from a named sorted set, returning true if it was a member.
+    fn remove_sorted_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError>;
+
+    /// Returns the members of a named sorted set with scores between `min` and `max` inclusive, with their scores.
+    ///
+    /// Members are ordered by score, then by their bytes.
+    fn sorted_set_range(
+        &self,
+        key: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, StateError>;
+
+    /// Removes the members of a named sorted set with scores between `min` and `max` inclusive, returning how many
+    /// were removed.
+    fn remove_sorted_set_range(&self, key: &str, min: f64, max: f64) -> Result<u64, StateError>;
+
+    /// Increments a rate limit, starting a new period of `window` seconds if the current one has ended.
+    fn increment_rate_limit(
+        &self,
+        key: &str,
+        delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Rate, StateError>;
+
+    /// Checks a rate limit, returning zero attempts if there is no current period.
+    fn check_rate_limit(&self, key: &str, timestamp: i64) -> Result<Rate, StateError>;
+
+    /// Increments a circuit breaker's success or failure counters, extending the period to `window` seconds from
+    /// `timestamp`.
+    fn increment_breaker(
+        &self,
+        key: &str,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Breaker, StateError>;
+
+    /// Checks a circuit breaker, returning zero counts if there is no current period.
+    fn check_breaker(&self, key: &str, timestamp: i64) -> Result<Breaker, StateError>;
+
+    /// Counts attempts against an approximated sliding window rate limit of `limit` attempts per `window`.
+    ///
+    /// Every attempt is counted, including those over the limit, so clients that keep retrying stay limited. A delta
+    /// of zero checks the rate limit without counting an attempt.
+    fn increment_sliding_window(
+        &self,
+        key: &str,
+        delta: i64,
+        limit: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError>;
+
+    /// Takes tokens from a token bucket holding up to `capacity` tokens, which refills at `refill` tokens per
+    /// `window`.
+    ///
+    /// Tokens are only taken if enough are available. Taking zero tokens checks the bucket without changing it.
+    fn take_token_bucket(
+        &self,
+        key: &str,
+        tokens: i64,
+        capacity: i64,
+        refill: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError>;
+
+    /// Returns false while the backend's store can't be reached.
+    ///
+    /// Backends that keep state in-process are always available.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Returns the number of operations that have failed because the backend's store couldn't be reached.
+    fn failures(&self) -> u64 {
+        0
+    }
+}
+
+/// Opens the state backend selected by the scheme of a URI.
+///
+/// `redis://`, `rediss://`, and `redis+unix://` URIs open a [`RedisStateBackend`]. `sled://` URIs open a
+/// [`SledStateBackend`] in the directory named by the rest of the URI, e.g. `sled:///var/lib/bulwark/state`.
+/// `memory://` opens a [`MemoryStateBackend`], which is also used when no URI is configured. Redis Sentinel is not
+/// supported, so `redis+sentinel://` URIs are rejected like any other unknown scheme.
+///
+/// # Arguments
+///
+/// * `uri` - The URI of the state store, if one is configured.
+/// * `settings` - The connection settings for the state store.
+pub fn open_state_backend(
+    uri: Option<&str>,
+    settings: &bulwark_config::State,
+) -> Result<std::sync::Arc<dyn StateBackend>, StateError> {
+    let uri = match uri {
+        Some(uri) => uri,
+        None => return Ok(std::sync::Arc::new(MemoryStateBackend::default())),
+    };
+    let (scheme, path) = uri.split_once("://").unwrap_or((uri, ""));
+    match scheme {
+        "memory" => Ok(std::sync::Arc::new(MemoryStateBackend::default())),
+        "sled" => Ok(std::sync::Arc::new(SledStateBackend::open(path)?)),
+        "redis" | "rediss" | "redis+unix" => {
+            Ok(std::sync::Arc::new(RedisStateBackend::new(uri, settings)?))
+        }
+        _ => Err(StateError::UnsupportedScheme {
+            scheme: scheme.to_string(),
+        }),
+    }
+}
+
+/// A client for either a standalone Redis server or a Redis Cluster.
+enum RedisClient {
+    Standalone(redis::Client),
+    Cluster(redis::cluster::ClusterClient),
+}
+
+/// Opens pooled Redis connections with the configured time limits.
+struct RedisConnectionManager {
+    client: RedisClient,
+    connect_timeout: Duration,
+    command_timeout: Duration,
+    /// True while a Redis Cluster connection attempt is running, including one that has outlived its time limit.
+    cluster_connecting: Arc<AtomicBool>,
+}
+
+impl RedisConnectionManager {
+    /// Opens a Redis Cluster connection, giving up once the connect timeout passes.
+    ///
+    /// The cluster client has no connection time limit of its own, so the connection is opened on a separate thread.
+    /// An attempt that outlives the time limit is left to finish in the background, and no new attempt starts until
+    /// it does, so an unreachable cluster can't pile up connecting threads.
+    fn connect_cluster(
+        &self,
+        client: &redis::cluster::ClusterClient,
+    ) -> redis::RedisResult<redis::cluster::ClusterConnection> {
+        let timed_out =
+            || redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        if self.cluster_connecting.swap(true, Ordering::AcqRel) {
+            return Err(timed_out());
+        }
+        let (sender, receiver) = mpsc::channel();
+        let client = client.clone();
+        let connecting = self.cluster_connecting.clone();
+        let spawned = std::thread::Builder::new()
+            .name("bulwark-redis-connect".to_string())
+            .spawn(move || {
+                sender.send(client.get_connection()).ok();
+                connecting.store(false, Ordering::Release);
+            });
+        if let Err(err) = spawned {
+            self.cluster_connecting.store(false, Ordering::Release);
+            return Err(err.into());
+        }
+        receiver
+            .recv_timeout(self.connect_timeout)
+            .unwrap_or_else(|_| Err(timed_out()))
+    }
+}
+
+impl r2d2::ManageConnection for RedisConnectionManager {
+    type Connection = Box<dyn redis::ConnectionLike + Send>;
+    type Error = redis::RedisError;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let command_timeout = Some(self.command_timeout);
+        match &self.client {
+            RedisClient::Standalone(client) => {
+                let conn = client.get_connection_with_timeout(self.connect_timeout)?;
+                conn.set_read_timeout(command_timeout)?;
+                conn.set_write_timeout(command_timeout)?;
+                Ok(Box::new(conn))
+            }
+            RedisClient::Cluster(client) => {
+                let conn = self.connect_cluster(client)?;
+                // Reconnecting inside a command would ignore the connect timeout, so a connection that loses a node
+                // fails instead and is replaced by the pool.
+                conn.set_auto_reconnect(false);
+                conn.set_read_timeout(command_timeout)?;
+                conn.set_write_timeout(command_timeout)?;
+                Ok(Box::new(conn))
+            }
+        }
+    }
+
+    fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        if conn.check_connection() {
+            Ok(())
+        } else {
+            Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe).into())
+        }
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        !conn.is_open()
+    }
+}
+
+/// A state backend that keeps state in an external Redis server so that it can be shared by every Bulwark node.
+///
+/// Connections are opened on demand. Once Redis can't be reached, the backend reports itself as unavailable and
+/// operations fail immediately, except for one operation each second that checks whether Redis is back.
+///
+/// Standalone servers and Redis Cluster are supported. Redis Sentinel is not supported.
+pub struct RedisStateBackend {
+    /// The connection pool
+    pool: r2d2::Pool<RedisConnectionManager>,
+    /// A Lua script registry
+    registry: ScriptRegistry,
+    /// True if connected to a Redis Cluster, where commands can't span keys in different slots.
+    cluster: bool,
+    /// False after an operation fails because Redis couldn't be reached, until an operation succeeds.
+    available: AtomicBool,
+    /// The unix timestamp, in milliseconds, after which an operation may try Redis again while it's unavailable.
+    retry_at: AtomicI64,
+    /// The number of operations that have failed because Redis couldn't be reached.
+    failures: AtomicU64,
+}
+
+impl RedisStateBackend {
+    /// Creates a new [`RedisStateBackend`] for the Redis server or cluster at the given URI.
+    ///
+    /// No connection is made until the backend is first used, so Redis being down does not prevent Bulwark from
+    /// starting.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the Redis server, or the first seed node of a Redis Cluster.
+    /// * `settings` - The connection settings, including any additional cluster seed nodes.
+    pub fn new(uri: &str, settings: &bulwark_config::State) -> Result<Self, StateError> {
+        let client = if settings.cluster_nodes.is_empty() {
+            let mut connection_info = uri.into_connection_info()?;
+            if let Some(username) = settings.username.as_ref() {
+                connection_info.redis.username = Some(username.clone());
+            }
+            if let Some(password) = settings.password.as_ref() {
+                connection_info.redis.password = Some(password.clone());
+            }
+            RedisClient::Standalone(redis::Client::open(connection_info)?)
+        } else {
+            let mut nodes = vec![uri];
+            nodes.extend(settings.cluster_nodes.iter().map(String::as_str));
+            let mut builder = redis::cluster::ClusterClient::builder(nodes);
+            if let Some(username) = settings.username.as_ref() {
+                builder = builder.username(username.clone());
+            }
+            if let Some(password) = settings.password.as_ref() {
+                builder = builder.password(password.clone());
+            }
+            RedisClient::Cluster(builder.build()?)
+        };
+        let cluster = matches!(client, RedisClient::Cluster(_));
+        let connect_timeout = Duration::from_millis(settings.connect_timeout);
+        Ok(Self {
+            pool: r2d2::Pool::builder()
+                .max_size(settings.pool_size)
+                .min_idle(Some(0))
+                .connection_timeout(connect_timeout)
+                .build_unchecked(RedisConnectionManager {
+                    client,
+                    connect_timeout,
+                    command_timeout: Duration::from_millis(settings.command_timeout),
+                    cluster_connecting: Arc::new(AtomicBool::new(false)),
+                }),
+            registry: ScriptRegistry::default(),
+            cluster,
+            available: AtomicBool::new(true),
+            retry_at: AtomicI64::new(0),
+            failures: AtomicU64::new(0),
+        })
+    }
+
+    /// Runs an operation with a pooled connection, tracking whether Redis can be reached.
+    ///
+    /// While Redis is unavailable, the operation fails without waiting on the pool unless it's the first since the
+    /// retry interval passed.
+    fn with_connection<T>(
+        &self,
+        operation: impl FnOnce(&mut dyn redis::ConnectionLike) -> redis::RedisResult<T>,
+    ) -> Result<T, StateError> {
+        if !self.available.load(Ordering::Relaxed) && !self.take_retry() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return Err(StateError::Unavailable);
+        }
+        let result = match self.pool.get() {
+            Ok(mut conn) => operation(conn.as_mut()).map_err(StateError::from),
+            Err(err) => Err(StateError::from(err)),
+        };
+        match &result {
+            Ok(_) => self.available.store(true, Ordering::Relaxed),
+            Err(err) if is_outage(err) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                self.retry_at.store(
+                    Utc::now().timestamp_millis() + REDIS_RETRY_INTERVAL,
+                    Ordering::Relaxed,
+                );
+                self.available.store(false, Ordering::Relaxed);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Returns true for exactly one caller once the retry interval has passed, letting it check whether Redis is back.
+    fn take_retry(&self) -> bool {
+        let now = Utc::now().timestamp_millis();
+        let retry_at = self.retry_at.load(Ordering::Relaxed);
+        now >= retry_at
+            && self
+                .retry_at
+                .compare_exchange(
+                    retry_at,
+                    now + REDIS_RETRY_INTERVAL,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+    }
+}
+
+/// How long, in milliseconds, operations fail immediately after Redis couldn't be reached before one tries again.
+const REDIS_RETRY_INTERVAL: i64 = 1000;
+
+/// Returns true if an error means that Redis couldn't be reached, rather than that a command was invalid.
+fn is_outage(err: &StateError) -> bool {
+    match err {
+        StateError::Pool(_) | StateError::Unavailable => true,
+        StateError::Redis(err) => {
+            err.is_io_error()
+                || err.is_timeout()
+                || err.is_connection_dropped()
+                || err.is_connection_refusal()
+                || err.is_cluster_error()
+        }
+        _ => false,
+    }
+}
+
+/// Wraps a key in a hash tag on a Redis Cluster, so that every key a script derives from it lands in the same slot,
+/// which the scripts require.
+///
+/// Standalone servers keep the untagged layout, so rate limits and breakers stored before cluster support was added
+/// carry over.
+fn hash_tag(key: &str, cluster: bool) -> String {
+    if cluster {
+        format!("{{{}}}", key)
+    } else {
+        key.to_string()
+    }
+}
+
+/// Derives the keys a rate limit is stored under in Redis.
+fn rate_limit_keys(key: &str, cluster: bool) -> [String; 2] {
+    let key = hash_tag(key, cluster);
+    [format!("rl:{}", key), format!("rl:{}:ex", key)]
+}
+
+/// Derives the keys a circuit breaker is stored under in Redis.
+fn breaker_keys(key: &str, cluster: bool) -> [String; 5] {
+    let key = hash_tag(key, cluster);
+    ["g", "s", "f", "cs", "cf"].map(|counter| format!("bk:{}:{}", counter, key))
+}
+
+impl StateBackend for RedisStateBackend {
+    fn get(&self, key: &str) -> Result<Vec<u8>, StateError> {
+        self.with_connection(|conn| redis::cmd("GET").arg(key).query(conn))
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), StateError> {
+        self.with_connection(|conn| redis::cmd("SET").arg(key).arg(value).query(conn))
+    }
+
+    fn increment(&self, key: &str, delta: i64) -> Result<i64, StateError> {
+        self.with_connection(|conn| redis::cmd("INCRBY").arg(key).arg(delta).query(conn))
+    }
+
+    fn expire(&self, key: &str, ttl: i64) -> Result<(), StateError> {
+        // Redis deletes values with a non-positive expiration immediately.
+        self.with_connection(|conn| redis::cmd("EXPIRE").arg(key).arg(ttl).query(conn))
+    }
+
+    fn set_with_ttl(&self, key: &str, value: &[u8], ttl: i64) -> Result<(), StateError> {
+        if ttl <= 0 {
+            self.delete(key)?;
+            return Ok(());
+        }
+        self.with_connection(|conn| {
+            redis::cmd("SET")
+                .arg(key)
+                .arg(value)
+                .arg("EX")
+                .arg(ttl)
+                .query(conn)
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StateError> {
+        self.with_connection(|conn| redis::cmd("DEL").arg(key).query(conn))
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Result<Vec<Vec<u8>>, StateError> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+        if self.cluster {
+            // MGET can't span slots, so each key is read separately.
+            return keys.iter().map(|key| self.get(key)).collect();
+        }
+        self.with_connection(|conn| redis::cmd("MGET").arg(keys).query(conn))
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        expected: &[u8],
+        value: &[u8],
+    ) -> Result<bool, StateError> {
+        self.with_connection(|conn| {
+            self.registry
+                .compare_and_set
+                .key(key)
+                .arg(expected)
+                .arg(value)
+                .invoke(conn)
+        })
+    }
+
+    fn add_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        self.with_connection(|conn| redis::cmd("SADD").arg(key).arg(member).query(conn))
+    }
+
+    fn remove_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        self.with_connection(|conn| redis::cmd("SREM").arg(key).arg(member).query(conn))
+    }
+
+    fn is_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        self.with_connection(|conn| redis::cmd("SISMEMBER").arg(key).arg(member).query(conn))
+    }
+
+    fn set_members(&self, key: &str) -> Result<Vec<Vec<u8>>, StateError> {
+        self.with_connection(|conn| redis::cmd("SMEMBERS").arg(key).query(conn))
+    }
+
+    fn add_sorted_set_member(
+        &self,
+        key: &str,
+        member: &[u8],
+        score: f64,
+    ) -> Result<bool, StateError> {
+        self.with_connection(|conn| {
+            redis::cmd("ZADD")
+                .arg(key)
+                .arg(score)
+                .arg(member)
+                .query(conn)
+        })
+    }
+
+    fn remove_sorted_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        self.with_connection(|conn| redis::cmd("ZREM").arg(key).arg(member).query(conn))
+    }
+
+    fn sorted_set_range(
+        &self,
+        key: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, StateError> {
+        self.with_connection(|conn| {
+            redis::cmd("ZRANGEBYSCORE")
+                .arg(key)
+                .arg(min)
+                .arg(max)
+                .arg("WITHSCORES")
+                .query(conn)
+        })
+    }
+
+    fn remove_sorted_set_range(&self, key: &str, min: f64, max: f64) -> Result<u64, StateError> {
+        self.with_connection(|conn| {
+            redis::cmd("ZREMRANGEBYSCORE")
+                .arg(key)
+                .arg(min)
+                .arg(max)
+                .query(conn)
+        })
+    }
+
+    fn increment_rate_limit(
+        &self,
+        key: &str,
+        delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Rate, StateError> {
+        let (attempts, expiration) = self.with_connection(|conn| {
+            self.registry
+                .increment_rate_limit
+                .key(&rate_limit_keys(key, self.cluster))
+                .arg(delta)
+                .arg(window)
+                .arg(timestamp)
+                .invoke::<(i64, i64)>(conn)
+        })?;
+        Ok(Rate {
+            attempts,
+            expiration,
+        })
+    }
+
+    fn check_rate_limit(&self, key: &str, timestamp: i64) -> Result<Rate, StateError> {
+        // Lua truncates tables at the first nil, so a missing rate limit is returned as an empty list.
+        let values = self.with_connection(|conn| {
+            self.registry
+                .check_rate_limit
+                .key(&rate_limit_keys(key, self.cluster))
+                .arg(timestamp)
+                .invoke::<Vec<i64>>(conn)
+        })?;
+        let value = |index: usize| values.get(index).copied().unwrap_or_default();
+        Ok(Rate {
+            attempts: value(0),
+            expiration: value(1),
+        })
+    }
+
+    fn increment_breaker(
+        &self,
+        key: &str,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Breaker, StateError> {
+        let integers = self.with_connection(|conn| {
+            self.registry
+                .increment_breaker
+                .key(&breaker_keys(key, self.cluster))
+                .arg(success_delta)
+                .arg(failure_delta)
+                .arg(window)
+                .arg(timestamp)
+                .invoke::<(i64, i64, i64, i64, i64, i64)>(conn)
+        })?;
+        let (
+            generation,
+            successes,
+            failures,
+            consecutive_successes,
+            consecutive_failures,
+            expiration,
+        ) = integers;
+        Ok(Breaker {
+            generation,
+            successes,
+            failures,
+            consecutive_successes,
+            consecutive_failures,
+            expiration,
+        })
+    }
+
+    fn check_breaker(&self, key: &str, timestamp: i64) -> Result<Breaker, StateError> {
+        // Lua truncates tables at the first nil, so a missing breaker is returned as an empty list.
+        let values = self.with_connection(|conn| {
+            self.registry
+                .check_breaker
+                .key(&breaker_keys(key, self.cluster))
+                .arg(timestamp)
+                .invoke::<Vec<i64>>(conn)
+        })?;
+        let value = |index: usize| values.get(index).copied().unwrap_or_default();
+        Ok(Breaker {
+            generation: value(0),
+            successes: value(1),
+            failures: value(2),
+            consecutive_successes: value(3),
+            consecutive_failures: value(4),
+            expiration: value(5),
+        })
+    }
+
+    fn increment_sliding_window(
+        &self,
+        key: &str,
+        delta: i64,
+        limit: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let (start, previous, current) = self.with_connection(|conn| {
+            self.registry
+                .increment_sliding_window
+                .key(format!("sw:{}", key))
+                .arg(delta)
+                .arg(window)
+                .arg(timestamp)
+                .invoke::<(i64, i64, i64)>(conn)
+        })?;
+        Ok(SlidingWindow {
+            start,
+            previous,
+            current,
+        }
+        .allowance(delta, limit, window, timestamp))
+    }
+
+    fn take_token_bucket(
+        &self,
+        key: &str,
+        tokens: i64,
+        capacity: i64,
+        refill: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let bucket = TokenBucket::new(capacity, refill, window);
+        // The script returns the bucket as it was before tokens were taken, or an empty list for a full bucket.
+        let values = self.with_connection(|conn| {
+            self.registry
+                .take_token_bucket
+                .key(format!("tb:{}", key))
+                .arg(tokens)
+                .arg(bucket.interval)
+                .arg(bucket.tolerance)
+                .arg(timestamp)
+                .invoke::<Vec<i64>>(conn)
+        })?;
+        let (_, allowance) = bucket.take(values.first().copied(), tokens, timestamp);
+        Ok(allowance)
+    }
+
+    fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// A registry of predefined Lua scripts for execution within Redis.
+///
+/// Every key a script touches is passed in `KEYS` so that scripts can run against a Redis Cluster.
+pub struct ScriptRegistry {
+    /// Increments a Redis key's counter value if it has not yet expired.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    increment_rate_limit: redis::Script,
+    /// Checks a Redis key's counter value if it has not yet expired.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    check_rate_limit: redis::Script,
+    /// Increments a Redis key's counter value, corresponding to either success or failure, if it has not yet expired.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    increment_breaker: redis::Script,
+    /// Checks a Redis key's counter value, corresponding to either success or failure, if it has not yet expired.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    check_breaker: redis::Script,
+    /// Increments the current fixed window of a sliding window rate limit, moving on to a new fixed window if the
+    /// current one has ended.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    increment_sliding_window: redis::Script,
+    /// Takes tokens from a token bucket if enough are available.
+    ///
+    /// Uses the service's clock rather than Redis'. Uses Redis' TTL on a best-effort basis.
+    take_token_bucket: redis::Script,
+    /// Sets a Redis key's value if its current value matches the expected value.
+    ///
+    /// A missing key matches an empty expected value.
+    compare_and_set: redis::Script,
+}
+
+impl Default for ScriptRegistry {
+    fn default() -> ScriptRegistry {
+        ScriptRegistry {
+            // TODO: handle overflow errors by expiring everything on overflow and returning nil?
+            increment_rate_limit: redis::Script::new(
+                r#"
+                local counter_key = KEYS[1]
+                local expiration_key = KEYS[2]
+                local increment_delta = tonumber(ARGV[1])
+                local expiration_window = tonumber(ARGV[2])
+                local timestamp = tonumber(ARGV[3])
+                local expiration = tonumber(redis.call("get", expiration_key))
+                local next_expiration = timestamp + expiration_window
+                if not expiration or timestamp > expiration then
+                    redis.call("set", expiration_key, next_expiration)
+                    redis.call("set", counter_key, 0)
+                    redis.call("expireat", expiration_key, next_expiration + 1)
+                    redis.call("expireat", counter_key, next_expiration + 1)
+                    expiration = next_expiration
+                end
+                local attempts = redis.call("incrby", counter_key, increment_delta)
+                return { attempts, expiration }
+                "#,
+            ),
+            check_rate_limit: redis::Script::new(
+                r#"
+                local counter_key = KEYS[1]
+                local expiration_key = KEYS[2]
+                local timestamp = tonumber(ARGV[1])
+                local attempts = tonumber(redis.call("get", counter_key))
+                local expiration = nil
+                if attempts then
+                    expiration = tonumber(redis.call("get", expiration_key))
+                    if not expiration or timestamp > expiration then
+                        attempts = nil
+                        expiration = nil
+                    end
+                end
+                return { attempts, expiration }
+                "#,
+            ),
+            increment_breaker: redis::Script::new(
+                r#"
+                local generation_key = KEYS[1]
+                local success_key = KEYS[2]
+                local failure_key = KEYS[3]
+                local consec_success_key = KEYS[4]
+                local consec_failure_key = KEYS[5]
+                local success_delta = tonumber(ARGV[1])
+                local failure_delta = tonumber(ARGV[2])
+                local expiration_window = tonumber(ARGV[3])
+                local timestamp = tonumber(ARGV[4])
+                local expiration = timestamp + expiration_window
+                local generation = redis.call("incrby", generation_key, 1)
+                local successes = 0
+                local failures = 0
+                local consec_successes = 0
+                local consec_failures = 0
+                if success_delta > 0 then
+                    successes = redis.call("incrby", success_key, success_delta)
+                    failures = tonumber(redis.call("get", failure_key)) or 0
+                    consec_successes = redis.call("incrby", consec_success_key, success_delta)
+                    redis.call("set", consec_failure_key, 0)
+                    consec_failures = 0
+                else
+                    successes = tonumber(redis.call("get", success_key))
+                    failures = redis.call("incrby", failure_key, failure_delta) or 0
+                    redis.call("set", consec_success_key, 0)
+                    consec_successes = 0
+                    consec_failures = redis.call("incrby", consec_failure_key, failure_delta)
+                end
+                redis.call("expireat", generation_key, expiration + 1)
+                redis.call("expireat", success_key, expiration + 1)
+                redis.call("expireat", failure_key, expiration + 1)
+                redis.call("expireat", consec_success_key, expiration + 1)
+                redis.call("expireat", consec_failure_key, expiration + 1)
+                return { generation, successes, failures, consec_successes, consec_failures, expiration }
+                "#,
+            ),
+            check_breaker: redis::Script::new(
+                r#"
+                local generation_key = KEYS[1]
+                local success_key = KEYS[2]
+                local failure_key = KEYS[3]
+                local consec_success_key = KEYS[4]
+                local consec_failure_key = KEYS[5]
+                local generation = tonumber(redis.call("get", generation_key))
+                if not generation then
+                    return { nil, nil, nil, nil, nil, nil }
+                end
+                local successes = tonumber(redis.call("get", success_key)) or 0
+                local failures = tonumber(redis.call("get", failure_key)) or 0
+                local consec_successes = tonumber(redis.call("get", consec_success_key)) or 0
+                local consec_failures = tonumber(redis.call("get", consec_failure_key)) or 0
+                local expiration = tonumber(redis.call("expiretime", success_key)) - 1
+                return { generation, successes, failures, consec_successes, consec_failures, expiration }
+                "#,
+            ),
+            // Millisecond timestamps are formatted explicitly because Lua would otherwise format them in
+            // scientific notation once they exceed its default precision.
+            increment_sliding_window: redis::Script::new(
+                r#"
+                local counts_key = KEYS[1]
+                local increment_delta = tonumber(ARGV[1])
+                local window = tonumber(ARGV[2])
+                local timestamp = tonumber(ARGV[3])
+                local start = timestamp - (timestamp % window)
+                local counts = redis.call("hmget", counts_key, "start", "previous", "current")
+                local stored_start = tonumber(counts[1])
+                local previous = 0
+                local current = 0
+                if stored_start and stored_start >= start then
+                    start = stored_start
+                    previous = tonumber(counts[2]) or 0
+                    current = tonumber(counts[3]) or 0
+                elseif stored_start and stored_start + window >= start then
+                    previous = tonumber(counts[3]) or 0
+                end
+                current = current + increment_delta
+                redis.call(
+                    "hset", counts_key,
+                    "start", string.format("%.0f", start),
+                    "previous", previous,
+                    "current", current
+                )
+                redis.call("pexpireat", counts_key, string.format("%.0f", start + 2 * window))
+                return { start, previous, current }
+                "#,
+            ),
+            take_token_bucket: redis::Script::new(
+                r#"
+                local bucket_key = KEYS[1]
+                local tokens = tonumber(ARGV[1])
+                local interval = tonumber(ARGV[2])
+                local tolerance = tonumber(ARGV[3])
+                local timestamp = tonumber(ARGV[4])
+                local stored_full_at = tonumber(redis.call("get", bucket_key))
+                local full_at = stored_full_at
+                if not full_at or full_at < timestamp then
+                    full_at = timestamp
+                end
+                if tokens > 0 and full_at + interval * tokens - tolerance <= timestamp then
+                    full_at = full_at + interval * tokens
+                    redis.call("set", bucket_key, string.format("%.0f", full_at), "px", full_at - timestamp)
+                end
+                return { stored_full_at }
+                "#,
+            ),
+            compare_and_set: redis::Script::new(
+                r#"
+                local value_key = KEYS[1]
+                local current = redis.call("get", value_key) or ""
+                if current ~= ARGV[1] then
+                    return 0
+                end
+                redis.call("set", value_key, ARGV[2])
+                return 1
+                "#,
+            ),
+        }
+    }
+}
+
+/// A value held by the in-memory backend.
+enum MemoryValue {
+    Bytes(Vec<u8>),
+    Rate(Rate),
+    Breaker(Breaker),
+    SlidingWindow(SlidingWindow),
+    /// The unix timestamp, in milliseconds, at which a token bucket will be full.
+    TokenBucket(i64),
+    Set(HashSet<Vec<u8>>),
+    /// The members of a sorted set and their scores.
+    SortedSet(HashMap<Vec<u8>, f64>),
+}
+
+/// A value held by the in-memory backend and the unix timestamp, in seconds, at which it expires.
+struct MemoryEntry {
+    value: MemoryValue,
+    expires_at: Option<i64>,
+}
+
+impl MemoryEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if now >= expires_at)
+    }
+}
+
+/// The entries held by the in-memory backend.
+#[derive(Default)]
+struct MemoryStore {
+    entries: HashMap<String, MemoryEntry>,
+}
+
+impl MemoryStore {
+    /// Returns a live entry, removing it first if it has expired.
+    fn entry(&mut self, key: &str, now: i64) -> Option<&mut MemoryEntry> {
+        if matches!(self.entries.get(key), Some(entry) if entry.is_expired(now)) {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Returns the live set under a key, if any.
+    fn set(&mut self, key: &str, now: i64) -> Result<Option<&mut HashSet<Vec<u8>>>, StateError> {
+        match self.entry(key, now) {
+            Some(MemoryEntry {
+                value: MemoryValue::Set(set),
+                ..
+            }) => Ok(Some(set)),
+            Some(_) => Err(StateError::WrongType {
+                key: key.to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the live sorted set under a key, if any.
+    fn sorted_set(
+        &mut self,
+        key: &str,
+        now: i64,
+    ) -> Result<Option<&mut HashMap<Vec<u8>, f64>>, StateError> {
+        match self.entry(key, now) {
+            Some(MemoryEntry {
+                value: MemoryValue::SortedSet(scores),
+                ..
+            }) => Ok(Some(scores)),
+            Some(_) => Err(StateError::WrongType {
+                key: key.to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the entry under a key if it holds an empty set or sorted set, the same as Redis.
+    fn remove_if_empty(&mut self, key: &str) {
+        if matches!(
+            self.entries.get(key),
+            Some(MemoryEntry { value: MemoryValue::Set(set), .. }) if set.is_empty()
+        ) || matches!(
+            self.entries.get(key),
+            Some(MemoryEntry { value: MemoryValue::SortedSet(scores), .. }) if scores.is_empty()
+        ) {
+            self.entries.remove(key);
+        }
+    }
+
+    /// Evicts expired entries that haven't been read since they expired.
+    fn sweep(&mut self, now: i64) {
+        self.entries.retain(|_, entry| !entry.is_expired(now));
+    }
+}
+
+/// A state backend that keeps state in memory, for single-node deployments and tests.
+///
+/// State is lost when Bulwark restarts and is not shared between nodes. Expired values are evicted lazily when
+/// they're next accessed and periodically swept from the store by a background thread.
+pub struct MemoryStateBackend {
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryStateBackend {
+    /// Creates an empty [`MemoryStateBackend`] and starts sweeping it for expired entries.
+    pub fn new() -> Self {
+        let store = Arc::new(Mutex::new(MemoryStore::default()));
+        sweep_periodically(Arc::downgrade(&store), |store, now| {
+            store.lock().unwrap().sweep(now)
+        });
+        Self { store }
+    }
+}
+
+impl Default for MemoryStateBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateBackend for MemoryStateBackend {
+    fn get(&self, key: &str) -> Result<Vec<u8>, StateError> {
+        let mut store = self.store.lock().unwrap();
+        Ok(match store.entry(key, Utc::now().timestamp()) {
+            Some(MemoryEntry {
+                value: MemoryValue::Bytes(value),
+                ..
+            }) => value.clone(),
+            _ => vec![],
+        })
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), StateError> {
+        let mut store = self.store.lock().unwrap();
+        store.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: MemoryValue::Bytes(value.to_vec()),
+                expires_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    fn increment(&self, key: &str, delta: i64) -> Result<i64, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        // Counters are stored as decimal strings, the same as Redis, so they can be read back with `get`.
+        let (current, expires_at) = match store.entry(key, now) {
+            Some(MemoryEntry {
+                value: MemoryValue::Bytes(value),
+                expires_at,
+            }) => (
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or_else(|| StateError::InvalidCounter {
+                        key: key.to_string(),
+                    })?,
+                *expires_at,
+            ),
+            Some(_) => {
+                return Err(StateError::InvalidCounter {
+                    key: key.to_string(),
+                })
+            }
+            None => (0, None),
+        };
+        let value = current
+            .checked_add(delta)
+            .ok_or_else(|| StateError::InvalidCounter {
+                key: key.to_string(),
+            })?;
+        store.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: MemoryValue::Bytes(value.to_string().into_bytes()),
+                expires_at,
+            },
+        );
+        Ok(value)
+    }
+
+    fn expire(&self, key: &str, ttl: i64) -> Result<(), StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        if ttl <= 0 {
+            store.entries.remove(key);
+        } else if let Some(entry) = store.entry(key, now) {
+            entry.expires_at = Some(now.saturating_add(ttl));
+        }
+        Ok(())
+    }
+
+    fn set_with_ttl(&self, key: &str, value: &[u8], ttl: i64) -> Result<(), StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        if ttl <= 0 {
+            store.entries.remove(key);
+        } else {
+            store.entries.insert(
+                key.to_string(),
+                MemoryEntry {
+                    value: MemoryValue::Bytes(value.to_vec()),
+                    expires_at: Some(now.saturating_add(ttl)),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        Ok(store.entry(key, now).is_some() && store.entries.remove(key).is_some())
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Result<Vec<Vec<u8>>, StateError> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        expected: &[u8],
+        value: &[u8],
+    ) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        let matches = match store.entry(key, now) {
+            Some(MemoryEntry {
+                value: MemoryValue::Bytes(current),
+                ..
+            }) => current.as_slice() == expected,
+            Some(_) => {
+                return Err(StateError::WrongType {
+                    key: key.to_string(),
+                })
+            }
+            None => expected.is_empty(),
+        };
+        if matches {
+            store.entries.insert(
+                key.to_string(),
+                MemoryEntry {
+                    value: MemoryValue::Bytes(value.to_vec()),
+                    expires_at: None,
+                },
+            );
+        }
+        Ok(matches)
+    }
+
+    fn add_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        if let Some(set) = store.set(key, now)? {
+            return Ok(set.insert(member.to_vec()));
+        }
+        store.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: MemoryValue::Set(HashSet::from([member.to_vec()])),
+                expires_at: None,
+            },
+        );
+        Ok(true)
+    }
+
+    fn remove_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        let removed = match store.set(key, now)? {
+            Some(set) => set.remove(member),
+            None => false,
+        };
+        store.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    fn is_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        Ok(matches!(store.set(key, now)?, Some(set) if set.contains(member)))
+    }
+
+    fn set_members(&self, key: &str) -> Result<Vec<Vec<u8>>, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        Ok(store
+            .set(key, now)?
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn add_sorted_set_member(
+        &self,
+        key: &str,
+        member: &[u8],
+        score: f64,
+    ) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        if let Some(scores) = store.sorted_set(key, now)? {
+            return Ok(scores.insert(member.to_vec(), score).is_none());
+        }
+        store.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: MemoryValue::SortedSet(HashMap::from([(member.to_vec(), score)])),
+                expires_at: None,
+            },
+        );
+        Ok(true)
+    }
+
+    fn remove_sorted_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        let removed = match store.sorted_set(key, now)? {
+            Some(scores) => scores.remove(member).is_some(),
+            None => false,
+        };
+        store.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    fn sorted_set_range(
+        &self,
+        key: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        let mut range: Vec<(Vec<u8>, f64)> = match store.sorted_set(key, now)? {
+            Some(scores) => scores
+                .iter()
+                .filter(|(_, score)| (min..=max).contains(*score))
+                .map(|(member, score)| (member.clone(), *score))
+                .collect(),
+            None => vec![],
+        };
+        range.sort_by(|(a_member, a_score), (b_member, b_score)| {
+            a_score
+                .total_cmp(b_score)
+                .then_with(|| a_member.cmp(b_member))
+        });
+        Ok(range)
+    }
+
+    fn remove_sorted_set_range(&self, key: &str, min: f64, max: f64) -> Result<u64, StateError> {
+        let now = Utc::now().timestamp();
+        let mut store = self.store.lock().unwrap();
+        let removed = match store.sorted_set(key, now)? {
+            Some(scores) => {
+                let before = scores.len();
+                scores.retain(|_, score| !(min..=max).contains(score));
+                (before - scores.len()) as u64
+            }
+            None => 0,
+        };
+        store.remove_if_empty(key);
+        Ok(removed)
+    }
+
+    fn increment_rate_limit(
+        &self,
+        key: &str,
+        delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Rate, StateError> {
+        let mut store = self.store.lock().unwrap();
+        let key = format!("rl:{}", key);
+        let current = match store.entries.get(&key) {
+            Some(MemoryEntry {
+                value: MemoryValue::Rate(rate),
+                ..
+            }) => Some(*rate),
+            _ => None,
+        };
+        let rate = Rate::incremented(current, delta, window, timestamp);
+        store.entries.insert(
+            key,
+            MemoryEntry {
+                value: MemoryValue::Rate(rate),
+                expires_at: Some(rate.expiration + 1),
+            },
+        );
+        Ok(rate)
+    }
+
+    fn check_rate_limit(&self, key: &str, timestamp: i64) -> Result<Rate, StateError> {
+        let store = self.store.lock().unwrap();
+        let current = match store.entries.get(&format!("rl:{}", key)) {
+            Some(MemoryEntry {
+                value: MemoryValue::Rate(rate),
+                ..
+            }) => Some(*rate),
+            _ => None,
+        };
+        Ok(Rate::checked(current, timestamp))
+    }
+
+    fn increment_breaker(
+        &self,
+        key: &str,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Breaker, StateError> {
+        let mut store = self.store.lock().unwrap();
+        let key = format!("bk:{}", key);
+        let current = match store.entries.get(&key) {
+            Some(MemoryEntry {
+                value: MemoryValue::Breaker(breaker),
+                ..
+            }) => Some(*breaker),
+            _ => None,
+        };
+        let breaker =
+            Breaker::incremented(current, success_delta, failure_delta, window, timestamp);
+        store.entries.insert(
+            key,
+            MemoryEntry {
+                value: MemoryValue::Breaker(breaker),
+                expires_at: Some(breaker.expiration + 1),
+            },
+        );
+        Ok(breaker)
+    }
+
+    fn check_breaker(&self, key: &str, timestamp: i64) -> Result<Breaker, StateError> {
+        let store = self.store.lock().unwrap();
+        let current = match store.entries.get(&format!("bk:{}", key)) {
+            Some(MemoryEntry {
+                value: MemoryValue::Breaker(breaker),
+                ..
+            }) => Some(*breaker),
+            _ => None,
+        };
+        Ok(Breaker::checked(current, timestamp))
+    }
+
+    fn increment_sliding_window(
+        &self,
+        key: &str,
+        delta: i64,
+        limit: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let mut store = self.store.lock().unwrap();
+        let key = format!("sw:{}", key);
+        let current = match store.entries.get(&key) {
+            Some(MemoryEntry {
+                value: MemoryValue::SlidingWindow(counts),
+                ..
+            }) => Some(*counts),
+            _ => None,
+        };
+        let counts = SlidingWindow::incremented(current, delta, window, timestamp);
+        store.entries.insert(
+            key,
+            MemoryEntry {
+                value: MemoryValue::SlidingWindow(counts),
+                expires_at: Some(seconds_from_millis(counts.expiration(window))),
+            },
+        );
+        Ok(counts.allowance(delta, limit, window, timestamp))
+    }
+
+    fn take_token_bucket(
+        &self,
+        key: &str,
+        tokens: i64,
+        capacity: i64,
+        refill: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let mut store = self.store.lock().unwrap();
+        let key = format!("tb:{}", key);
+        let current = match store.entries.get(&key) {
+            Some(MemoryEntry {
+                value: MemoryValue::TokenBucket(full_at),
+                ..
+            }) => Some(*full_at),
+            _ => None,
+        };
+        let (full_at, allowance) =
+            TokenBucket::new(capacity, refill, window).take(current, tokens, timestamp);
+        if let Some(full_at) = full_at {
+            store.entries.insert(
+                key,
+                MemoryEntry {
+                    value: MemoryValue::TokenBucket(full_at),
+                    expires_at: Some(seconds_from_millis(full_at)),
+                },
+            );
+        }
+        Ok(allowance)
+    }
+}
+
+/// Marks a value held by the sled backend that never expires.
+const SLED_NO_EXPIRATION: i64 = i64::MAX;
+
+/// A value held by the sled backend and the unix timestamp, in seconds, at which it expires.
+struct SledEntry {
+    payload: Vec<u8>,
+    expires_at: Option<i64>,
+}
+
+impl SledEntry {
+    /// Encodes the entry as its expiration followed by its payload.
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(8 + self.payload.len());
+        encoded.extend_from_slice(&self.expires_at.unwrap_or(SLED_NO_EXPIRATION).to_be_bytes());
+        encoded.extend_from_slice(&self.payload);
+        encoded
+    }
+
+    /// Decodes an entry, returning `None` if it has expired.
+    fn decode(encoded: &[u8], now: i64) -> Option<SledEntry> {
+        let expires_at = i64::from_be_bytes(encoded.get(..8)?.try_into().ok()?);
+        if now >= expires_at {
+            return None;
+        }
+        Some(SledEntry {
+            payload: encoded[8..].to_vec(),
+            expires_at: Some(expires_at).filter(|expires_at| *expires_at != SLED_NO_EXPIRATION),
+        })
+    }
+}
+
+/// Encodes a fixed number of integers, used to store rate limits and circuit breakers.
+fn encode_integers<const N: usize>(integers: [i64; N]) -> Vec<u8> {
+    integers
+        .iter()
+        .flat_map(|integer| integer.to_be_bytes())
+        .collect()
+}
+
+/// Decodes a fixed number of integers, returning `None` if the payload is the wrong size.
+fn decode_integers<const N: usize>(payload: &[u8]) -> Option<[i64; N]> {
+    if payload.len() != N * 8 {
+        return None;
+    }
+    let mut integers = [0; N];
+    for (integer, bytes) in integers.iter_mut().zip(payload.chunks_exact(8)) {
+        *integer = i64::from_be_bytes(bytes.try_into().ok()?);
+    }
+    Some(integers)
+}
+
+/// Encodes the members of a set or sorted set, each as its score, its length, and its bytes.
+fn encode_members(members: &BTreeMap<Vec<u8>, f64>) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (member, score) in members {
+        payload.extend_from_slice(&score.to_be_bytes());
+        payload.extend_from_slice(&(member.len() as u32).to_be_bytes());
+        payload.extend_from_slice(member);
+    }
+    payload
+}
+
+/// Decodes the members of a set or sorted set, returning `None` if the payload is malformed.
+fn decode_members(mut payload: &[u8]) -> Option<BTreeMap<Vec<u8>, f64>> {
+    let mut members = BTreeMap::new();
+    while !payload.is_empty() {
+        let score = f64::from_be_bytes(payload.get(..8)?.try_into().ok()?);
+        let length = u32::from_be_bytes(payload.get(8..12)?.try_into().ok()?) as usize;
+        let member = payload.get(12..12 + length)?;
+        members.insert(member.to_vec(), score);
+        payload = &payload[12 + length..];
+    }
+    Some(members)
+}
+
+/// The trees the sled backend keeps each kind of state in.
+///
+/// Values live in the database's default tree. Everything else has a tree of its own, so the keys plugins choose
+/// never mix with the backend's own.
+struct SledStore {
+    values: sled::Db,
+    sets: sled::Tree,
+    sorted_sets: sled::Tree,
+    rate_limits: sled::Tree,
+    breakers: sled::Tree,
+    sliding_windows: sled::Tree,
+    token_buckets: sled::Tree,
+}
+
+impl SledStore {
+    /// Opens the database and its trees, creating them if they don't exist.
+    fn open(path: impl AsRef<Path>) -> Result<Self, StateError> {
+        let db = sled::open(path)?;
+        Ok(Self {
+            sets: db.open_tree("sets")?,
+            sorted_sets: db.open_tree("sorted_sets")?,
+            rate_limits: db.open_tree("rate_limits")?,
+            breakers: db.open_tree("breakers")?,
+            sliding_windows: db.open_tree("sliding_windows")?,
+            token_buckets: db.open_tree("token_buckets")?,
+            values: db,
+        })
+    }
+
+    /// The trees that may hold a value, a set, and a sorted set under a single key.
+    fn keyed_trees(&self) -> [&sled::Tree; 3] {
+        [&self.values, &self.sets, &self.sorted_sets]
+    }
+
+    /// Evicts expired entries that haven't been read since they expired.
+    fn sweep(&self, now: i64) -> Result<(), StateError> {
+        for tree in [
+            &self.values,
+            &self.sets,
+            &self.sorted_sets,
+            &self.rate_limits,
+            &self.breakers,
+            &self.sliding_windows,
+            &self.token_buckets,
+        ] {
+            for item in tree.iter() {
+                let (key, encoded) = item?;
+                if SledEntry::decode(&encoded, now).is_none() {
+                    // Entries that were refreshed since they were read are left alone.
+                    tree.compare_and_swap(key, Some(encoded), None as Option<&[u8]>)?
+                        .ok();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the live entry under a key in a sled tree, if any.
+fn sled_entry(tree: &sled::Tree, key: &str, now: i64) -> Result<Option<SledEntry>, StateError> {
+    Ok(tree
+        .get(key)?
+        .and_then(|encoded| SledEntry::decode(&encoded, now)))
+}
+
+/// Atomically replaces the entry under a key in a sled tree, returning the entry that was written.
+///
+/// The `update` closure receives the live entry, if any, and returns the entry to write in its place, or `None` to
+/// leave the key untouched. It may be called more than once if the key is written to concurrently.
+fn sled_update(
+    tree: &sled::Tree,
+    key: &str,
+    mut update: impl FnMut(Option<SledEntry>) -> Result<Option<SledEntry>, StateError>,
+) -> Result<Option<SledEntry>, StateError> {
+    let now = Utc::now().timestamp();
+    loop {
+        let current = tree.get(key)?;
+        let entry = current
+            .as_ref()
+            .and_then(|encoded| SledEntry::decode(encoded, now));
+        let next = match update(entry)? {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        if tree
+            .compare_and_swap(key, current, Some(next.encode()))?
+            .is_ok()
+        {
+            return Ok(Some(next));
+        }
+    }
+}
+
+/// Returns the live members of the set or sorted set stored under a key in a sled tree.
+fn sled_members(tree: &sled::Tree, key: &str) -> Result<BTreeMap<Vec<u8>, f64>, StateError> {
+    match sled_entry(tree, key, Utc::now().timestamp())? {
+        Some(entry) => decode_members(&entry.payload).ok_or_else(|| StateError::WrongType {
+            key: key.to_string(),
+        }),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Atomically updates the members of the set or sorted set stored under a key in a sled tree, returning the result
+/// of the `update` closure.
+///
+/// Sets and sorted sets that are left empty are removed, the same as Redis.
+fn sled_update_members<T: Default>(
+    tree: &sled::Tree,
+    key: &str,
+    mut update: impl FnMut(&mut BTreeMap<Vec<u8>, f64>) -> T,
+) -> Result<T, StateError> {
+    let mut result = T::default();
+    let written = sled_update(tree, key, |entry| {
+        let (mut members, expires_at) = match entry {
+            Some(entry) => (
+                decode_members(&entry.payload).ok_or_else(|| StateError::WrongType {
+                    key: key.to_string(),
+                })?,
+                entry.expires_at,
+            ),
+            None => (BTreeMap::new(), None),
+        };
+        result = update(&mut members);
+        Ok(Some(SledEntry {
+            payload: encode_members(&members),
+            expires_at,
+        }))
+    })?;
+    if let Some(entry) = written.filter(|entry| entry.payload.is_empty()) {
+        tree.compare_and_swap(key, Some(entry.encode()), None as Option<&[u8]>)?
+            .ok();
+    }
+    Ok(result)
+}
+
+/// A state backend that keeps state in an embedded [sled](https://docs.rs/sled) database on local disk.
+///
+/// State survives restarts but is not shared between nodes, which suits single-node and edge deployments without a
+/// Redis server. Expired values are evicted lazily when they're next accessed and periodically swept from disk by a
+/// background thread.
+///
+/// Sets and sorted sets are stored separately from other values, so unlike Redis, a key may hold a value, a set, and
+/// a sorted set at the same time. Deleting or expiring a key applies to all three.
+pub struct SledStateBackend {
+    store: Arc<SledStore>,
+}
+
+impl SledStateBackend {
+    /// Opens a [`SledStateBackend`], creating the database if it doesn't exist, and starts sweeping it for expired
+    /// entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory the database is stored in.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StateError> {
+        let store = Arc::new(SledStore::open(path)?);
+        sweep_periodically(Arc::downgrade(&store), |store, now| {
+            if let Err(err) = store.sweep(now) {
+                warn!(message = "state sweep failed", error_message = %err);
+            }
+        });
+        Ok(Self { store })
+    }
+}
+
+impl StateBackend for SledStateBackend {
+    fn get(&self, key: &str) -> Result<Vec<u8>, StateError> {
+        Ok(sled_entry(&self.store.values, key, Utc::now().timestamp())?
+            .map(|entry| entry.payload)
+            .unwrap_or_default())
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<(), StateError> {
+        self.store.values.insert(
+            key,
+            SledEntry {
+                payload: value.to_vec(),
+                expires_at: None,
+            }
+            .encode(),
+        )?;
+        Ok(())
+    }
+
+    fn increment(&self, key: &str, delta: i64) -> Result<i64, StateError> {
+        let invalid_counter = || StateError::InvalidCounter {
+            key: key.to_string(),
+        };
+        let mut value = 0;
+        // Counters are stored as decimal strings, the same as Redis, so they can be read back with `get`.
+        sled_update(&self.store.values, key, |entry| {
+            let (current, expires_at) = match entry {
+                Some(entry) => (
+                    std::str::from_utf8(&entry.payload)
+                        .ok()
+                        .and_then(|value| value.parse::<i64>().ok())
+                        .ok_or_else(invalid_counter)?,
+                    entry.expires_at,
+                ),
+                None => (0, None),
+            };
+            value = current.checked_add(delta).ok_or_else(invalid_counter)?;
+            Ok(Some(SledEntry {
+                payload: value.to_string().into_bytes(),
+                expires_at,
+            }))
+        })?;
+        Ok(value)
+    }
+
+    fn expire(&self, key: &str, ttl: i64) -> Result<(), StateError> {
+        if ttl <= 0 {
+            self.delete(key)?;
+            return Ok(());
+        }
+        let expires_at = Utc::now().timestamp().saturating_add(ttl);
+        for tree in self.store.keyed_trees() {
+            sled_update(tree, key, |entry| {
+                Ok(entry.map(|entry| SledEntry {
+                    payload: entry.payload,
+                    expires_at: Some(expires_at),
+                }))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn set_with_ttl(&self, key: &str, value: &[u8], ttl: i64) -> Result<(), StateError> {
+        let now = Utc::now().timestamp();
+        if ttl <= 0 {
+            self.store.values.remove(key)?;
+        } else {
+            self.store.values.insert(
+                key,
+                SledEntry {
+                    payload: value.to_vec(),
+                    expires_at: Some(now.saturating_add(ttl)),
+                }
+                .encode(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StateError> {
+        let now = Utc::now().timestamp();
+        let mut existed = false;
+        for tree in self.store.keyed_trees() {
+            if let Some(encoded) = tree.remove(key)? {
+                existed |= SledEntry::decode(&encoded, now).is_some();
+            }
+        }
+        Ok(existed)
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Result<Vec<Vec<u8>>, StateError> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        expected: &[u8],
+        value: &[u8],
+    ) -> Result<bool, StateError> {
+        let written = sled_update(&self.store.values, key, |entry| {
+            let current = entry.map(|entry| entry.payload).unwrap_or_default();
+            Ok((current == expected).then(|| SledEntry {
+                payload: value.to_vec(),
+                expires_at: None,
+            }))
+        })?;
+        Ok(written.is_some())
+    }
+
+    fn add_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        sled_update_members(&self.store.sets, key, |members| {
+            members.insert(member.to_vec(), 0.0).is_none()
+        })
+    }
+
+    fn remove_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        sled_update_members(&self.store.sets, key, |members| {
+            members.remove(member).is_some()
+        })
+    }
+
+    fn is_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        Ok(sled_members(&self.store.sets, key)?.contains_key(member))
+    }
+
+    fn set_members(&self, key: &str) -> Result<Vec<Vec<u8>>, StateError> {
+        Ok(sled_members(&self.store.sets, key)?.into_keys().collect())
+    }
+
+    fn add_sorted_set_member(
+        &self,
+        key: &str,
+        member: &[u8],
+        score: f64,
+    ) -> Result<bool, StateError> {
+        sled_update_members(&self.store.sorted_sets, key, |members| {
+            members.insert(member.to_vec(), score).is_none()
+        })
+    }
+
+    fn remove_sorted_set_member(&self, key: &str, member: &[u8]) -> Result<bool, StateError> {
+        sled_update_members(&self.store.sorted_sets, key, |members| {
+            members.remove(member).is_some()
+        })
+    }
+
+    fn sorted_set_range(
+        &self,
+        key: &str,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<(Vec<u8>, f64)>, StateError> {
+        // Members are already ordered by their bytes, so a stable sort by score orders ties correctly.
+        let mut range: Vec<(Vec<u8>, f64)> = sled_members(&self.store.sorted_sets, key)?
+            .into_iter()
+            .filter(|(_, score)| (min..=max).contains(score))
+            .collect();
+        range.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        Ok(range)
+    }
+
+    fn remove_sorted_set_range(&self, key: &str, min: f64, max: f64) -> Result<u64, StateError> {
+        sled_update_members(&self.store.sorted_sets, key, |members| {
+            let before = members.len();
+            members.retain(|_, score| !(min..=max).contains(score));
+            (before - members.len()) as u64
+        })
+    }
+
+    fn increment_rate_limit(
+        &self,
+        key: &str,
+        delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Rate, StateError> {
+        let mut rate = Rate::default();
+        sled_update(&self.store.rate_limits, key, |entry| {
+            let current = entry.and_then(|entry| decode_integers(&entry.payload)).map(
+                |[attempts, expiration]| Rate {
+                    attempts,
+                    expiration,
+                },
+            );
+            rate = Rate::incremented(current, delta, window, timestamp);
+            Ok(Some(SledEntry {
+                payload: encode_integers([rate.attempts, rate.expiration]),
+                expires_at: Some(rate.expiration + 1),
+            }))
+        })?;
+        Ok(rate)
+    }
+
+    fn check_rate_limit(&self, key: &str, timestamp: i64) -> Result<Rate, StateError> {
+        let current = sled_entry(&self.store.rate_limits, key, Utc::now().timestamp())?
+            .and_then(|entry| decode_integers(&entry.payload))
+            .map(|[attempts, expiration]| Rate {
+                attempts,
+                expiration,
+            });
+        Ok(Rate::checked(current, timestamp))
+    }
+
+    fn increment_breaker(
+        &self,
+        key: &str,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Breaker, StateError> {
+        let mut breaker = Breaker::default();
+        sled_update(&self.store.breakers, key, |entry| {
+            let current = entry
+                .and_then(|entry| decode_integers(&entry.payload))
+                .map(Breaker::from_integers);
+            breaker =
+                Breaker::incremented(current, success_delta, failure_delta, window, timestamp);
+            Ok(Some(SledEntry {
+                payload: encode_integers(breaker.to_integers()),
+                expires_at: Some(breaker.expiration + 1),
+            }))
+        })?;
+        Ok(breaker)
+    }
+
+    fn check_breaker(&self, key: &str, timestamp: i64) -> Result<Breaker, StateError> {
+        let current = sled_entry(&self.store.breakers, key, Utc::now().timestamp())?
+            .and_then(|entry| decode_integers(&entry.payload))
+            .map(Breaker::from_integers);
+        Ok(Breaker::checked(current, timestamp))
+    }
+
+    fn increment_sliding_window(
+        &self,
+        key: &str,
+        delta: i64,
+        limit: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let mut counts = SlidingWindow::default();
+        sled_update(&self.store.sliding_windows, key, |entry| {
+            let current = entry.and_then(|entry| decode_integers(&entry.payload)).map(
+                |[start, previous, current]| SlidingWindow {
+                    start,
+                    previous,
+                    current,
+                },
+            );
+            counts = SlidingWindow::incremented(current, delta, window, timestamp);
+            Ok(Some(SledEntry {
+                payload: encode_integers([counts.start, counts.previous, counts.current]),
+                expires_at: Some(seconds_from_millis(counts.expiration(window))),
+            }))
+        })?;
+        Ok(counts.allowance(delta, limit, window, timestamp))
+    }
+
+    fn take_token_bucket(
+        &self,
+        key: &str,
+        tokens: i64,
+        capacity: i64,
+        refill: i64,
+        window: i64,
+        timestamp: i64,
+    ) -> Result<Allowance, StateError> {
+        let bucket = TokenBucket::new(capacity, refill, window);
+        let mut allowance = Allowance::default();
+        sled_update(&self.store.token_buckets, key, |entry| {
+            let current = entry
+                .and_then(|entry| decode_integers(&entry.payload))
+                .map(|[full_at]| full_at);
+            let (full_at, taken) = bucket.take(current, tokens, timestamp);
+            allowance = taken;
+            Ok(full_at.map(|full_at| SledEntry {
+                payload: encode_integers([full_at]),
+                expires_at: Some(seconds_from_millis(full_at)),
+            }))
+        })?;
+        Ok(allowance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_state() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        assert_eq!(backend.get("missing")?, Vec::<u8>::new());
+
+        backend.set("greeting", b"hello")?;
+        assert_eq!(backend.get("greeting")?, b"hello".to_vec());
+
+        assert_eq!(backend.increment("counter", 1)?, 1);
+        assert_eq!(backend.increment("counter", 5)?, 6);
+        assert_eq!(backend.get("counter")?, b"6".to_vec());
+        assert!(matches!(
+            backend.increment("greeting", 1),
+            Err(StateError::InvalidCounter { .. })
+        ));
+
+        backend.expire("counter", 60)?;
+        assert_eq!(backend.increment("counter", 1)?, 7);
+        backend.expire("counter", 0)?;
+        assert_eq!(backend.get("counter")?, Vec::<u8>::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_sweep() {
+        let mut store = MemoryStore::default();
+        for (key, expires_at) in [
+            ("stale", Some(100)),
+            ("fresh", Some(200)),
+            ("lasting", None),
+        ] {
+            store.entries.insert(
+                key.to_string(),
+                MemoryEntry {
+                    value: MemoryValue::Bytes(vec![]),
+                    expires_at,
+                },
+            );
+        }
+
+        store.sweep(150);
+        let mut keys: Vec<&str> = store.entries.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["fresh", "lasting"]);
+    }
+
+    #[test]
+    fn test_memory_rate_limit() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        assert_eq!(backend.check_rate_limit("client", 1000)?, Rate::default());
+
+        let rate = backend.increment_rate_limit("client", 1, 60, 1000)?;
+        assert_eq!(rate.attempts, 1);
+        assert_eq!(rate.expiration, 1060);
+        let rate = backend.increment_rate_limit("client", 2, 60, 1030)?;
+        assert_eq!(rate.attempts, 3);
+        assert_eq!(rate.expiration, 1060);
+        assert_eq!(backend.check_rate_limit("client", 1060)?, rate);
+
+        // A new period begins once the previous one has ended.
+        assert_eq!(backend.check_rate_limit("client", 1061)?, Rate::default());
+        let rate = backend.increment_rate_limit("client", 1, 60, 1061)?;
+        assert_eq!(rate.attempts, 1);
+        assert_eq!(rate.expiration, 1121);
+
+        Ok(())
+    }
+
+    /// Exercises the operations on values, sets, and sorted sets that every backend must agree on.
+    fn check_collections(backend: &dyn StateBackend) -> Result<(), StateError> {
+        backend.set_with_ttl("session", b"alice", 3600)?;
+        backend.set_with_ttl("expired", b"bob", 0)?;
+        assert_eq!(
+            backend.get_many(&["session", "expired", "missing"])?,
+            vec![b"alice".to_vec(), vec![], vec![]]
+        );
+
+        assert!(!backend.compare_and_set("session", b"bob", b"carol")?);
+        assert!(backend.compare_and_set("session", b"alice", b"carol")?);
+        assert!(backend.compare_and_set("fresh", b"", b"dave")?);
+        assert_eq!(backend.get("session")?, b"carol".to_vec());
+        assert_eq!(backend.get("fresh")?, b"dave".to_vec());
+        assert!(backend.delete("session")?);
+        assert!(!backend.delete("session")?);
+
+        assert!(backend.add_set_member("seen", b"a")?);
+        assert!(backend.add_set_member("seen", b"b")?);
+        assert!(!backend.add_set_member("seen", b"a")?);
+        assert!(backend.is_set_member("seen", b"b")?);
+        assert!(backend.remove_set_member("seen", b"b")?);
+        assert!(!backend.is_set_member("seen", b"b")?);
+        assert_eq!(backend.set_members("seen")?, vec![b"a".to_vec()]);
+        assert!(backend.remove_set_member("seen", b"a")?);
+        assert!(!backend.delete("seen")?);
+
+        assert!(backend.add_sorted_set_member("log", b"b", 20.0)?);
+        assert!(backend.add_sorted_set_member("log", b"a", 10.0)?);
+        assert!(backend.add_sorted_set_member("log", b"c", 20.0)?);
+        assert!(!backend.add_sorted_set_member("log", b"a", 30.0)?);
+        assert_eq!(
+            backend.sorted_set_range("log", f64::NEG_INFINITY, f64::INFINITY)?,
+            vec![
+                (b"b".to_vec(), 20.0),
+                (b"c".to_vec(), 20.0),
+                (b"a".to_vec(), 30.0)
+            ]
+        );
+        assert_eq!(backend.remove_sorted_set_range("log", 0.0, 20.0)?, 2);
+        assert!(backend.remove_sorted_set_member("log", b"a")?);
+        assert_eq!(backend.sorted_set_range("log", 0.0, 100.0)?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_collections() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        check_collections(&backend)?;
+
+        backend.set("greeting", b"hello")?;
+        assert!(matches!(
+            backend.add_set_member("greeting", b"a"),
+            Err(StateError::WrongType { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sled_collections() -> Result<(), StateError> {
+        let path = std::env::temp_dir().join(format!(
+            "bulwark-sled-collections-test-{}",
+            std::process::id()
+        ));
+        let backend = SledStateBackend::open(&path)?;
+        check_collections(&backend)?;
+
+        // Deleting or expiring a key applies to its sets as well as its value.
+        backend.set("visitor", b"hello")?;
+        backend.add_set_member("visitor", b"a")?;
+        backend.expire("visitor", 3600)?;
+        assert!(backend.is_set_member("visitor", b"a")?);
+        assert!(backend.delete("visitor")?);
+        assert_eq!(backend.set_members("visitor")?, Vec::<Vec<u8>>::new());
+
+        drop(backend);
+        std::fs::remove_dir_all(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sled_keyspaces() -> Result<(), StateError> {
+        let path = std::env::temp_dir().join(format!(
+            "bulwark-sled-keyspaces-test-{}",
+            std::process::id()
+        ));
+        let backend = SledStateBackend::open(&path)?;
+        let now = Utc::now().timestamp();
+
+        // Plugin keys that resemble the backend's own never touch its internal state.
+        backend.set("rl:client", b"not a rate limit")?;
+        assert_eq!(
+            backend.increment_rate_limit("client", 1, 60, now)?.attempts,
+            1
+        );
+        assert_eq!(backend.get("rl:client")?, b"not a rate limit".to_vec());
+        assert!(!backend.delete("client")?);
+        assert_eq!(backend.check_rate_limit("client", now)?.attempts, 1);
+
+        // Sweeping evicts expired entries from every tree.
+        backend.increment_rate_limit("expired", 1, 1, now - 120)?;
+        backend.set_with_ttl("session", b"alice", 3600)?;
+        backend.store.sweep(now)?;
+        assert!(backend.store.rate_limits.get("expired")?.is_none());
+        assert!(backend.store.rate_limits.get("client")?.is_some());
+        assert_eq!(backend.get("session")?, b"alice".to_vec());
+
+        drop(backend);
+        std::fs::remove_dir_all(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_sliding_window() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        let allowance = backend.increment_sliding_window("client", 4, 10, 60_000, 0)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 6);
+        assert_eq!(allowance.retry_after, 0);
+
+        let allowance = backend.increment_sliding_window("client", 6, 10, 60_000, 30_000)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 0);
+        // Another six attempts fit once the current window has ended and mostly slid out of the sliding window.
+        assert_eq!(allowance.retry_after, 66);
+
+        // The previous window still counts for most of the next one, so there's no burst at the boundary.
+        let allowance = backend.increment_sliding_window("client", 0, 10, 60_000, 66_000)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 1);
+        let allowance = backend.increment_sliding_window("client", 1, 10, 60_000, 66_000)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 0);
+        assert_eq!(allowance.retry_after, 6);
+        let allowance = backend.increment_sliding_window("client", 1, 10, 60_000, 66_000)?;
+        assert!(!allowance.allowed);
+        assert_eq!(allowance.remaining, 0);
+
+        let allowance = backend.increment_sliding_window("client", 0, 10, 60_000, 180_000)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_token_bucket() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        let allowance = backend.take_token_bucket("client", 5, 5, 1, 1000, 0)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 0);
+        assert_eq!(allowance.retry_after, 5);
+
+        // Denied attempts don't take tokens.
+        let allowance = backend.take_token_bucket("client", 1, 5, 1, 1000, 0)?;
+        assert!(!allowance.allowed);
+        assert_eq!(allowance.retry_after, 1);
+
+        let allowance = backend.take_token_bucket("client", 1, 5, 1, 1000, 2500)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 1);
+        assert_eq!(allowance.retry_after, 0);
+        assert_eq!(
+            backend.take_token_bucket("client", 0, 5, 1, 1000, 2500)?,
+            allowance
+        );
+
+        let allowance = backend.take_token_bucket("client", 0, 5, 1, 1000, 100_000)?;
+        assert!(allowance.allowed);
+        assert_eq!(allowance.remaining, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_breaker() -> Result<(), StateError> {
+        let backend = MemoryStateBackend::default();
+        assert_eq!(backend.check_breaker("upstream", 1000)?, Breaker::default());
+
+        backend.increment_breaker("upstream", 0, 1, 60, 1000)?;
+        let breaker = backend.increment_breaker("upstream", 0, 1, 60, 1010)?;
+        assert_eq!(breaker.generation, 2);
+        assert_eq!(breaker.failures, 2);
+        assert_eq!(breaker.consecutive_failures, 2);
+        assert_eq!(breaker.expiration, 1070);
+
+        let breaker = backend.increment_breaker("upstream", 1, 0, 60, 1020)?;
+        assert_eq!(breaker.generation, 3);
+        assert_eq!(breaker.successes, 1);
+        assert_eq!(breaker.failures, 2);
+        assert_eq!(breaker.consecutive_successes, 1);
+        assert_eq!(breaker.consecutive_failures, 0);
+        assert_eq!(backend.check_breaker("upstream", 1080)?, breaker);
+        assert_eq!(backend.check_breaker("upstream", 1081)?, Breaker::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sled_state() -> Result<(), StateError> {
+        let path =
+            std::env::temp_dir().join(format!("bulwark-sled-state-test-{}", std::process::id()));
+        // Rate limits and breakers are evicted once they expire by the wall clock.
+        let now = Utc::now().timestamp();
+        {
+            let backend = SledStateBackend::open(&path)?;
+            backend.set("greeting", b"hello")?;
+            assert_eq!(backend.increment("counter", 2)?, 2);
+            assert_eq!(backend.increment("counter", 3)?, 5);
+            assert!(matches!(
+                backend.increment("greeting", 1),
+                Err(StateError::InvalidCounter { .. })
+            ));
+            backend.set("temporary", b"gone")?;
+            backend.expire("temporary", 0)?;
+            backend.set("lasting", b"still here")?;
+            backend.expire("lasting", 3600)?;
+
+            let rate = backend.increment_rate_limit("client", 1, 60, now)?;
+            assert_eq!(rate.attempts, 1);
+            let rate = backend.increment_rate_limit("client", 1, 60, now + 10)?;
+            assert_eq!(rate.attempts, 2);
+            assert_eq!(rate.expiration, now + 60);
+
+            let breaker = backend.increment_breaker("upstream", 0, 1, 60, now)?;
+            assert_eq!(breaker.consecutive_failures, 1);
+        }
+
+        // State outlasts the backend that wrote it.
+        let backend = SledStateBackend::open(&path)?;
+        assert_eq!(backend.get("greeting")?, b"hello".to_vec());
+        assert_eq!(backend.get("counter")?, b"5".to_vec());
+        assert_eq!(backend.get("temporary")?, Vec::<u8>::new());
+        assert_eq!(backend.get("lasting")?, b"still here".to_vec());
+        assert_eq!(
+            backend.check_rate_limit("client", now + 20)?,
+            Rate {
+                attempts: 2,
+                expiration: now + 60
+            }
+        );
+        assert_eq!(
+            backend.check_rate_limit("client", now + 61)?,
+            Rate::default()
+        );
+        let breaker = backend.increment_breaker("upstream", 1, 0, 60, now + 30)?;
+        assert_eq!(breaker.generation, 2);
+        assert_eq!(breaker.failures, 1);
+        assert_eq!(breaker.successes, 1);
+        assert_eq!(backend.check_breaker("upstream", now + 30)?, breaker);
+
+        drop(backend);
+        std::fs::remove_dir_all(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_redis_keys() {
+        // Standalone servers keep the original layout.
+        assert_eq!(
+            rate_limit_keys("client", false),
+            ["rl:client".to_string(), "rl:client:ex".to_string()]
+        );
+        assert_eq!(breaker_keys("client", false)[0], "bk:g:client");
+        // Cluster keys share a slot.
+        assert_eq!(
+            rate_limit_keys("client", true),
+            ["rl:{client}".to_string(), "rl:{client}:ex".to_string()]
+        );
+        for key in breaker_keys("client", true) {
+            assert!(key.contains("{client}"), "missing hash tag: {}", key);
+        }
+    }
+
+    #[test]
+    fn test_redis_unavailable() -> Result<(), StateError> {
+        let settings = bulwark_config::State {
+            connect_timeout: 100,
+            command_timeout: 100,
+            ..Default::default()
+        };
+        // Nothing listens on port 1, so the backend must open but report every operation as a failure.
+        let backend = RedisStateBackend::new("redis://127.0.0.1:1", &settings)?;
+        assert!(backend.is_available());
+        assert!(backend.get("key").is_err());
+        assert!(!backend.is_available());
+        // Further operations fail without waiting on the pool until the retry interval passes.
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            backend.check_rate_limit("key", 0),
+            Err(StateError::Unavailable)
+        ));
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert_eq!(backend.failures(), 2);
+        // Once it has passed, one operation tries Redis again.
+        backend.retry_at.store(0, Ordering::Relaxed);
+        assert!(matches!(backend.get("key"), Err(StateError::Pool(_))));
+        assert!(matches!(backend.get("key"), Err(StateError::Unavailable)));
+        assert_eq!(backend.failures(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_redis_cluster_connect_timeout() -> Result<(), StateError> {
+        let settings = bulwark_config::State {
+            connect_timeout: 50,
+            cluster_nodes: vec!["redis://127.0.0.1:2".to_string()],
+            ..Default::default()
+        };
+        let backend = RedisStateBackend::new("redis://127.0.0.1:1", &settings)?;
+        let start = std::time::Instant::now();
+        assert!(backend.get("key").is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(!backend.is_available());
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use bulwark_wasm_sdk::*;
+
+struct BlankSlate;
+
+impl Handlers for BlankSlate {
+    fn on_init() {
+        // Initialization logic goes here.
+    }
+
+    // Uncomment to implement cross-plugin communication logic.
+    // fn on_request() {
+    // }
+
+    fn on_request_decision() {
+        let _request = get_request();
+        set_decision(Decision {
+            accept: 0.0,
+            restrict: 0.0,
+            unknown: 1.0,
+        })
+        .expect("decision should be valid");
+        set_tags(["blank-slate"]);
+    }
+
+    // Uncomment to process responses from the interior service.
+    // fn on_response_decision() {
+    //     let _request = get_request();
+    //     let _response = get_response();
+    // }
+
+    // Uncomment to implement feedback loops.
+    // fn on_decision_feedback() {
+    // }
+}
+
+bulwark_plugin!(BlankSlate);
@@ -6,3 +6,83 @@ pub enum ParseCounterError {
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
 }
+
+/// Returned when a plugin attempts an operation that requires a permission it has not been granted.
+///
+/// The missing permission is named as it appears in the plugin's configuration.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("access to '{resource}' denied, missing '{permission}' permission")]
+pub struct PermissionError {
+    /// The name of the missing permission.
+    pub permission: String,
+    /// The environment variable, domain, state key, or header the plugin attempted to access.
+    pub resource: String,
+}
+
+/// Returned when an environment variable cannot be read by a plugin.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum EnvError {
+    #[error(transparent)]
+    Permission(#[from] PermissionError),
+    #[error("environment variable '{0}' is not set")]
+    Missing(String),
+    #[error("environment variable '{0}' is not valid unicode")]
+    NotUnicode(String),
+}
+
+/// Returned when a plugin records a param value that the host cannot parse.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    #[error("invalid param value: {0}")]
+    InvalidJson(String),
+}
+
+/// Returned when a plugin's change to a header on the request forwarded to the interior service is rejected.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    #[error(transparent)]
+    Permission(#[from] PermissionError),
+    #[error("invalid header name '{0}'")]
+    InvalidName(String),
+}
+
+/// Returned when an outbound HTTP request made by a plugin fails.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum OutboundError {
+    #[error(transparent)]
+    Permission(#[from] PermissionError),
+    #[error("invalid outbound request uri '{0}'")]
+    InvalidUri(String),
+    #[error("unsupported outbound request method '{0}'")]
+    UnsupportedMethod(String),
+    #[error("invalid outbound request header '{0}'")]
+    InvalidHeader(String),
+    #[error("unknown outbound request {0}")]
+    UnknownRequest(u64),
+    #[error("outbound request timed out")]
+    Timeout,
+    #[error("outbound request failed: {0}")]
+    Transport(String),
+    #[error("outbound response exceeded {0} bytes")]
+    ResponseTooLarge(u64),
+    #[error("outbound request body must be sent in full as a single chunk")]
+    IncompleteBody,
+    #[error("outbound request body exceeded {0} bytes")]
+    RequestTooLarge(u64),
+}
+
+/// Returned when a state operation made by a plugin fails.
+///
+/// The state backend's failures are returned rather than hidden, so that a plugin can decide whether to fail open
+/// or closed while the state backend is degraded.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    #[error(transparent)]
+    Permission(#[from] PermissionError),
+    #[error("state backend is unavailable")]
+    Unavailable,
+    #[error("state backend timed out")]
+    Timeout,
+    #[error("state value for '{0}' holds the wrong kind of value for the operation")]
+    WrongType(String),
+}
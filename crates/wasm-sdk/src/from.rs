@@ -1,5 +1,8 @@
 use {
-    crate::{BodyChunk, Decision, Outcome, Response},
+    crate::{
+        BodyChunk, Decision, EnvError, HeaderError, OutboundError, Outcome, ParamError,
+        PermissionError, Response, StateError,
+    },
     std::net::{IpAddr, Ipv4Addr, Ipv6Addr},
 };
 
@@ -21,6 +24,74 @@ impl From<crate::bulwark_host::ResponseInterface> for Response {
     }
 }
 
+impl From<crate::bulwark_host::PermissionError> for PermissionError {
+    fn from(error: crate::bulwark_host::PermissionError) -> Self {
+        PermissionError {
+            permission: error.permission,
+            resource: error.resource,
+        }
+    }
+}
+
+impl From<crate::bulwark_host::HeaderError> for HeaderError {
+    fn from(error: crate::bulwark_host::HeaderError) -> Self {
+        match error {
+            crate::bulwark_host::HeaderError::Permission(error) => Self::Permission(error.into()),
+            crate::bulwark_host::HeaderError::InvalidName(name) => Self::InvalidName(name),
+        }
+    }
+}
+
+impl From<crate::bulwark_host::EnvError> for EnvError {
+    fn from(error: crate::bulwark_host::EnvError) -> Self {
+        match error {
+            crate::bulwark_host::EnvError::Permission(error) => Self::Permission(error.into()),
+            crate::bulwark_host::EnvError::Missing(key) => Self::Missing(key),
+            crate::bulwark_host::EnvError::NotUnicode(key) => Self::NotUnicode(key),
+        }
+    }
+}
+
+impl From<crate::bulwark_host::ParamError> for ParamError {
+    fn from(error: crate::bulwark_host::ParamError) -> Self {
+        match error {
+            crate::bulwark_host::ParamError::InvalidJson(message) => Self::InvalidJson(message),
+        }
+    }
+}
+
+impl From<crate::bulwark_host::OutboundError> for OutboundError {
+    fn from(error: crate::bulwark_host::OutboundError) -> Self {
+        match error {
+            crate::bulwark_host::OutboundError::Permission(error) => Self::Permission(error.into()),
+            crate::bulwark_host::OutboundError::InvalidUri(uri) => Self::InvalidUri(uri),
+            crate::bulwark_host::OutboundError::UnsupportedMethod(method) => {
+                Self::UnsupportedMethod(method)
+            }
+            crate::bulwark_host::OutboundError::InvalidHeader(name) => Self::InvalidHeader(name),
+            crate::bulwark_host::OutboundError::UnknownRequest(request_id) => {
+                Self::UnknownRequest(request_id)
+            }
+            crate::bulwark_host::OutboundError::Timeout => Self::Timeout,
+            crate::bulwark_host::OutboundError::Transport(message) => Self::Transport(message),
+            crate::bulwark_host::OutboundError::ResponseTooLarge(limit) => {
+                Self::ResponseTooLarge(limit)
+            }
+        }
+    }
+}
+
+impl From<crate::bulwark_host::StateError> for StateError {
+    fn from(error: crate::bulwark_host::StateError) -> Self {
+        match error {
+            crate::bulwark_host::StateError::Permission(error) => Self::Permission(error.into()),
+            crate::bulwark_host::StateError::Unavailable => Self::Unavailable,
+            crate::bulwark_host::StateError::Timeout => Self::Timeout,
+            crate::bulwark_host::StateError::WrongType(key) => Self::WrongType(key),
+        }
+    }
+}
+
 impl From<crate::bulwark_host::IpInterface> for IpAddr {
     fn from(ip: crate::bulwark_host::IpInterface) -> Self {
         match ip {
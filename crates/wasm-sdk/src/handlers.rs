@@ -0,0 +1,89 @@
+/// The functions a plugin implements to handle each phase of a request.
+///
+/// Every handler does nothing by default, so a plugin only implements the phases it takes part in. The type
+/// implementing `Handlers` is exported with the [`bulwark_plugin!`](crate::bulwark_plugin) macro.
+///
+/// # Example
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::*;
+///
+/// struct BlankSlate;
+///
+/// impl Handlers for BlankSlate {
+///     fn on_request_decision() {
+///         set_decision(Decision {
+///             accept: 0.0,
+///             restrict: 0.0,
+///             unknown: 1.0,
+///         })
+///         .expect("decision should be valid");
+///     }
+/// }
+///
+/// bulwark_plugin!(BlankSlate);
+/// ```
+pub trait Handlers {
+    /// Called once when an instance of the plugin is created, before it handles a request.
+    ///
+    /// Instances may be created ahead of the requests they handle, so request details aren't available yet.
+    fn on_init() {}
+
+    /// Called for each request before any plugin makes a decision.
+    ///
+    /// Typically used to pass values to other plugins through params.
+    fn on_request() {}
+
+    /// Called for each request to decide whether it should be restricted.
+    fn on_request_decision() {}
+
+    /// Called once the interior service has responded, to decide whether the response should be restricted.
+    fn on_response_decision() {}
+
+    /// Called after the combined decision for the request has been made, to implement feedback loops.
+    fn on_decision_feedback() {}
+}
+
+impl<T: Handlers> crate::bindings::Guest for T {
+    fn on_init() {
+        T::on_init()
+    }
+
+    fn on_request() {
+        T::on_request()
+    }
+
+    fn on_request_decision() {
+        T::on_request_decision()
+    }
+
+    fn on_response_decision() {
+        T::on_response_decision()
+    }
+
+    fn on_decision_feedback() {
+        T::on_decision_feedback()
+    }
+}
+
+/// Exports a type implementing [`Handlers`](crate::Handlers) as the plugin's handlers.
+///
+/// Every plugin must use this macro exactly once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::*;
+///
+/// struct EvilBit;
+///
+/// impl Handlers for EvilBit {}
+///
+/// bulwark_plugin!(EvilBit);
+/// ```
+#[macro_export]
+macro_rules! bulwark_plugin {
+    ($handlers:ident) => {
+        $crate::bindings::export!($handlers with_types_in $crate::bindings);
+    };
+}
@@ -1,5 +1,5 @@
 use {
-    std::{net::IpAddr, str, str::FromStr},
+    std::{net::IpAddr, str, str::FromStr, time::Duration},
     validator::{Validate, ValidationErrors},
 };
 
@@ -7,7 +7,10 @@ use {
 
 use crate::bulwark_host::DecisionInterface;
 
-pub use crate::{Decision, Outcome, ParseCounterError};
+pub use crate::{
+    Decision, EnvError, HeaderError, OutboundError, Outcome, ParamError, ParseCounterError,
+    StateError,
+};
 pub use http::{Extensions, Method, StatusCode, Uri, Version};
 pub use serde_json::{Map, Value};
 
@@ -18,6 +21,9 @@ pub type Request = http::Request<BodyChunk>;
 /// access to the first chunk of a response body.
 pub type Response = http::Response<BodyChunk>;
 
+/// The largest request body, in bytes, that can be sent with [`send_request`].
+pub const MAX_OUTBOUND_REQUEST_BODY_SIZE: u64 = 16384;
+
 // NOTE: fields are documented via Markdown instead of normal rustdoc because the underlying type is from the macro.
 /// A `Breaker` contains the values needed to implement a circuit-breaker pattern within a plugin.
 ///
@@ -37,6 +43,26 @@ pub type Breaker = crate::bulwark_host::BreakerInterface;
 /// * `attempts` - The number of attempts made within the expiration window.
 /// * `expiration` - The expiration timestamp in seconds since the epoch.
 pub type Rate = crate::bulwark_host::RateInterface;
+/// An `Allowance` is the outcome of an attempt against a sliding window rate limit or a token bucket.
+///
+/// # Fields
+///
+/// * `allowed` - Whether the attempt is within the limit.
+/// * `remaining` - The number of further attempts or tokens allowed right now.
+/// * `retry_after` - The number of seconds until another attempt of the same size would be allowed, or zero if it
+///     would be allowed now.
+pub type Allowance = crate::bulwark_host::AllowanceInterface;
+/// A `ScoredMember` is a member of a sorted set along with the score the set is ordered by.
+///
+/// # Fields
+///
+/// * `member` - The member's value.
+/// * `score` - The member's score.
+pub type ScoredMember = crate::bulwark_host::ScoredMemberInterface;
+/// The verbosity level of an event logged by a plugin.
+///
+/// Events below the level configured for Bulwark's `bulwark_plugin` target are discarded.
+pub type LogLevel = crate::bulwark_host::LogLevelInterface;
 
 /// The number of successes or failures to increment the breaker by.
 pub enum BreakerDelta {
@@ -97,9 +123,12 @@ pub fn get_request() -> Request {
         .unwrap()
 }
 
-/// Returns the response received from the interior service.
-pub fn get_response() -> Response {
-    let raw_response: crate::bulwark_host::ResponseInterface = crate::bulwark_host::get_response();
+/// Returns the response received from the interior service, if available.
+///
+/// The response is only available once the interior service has responded, so this returns `None` in the request
+/// phases.
+pub fn get_response() -> Option<Response> {
+    let raw_response: crate::bulwark_host::ResponseInterface = crate::bulwark_host::get_response()?;
     let chunk: Vec<u8> = raw_response.chunk;
     // TODO: error handling
     let status: u16 = raw_response.status.try_into().unwrap();
@@ -107,14 +136,16 @@ pub fn get_response() -> Response {
     for header in raw_response.headers {
         response = response.header(header.name, header.value);
     }
-    response
-        .body(BodyChunk {
-            content: chunk,
-            size: raw_response.chunk_length,
-            start: raw_response.chunk_start,
-            end_of_stream: raw_response.end_of_stream,
-        })
-        .unwrap()
+    Some(
+        response
+            .body(BodyChunk {
+                content: chunk,
+                size: raw_response.chunk_length,
+                start: raw_response.chunk_start,
+                end_of_stream: raw_response.end_of_stream,
+            })
+            .unwrap(),
+    )
 }
 
 /// Returns the originating client's IP address, if available.
@@ -122,6 +153,87 @@ pub fn get_client_ip() -> Option<IpAddr> {
     crate::bulwark_host::get_client_ip().map(|ip| ip.into())
 }
 
+/// Returns the identifier used to correlate the request across log events, plugins, and the interior service.
+///
+/// The identifier is read from the configured request ID header if the incoming request has one, and generated
+/// otherwise. Including it in a plugin's own outbound requests or state keys allows them to be traced back.
+#[inline]
+pub fn get_request_id() -> String {
+    crate::bulwark_host::get_request_id()
+}
+
+/// Returns the time remaining before the current handler function times out.
+///
+/// The budget accounts for both the plugin's own timeout and the overall deadline for the request. Plugins should
+/// check it before making expensive calls, like outbound HTTP requests, that are unlikely to finish in time.
+#[inline]
+pub fn get_remaining_budget() -> Duration {
+    Duration::from_millis(crate::bulwark_host::get_remaining_budget())
+}
+
+/// Logs an event through Bulwark's logging pipeline.
+///
+/// The event is attributed to the plugin and the request being processed. The [`error!`](crate::error),
+/// [`warn!`](crate::warn), [`info!`](crate::info), [`debug!`](crate::debug), and [`trace!`](crate::trace) macros
+/// are usually more convenient than calling this directly.
+///
+/// # Arguments
+///
+/// * `level` - The verbosity level of the event.
+/// * `message` - The message describing the event.
+/// * `fields` - Additional named values describing the event.
+pub fn log(level: LogLevel, message: &str, fields: &[(&str, String)]) {
+    let fields = fields
+        .iter()
+        .map(|(name, value)| crate::bulwark_host::LogFieldInterface {
+            name: name.to_string(),
+            value: value.clone(),
+        })
+        .collect::<Vec<crate::bulwark_host::LogFieldInterface>>();
+    crate::bulwark_host::log(level, message, fields.as_slice());
+}
+
+/// Adds a header value to the request forwarded to the interior service, keeping any existing values.
+///
+/// Header changes only take effect for plugins that are enforcing, not shadow plugins, and only when the request is
+/// allowed. In order for this function to succeed, a plugin's configuration must explicitly declare a permission
+/// grant for the header name. Header names beginning with `Bulwark-` are reserved. This function returns a
+/// [`HeaderError`] if permission has not been granted or if the name is not a valid header name.
+///
+/// # Arguments
+///
+/// * `name` - The header name. Case-insensitive.
+/// * `value` - The header value bytes.
+#[inline]
+pub fn add_upstream_header(name: &str, value: &[u8]) -> Result<(), HeaderError> {
+    Ok(crate::bulwark_host::add_upstream_header(name, value)?)
+}
+
+/// Replaces any values for a header on the request forwarded to the interior service.
+///
+/// The same restrictions as [`add_upstream_header`] apply.
+///
+/// # Arguments
+///
+/// * `name` - The header name. Case-insensitive.
+/// * `value` - The header value bytes.
+#[inline]
+pub fn set_upstream_header(name: &str, value: &[u8]) -> Result<(), HeaderError> {
+    Ok(crate::bulwark_host::set_upstream_header(name, value)?)
+}
+
+/// Removes a header from the request forwarded to the interior service.
+///
+/// The same restrictions as [`add_upstream_header`] apply.
+///
+/// # Arguments
+///
+/// * `name` - The header name. Case-insensitive.
+#[inline]
+pub fn remove_upstream_header(name: &str) -> Result<(), HeaderError> {
+    Ok(crate::bulwark_host::remove_upstream_header(name)?)
+}
+
 /// Returns a named value from the request context's params.
 ///
 /// # Arguments
@@ -140,10 +252,10 @@ pub fn get_param_value(key: &str) -> Value {
 ///
 /// * `key` - The key name corresponding to the param value.
 /// * `value` - The value to record. Values are serialized JSON.
-pub fn set_param_value(key: &str, value: Value) {
-    // TODO: this should return a result
-    let json = serde_json::to_vec(&value).unwrap();
-    crate::bulwark_host::set_param_value(key, &json);
+pub fn set_param_value(key: &str, value: Value) -> Result<(), ParamError> {
+    let json =
+        serde_json::to_vec(&value).map_err(|err| ParamError::InvalidJson(err.to_string()))?;
+    Ok(crate::bulwark_host::set_param_value(key, &json)?)
 }
 
 /// Returns the guest environment's configuration value as a JSON [`Value`].
@@ -174,27 +286,28 @@ pub fn get_config_value(key: &str) -> Option<Value> {
 /// Returns a named environment variable value as a [`String`].
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the environment variable being requested. This function will panic if permission has not been granted.
+/// the environment variable being requested. This function returns an [`EnvError`] if permission has not been
+/// granted, if the environment variable is not set, or if its value is not valid UTF-8.
 ///
 /// # Arguments
 ///
 /// * `key` - The environment variable name. Case-sensitive.
-pub fn get_env(key: &str) -> String {
-    // TODO: this should return a result
-    String::from_utf8(crate::bulwark_host::get_env_bytes(key)).unwrap()
+pub fn get_env(key: &str) -> Result<String, EnvError> {
+    String::from_utf8(crate::bulwark_host::get_env_bytes(key)?)
+        .map_err(|_| EnvError::NotUnicode(key.to_string()))
 }
 
 /// Returns a named environment variable value as bytes.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the environment variable being requested. This function will panic if permission has not been granted.
+/// the environment variable being requested. This function returns an [`EnvError`] if permission has not been
+/// granted or if the environment variable is not set.
 ///
 /// # Arguments
 ///
 /// * `key` - The environment variable name. Case-sensitive.
-pub fn get_env_bytes(key: &str) -> Vec<u8> {
-    // TODO: this should return a result
-    crate::bulwark_host::get_env_bytes(key)
+pub fn get_env_bytes(key: &str) -> Result<Vec<u8>, EnvError> {
+    Ok(crate::bulwark_host::get_env_bytes(key)?)
 }
 
 /// Records the decision value the plugin wants to return.
@@ -276,7 +389,6 @@ pub fn set_restricted(value: f64) {
 #[inline]
 pub fn set_tags<I: IntoIterator<Item = V>, V: Into<String>>(tags: I) {
     let tags: Vec<String> = tags.into_iter().map(|s| s.into()).collect();
-    let tags: Vec<&str> = tags.iter().map(|s| s.as_str()).collect();
     // TODO: use BTreeSet for merging sorted tag lists?
     crate::bulwark_host::set_tags(tags.as_slice())
 }
@@ -284,65 +396,72 @@ pub fn set_tags<I: IntoIterator<Item = V>, V: Into<String>>(tags: I) {
 /// Returns the combined decision, if available.
 ///
 /// Typically used in the feedback phase.
-pub fn get_combined_decision() -> Decision {
-    // TODO: Option<Decision> ?
-    crate::bulwark_host::get_combined_decision().into()
+pub fn get_combined_decision() -> Option<Decision> {
+    crate::bulwark_host::get_combined_decision().map(Decision::from)
 }
 
 /// Returns the combined set of tags associated with a decision, if available.
 ///
 /// Typically used in the feedback phase.
 #[inline]
-pub fn get_combined_tags() -> Vec<String> {
+pub fn get_combined_tags() -> Option<Vec<String>> {
     crate::bulwark_host::get_combined_tags()
 }
 
 /// Returns the outcome of the combined decision, if available.
 ///
 /// Typically used in the feedback phase.
-pub fn get_outcome() -> Outcome {
-    // TODO: Option<Outcome> ?
-    crate::bulwark_host::get_outcome().into()
+pub fn get_outcome() -> Option<Outcome> {
+    crate::bulwark_host::get_outcome().map(Outcome::from)
 }
 
 /// Sends an outbound HTTP request.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the host being requested. This function will panic if permission has not been granted.
+/// the host being requested. This function returns an [`OutboundError`] if permission has not been granted.
+///
+/// An [`OutboundError`] is also returned if the request fails, if it takes longer than either the service's outbound
+/// timeout or the plugin's [remaining budget](get_remaining_budget), or if the response body is larger than the
+/// service allows. The request body must be complete, starting from its first byte, and no larger than
+/// [`MAX_OUTBOUND_REQUEST_BODY_SIZE`].
 ///
 /// # Arguments
 ///
 /// * `request` - The HTTP request to send.
-pub fn send_request(request: Request) -> Response {
+pub fn send_request(request: Request) -> Result<Response, OutboundError> {
+    let chunk = request.body();
+    if !chunk.end_of_stream || chunk.start != 0 {
+        return Err(OutboundError::IncompleteBody);
+    } else if chunk.size > MAX_OUTBOUND_REQUEST_BODY_SIZE {
+        return Err(OutboundError::RequestTooLarge(
+            MAX_OUTBOUND_REQUEST_BODY_SIZE,
+        ));
+    }
     let request_id = crate::bulwark_host::prepare_request(
         request.method().as_str(),
         request.uri().to_string().as_str(),
-    );
+    )?;
     for (name, value) in request.headers() {
-        crate::bulwark_host::add_request_header(request_id, name.as_str(), value.as_bytes());
+        crate::bulwark_host::add_request_header(request_id, name.as_str(), value.as_bytes())?;
     }
-    let chunk = request.body();
-    if !chunk.end_of_stream {
-        panic!("the entire request body must be available");
-    } else if chunk.start != 0 {
-        panic!("chunk start must be 0");
-    } else if chunk.size > 16384 {
-        panic!("the entire request body must be 16384 bytes or less");
-    }
-    let response = crate::bulwark_host::set_request_body(request_id, &chunk.content);
-    Response::from(response)
+    let response = crate::bulwark_host::set_request_body(request_id, &chunk.content)?;
+    Ok(Response::from(response))
 }
 
-/// Returns the named state value retrieved from Redis.
+/// Returns the named state value retrieved from the state backend.
 ///
 /// Also used to retrieve a counter value.
 ///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
 /// # Arguments
 ///
 /// * `key` - The key name corresponding to the state value.
 #[inline]
-pub fn get_remote_state(key: &str) -> Vec<u8> {
-    crate::bulwark_host::get_remote_state(key)
+pub fn get_remote_state(key: &str) -> Result<Vec<u8>, StateError> {
+    Ok(crate::bulwark_host::get_remote_state(key)?)
 }
 
 /// Parses a counter value from state stored as a string.
@@ -355,63 +474,318 @@ pub fn parse_counter(value: Vec<u8>) -> Result<i64, ParseCounterError> {
     Ok(str::from_utf8(value.as_slice())?.parse::<i64>()?)
 }
 
-/// Set a named value in Redis.
+/// Set a named value in the state backend.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
 /// * `key` - The key name corresponding to the state value.
-/// * `value` - The value to record. Values are byte strings, but may be interpreted differently by Redis depending on context.
+/// * `value` - The value to record. Values are byte strings, but may be interpreted differently by the state backend depending on context.
 #[inline]
-pub fn set_remote_state(key: &str, value: &[u8]) {
-    crate::bulwark_host::set_remote_state(key, value)
+pub fn set_remote_state(key: &str, value: &[u8]) -> Result<(), StateError> {
+    Ok(crate::bulwark_host::set_remote_state(key, value)?)
 }
 
-/// Increments a named counter in Redis.
+/// Increments a named counter in the state backend.
 ///
 /// Returns the value of the counter after it's incremented.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
 /// * `key` - The key name corresponding to the state counter.
 #[inline]
-pub fn increment_remote_state(key: &str) -> i64 {
-    crate::bulwark_host::increment_remote_state(key)
+pub fn increment_remote_state(key: &str) -> Result<i64, StateError> {
+    Ok(crate::bulwark_host::increment_remote_state(key)?)
 }
 
-/// Increments a named counter in Redis by a specified delta value.
+/// Increments a named counter in the state backend by a specified delta value.
 ///
 /// Returns the value of the counter after it's incremented.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
 /// * `key` - The key name corresponding to the state counter.
 /// * `delta` - The amount to increase the counter by.
 #[inline]
-pub fn increment_remote_state_by(key: &str, delta: i64) -> i64 {
-    crate::bulwark_host::increment_remote_state_by(key, delta)
+pub fn increment_remote_state_by(key: &str, delta: i64) -> Result<i64, StateError> {
+    Ok(crate::bulwark_host::increment_remote_state_by(key, delta)?)
 }
 
-/// Sets an expiration on a named value in Redis.
+/// Sets an expiration on a named value in the state backend.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
 /// * `key` - The key name corresponding to the state value.
 /// * `ttl` - The time-to-live for the value in seconds.
 #[inline]
-pub fn set_remote_ttl(key: &str, ttl: i64) {
-    crate::bulwark_host::set_remote_ttl(key, ttl)
+pub fn set_remote_ttl(key: &str, ttl: i64) -> Result<(), StateError> {
+    Ok(crate::bulwark_host::set_remote_ttl(key, ttl)?)
+}
+
+/// Sets a named value in the state backend that expires after a time-to-live.
+///
+/// Unlike calling [`set_remote_state`] followed by [`set_remote_ttl`], the value and its expiration are set together
+/// in a single atomic operation, so the value can never be left without an expiration.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the state value.
+/// * `value` - The value to record.
+/// * `ttl` - The time-to-live for the value in seconds. A non-positive value deletes the value instead.
+#[inline]
+pub fn set_remote_state_with_ttl(key: &str, value: &[u8], ttl: i64) -> Result<(), StateError> {
+    Ok(crate::bulwark_host::set_remote_state_with_ttl(
+        key, value, ttl,
+    )?)
+}
+
+/// Deletes a named value, set, or sorted set from the state backend.
+///
+/// Returns true if the key existed.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the state value.
+#[inline]
+pub fn delete_remote_state(key: &str) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::delete_remote_state(key)?)
+}
+
+/// Returns several named state values from the state backend at once.
+///
+/// Values are returned in the same order as their keys, with an empty value for each key that doesn't exist.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of every key being requested. This function returns a [`StateError`] if permission has not been
+/// granted for any of them or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `keys` - The key names corresponding to the state values.
+#[inline]
+pub fn get_remote_states(keys: &[&str]) -> Result<Vec<Vec<u8>>, StateError> {
+    let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    Ok(crate::bulwark_host::get_remote_states(&keys)?)
+}
+
+/// Sets a named value in the state backend only if its current value matches an expected value.
+///
+/// Returns true if the value was set. Like [`set_remote_state`], a successful write clears any expiration.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the state value.
+/// * `expected` - The expected current value. An empty value matches a value that doesn't exist.
+/// * `value` - The value to record.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::compare_and_set_remote_state;
+///
+/// let key = "session:example";
+/// // Claims the session only if no other request has claimed it first.
+/// let claimed = compare_and_set_remote_state(key, b"", b"claimed").expect("state operation failed");
+/// ```
+#[inline]
+pub fn compare_and_set_remote_state(
+    key: &str,
+    expected: &[u8],
+    value: &[u8],
+) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::compare_and_set_remote_state(
+        key, expected, value,
+    )?)
+}
+
+/// Adds a member to a named set in the state backend.
+///
+/// Returns true if the value wasn't already a member of the set.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the set.
+/// * `member` - The member to add.
+#[inline]
+pub fn add_remote_set_member(key: &str, member: &[u8]) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::add_remote_set_member(key, member)?)
+}
+
+/// Removes a member from a named set in the state backend.
+///
+/// Returns true if the value was a member of the set.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the set.
+/// * `member` - The member to remove.
+#[inline]
+pub fn remove_remote_set_member(key: &str, member: &[u8]) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::remove_remote_set_member(key, member)?)
+}
+
+/// Returns true if a value is a member of a named set in the state backend.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the set.
+/// * `member` - The value to look for.
+#[inline]
+pub fn is_remote_set_member(key: &str, member: &[u8]) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::is_remote_set_member(key, member)?)
+}
+
+/// Returns the members of a named set in the state backend, in no particular order.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the set.
+#[inline]
+pub fn get_remote_set_members(key: &str) -> Result<Vec<Vec<u8>>, StateError> {
+    Ok(crate::bulwark_host::get_remote_set_members(key)?)
+}
+
+/// Adds a member to a named sorted set in the state backend, or updates its score if it's already a member.
+///
+/// Returns true if the value wasn't already a member of the sorted set.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the sorted set.
+/// * `member` - The member to add.
+/// * `score` - The score the sorted set is ordered by.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::{add_remote_sorted_set_member, remove_remote_sorted_set_range};
+///
+/// // Tracks the usernames a client has tried, scored by the time of each attempt.
+/// let key = "login.ip:192.168.0.1";
+/// let now = 1_700_000_000.0;
+/// add_remote_sorted_set_member(key, b"alice", now).expect("state operation failed");
+/// // Forgets attempts older than an hour.
+/// remove_remote_sorted_set_range(key, f64::NEG_INFINITY, now - 3600.0).expect("state operation failed");
+/// ```
+#[inline]
+pub fn add_remote_sorted_set_member(
+    key: &str,
+    member: &[u8],
+    score: f64,
+) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::add_remote_sorted_set_member(
+        key, member, score,
+    )?)
+}
+
+/// Removes a member from a named sorted set in the state backend.
+///
+/// Returns true if the value was a member of the sorted set.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the sorted set.
+/// * `member` - The member to remove.
+#[inline]
+pub fn remove_remote_sorted_set_member(key: &str, member: &[u8]) -> Result<bool, StateError> {
+    Ok(crate::bulwark_host::remove_remote_sorted_set_member(
+        key, member,
+    )?)
+}
+
+/// Returns the members of a named sorted set in the state backend with scores between `min` and `max` inclusive.
+///
+/// Members are ordered by score, and members with the same score are ordered by their bytes.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the sorted set.
+/// * `min` - The lowest score to include.
+/// * `max` - The highest score to include.
+#[inline]
+pub fn get_remote_sorted_set_range(
+    key: &str,
+    min: f64,
+    max: f64,
+) -> Result<Vec<ScoredMember>, StateError> {
+    Ok(crate::bulwark_host::get_remote_sorted_set_range(
+        key, min, max,
+    )?)
+}
+
+/// Removes the members of a named sorted set in the state backend with scores between `min` and `max` inclusive.
+///
+/// Returns the number of members removed.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the sorted set.
+/// * `min` - The lowest score to remove.
+/// * `max` - The highest score to remove.
+#[inline]
+pub fn remove_remote_sorted_set_range(key: &str, min: f64, max: f64) -> Result<u64, StateError> {
+    Ok(crate::bulwark_host::remove_remote_sorted_set_range(
+        key, min, max,
+    )?)
 }
 
 // TODO: needs an example
@@ -423,7 +797,8 @@ pub fn set_remote_ttl(key: &str, ttl: i64) {
 /// value it receives.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
@@ -431,14 +806,17 @@ pub fn set_remote_ttl(key: &str, ttl: i64) {
 /// * `delta` - The amount to increase the counter by.
 /// * `window` - How long each period should be in seconds.
 #[inline]
-pub fn increment_rate_limit(key: &str, delta: i64, window: i64) -> Rate {
-    crate::bulwark_host::increment_rate_limit(key, delta, window)
+pub fn increment_rate_limit(key: &str, delta: i64, window: i64) -> Result<Rate, StateError> {
+    Ok(crate::bulwark_host::increment_rate_limit(
+        key, delta, window,
+    )?)
 }
 
 /// Checks a rate limit, returning the number of attempts so far and the expiration time.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// See [`increment_rate_limit`].
 ///
@@ -446,8 +824,8 @@ pub fn increment_rate_limit(key: &str, delta: i64, window: i64) -> Rate {
 ///
 /// * `key` - The key name corresponding to the state counter.
 #[inline]
-pub fn check_rate_limit(key: &str) -> Rate {
-    crate::bulwark_host::check_rate_limit(key)
+pub fn check_rate_limit(key: &str) -> Result<Rate, StateError> {
+    Ok(crate::bulwark_host::check_rate_limit(key)?)
 }
 
 /// Increments a circuit breaker, returning the generation count, success count, failure count,
@@ -458,7 +836,8 @@ pub fn check_rate_limit(key: &str) -> Rate {
 /// control the plugin has over the behavior of the breaker.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// # Arguments
 ///
@@ -481,21 +860,32 @@ pub fn check_rate_limit(key: &str) -> Rate {
 ///         BreakerDelta::Failure(1)
 ///     },
 ///     60 * 60, // 1 hour
-/// );
+/// )
+/// .expect("state operation failed");
 /// ```
-pub fn increment_breaker(key: &str, delta: BreakerDelta, window: i64) -> Breaker {
+pub fn increment_breaker(
+    key: &str,
+    delta: BreakerDelta,
+    window: i64,
+) -> Result<Breaker, StateError> {
     let (success_delta, failure_delta) = match delta {
         BreakerDelta::Success(d) => (d, 0),
         BreakerDelta::Failure(d) => (0, d),
     };
-    crate::bulwark_host::increment_breaker(key, success_delta, failure_delta, window)
+    Ok(crate::bulwark_host::increment_breaker(
+        key,
+        success_delta,
+        failure_delta,
+        window,
+    )?)
 }
 
 /// Checks a circuit breaker, returning the generation count, success count, failure count,
 /// consecutive success count, consecutive failure count, and expiration time.
 ///
 /// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
-/// the prefix of the key being requested. This function will panic if permission has not been granted.
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
 ///
 /// See [`increment_breaker`].
 ///
@@ -503,6 +893,136 @@ pub fn increment_breaker(key: &str, delta: BreakerDelta, window: i64) -> Breaker
 ///
 /// * `key` - The key name corresponding to the state counter.
 #[inline]
-pub fn check_breaker(key: &str) -> Breaker {
-    crate::bulwark_host::check_breaker(key)
+pub fn check_breaker(key: &str) -> Result<Breaker, StateError> {
+    Ok(crate::bulwark_host::check_breaker(key)?)
+}
+
+/// Counts attempts against a sliding window rate limit, returning whether the attempt is allowed, how many further
+/// attempts are allowed, and how many seconds until another attempt would be allowed.
+///
+/// Unlike [`increment_rate_limit`], the window slides continuously rather than resetting at the end of each period,
+/// so clients can't burst to twice the limit by timing their attempts around the boundary between periods. Every
+/// attempt is counted, including those over the limit, so clients that keep retrying stay limited.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the rate limit.
+/// * `delta` - The number of attempts to count.
+/// * `limit` - The number of attempts allowed within the window.
+/// * `window` - How long the window is in seconds.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::increment_sliding_window;
+///
+/// let key = "login.ip:192.168.0.1";
+/// let allowance = increment_sliding_window(key, 1, 10, 60 * 5).expect("state operation failed");
+/// if !allowance.allowed {
+///     // Retry in `allowance.retry_after` seconds.
+/// }
+/// ```
+#[inline]
+pub fn increment_sliding_window(
+    key: &str,
+    delta: i64,
+    limit: i64,
+    window: i64,
+) -> Result<Allowance, StateError> {
+    Ok(crate::bulwark_host::increment_sliding_window(
+        key, delta, limit, window,
+    )?)
+}
+
+/// Checks a sliding window rate limit without counting an attempt.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// See [`increment_sliding_window`].
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the rate limit.
+/// * `limit` - The number of attempts allowed within the window.
+/// * `window` - How long the window is in seconds.
+#[inline]
+pub fn check_sliding_window(key: &str, limit: i64, window: i64) -> Result<Allowance, StateError> {
+    Ok(crate::bulwark_host::increment_sliding_window(
+        key, 0, limit, window,
+    )?)
+}
+
+/// Takes tokens from a token bucket, returning whether they were taken, how many tokens remain, and how many seconds
+/// until the same number of tokens could be taken again.
+///
+/// The bucket allows bursts of up to `capacity` tokens while limiting the sustained rate to `refill` tokens per
+/// `window`. Tokens are only taken if enough are available.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the token bucket.
+/// * `tokens` - The number of tokens to take.
+/// * `capacity` - The maximum number of tokens the bucket holds.
+/// * `refill` - The number of tokens added to the bucket per window.
+/// * `window` - How long it takes to add `refill` tokens, in seconds.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::take_token_bucket;
+///
+/// let key = "api.client:example";
+/// // Bursts of up to 20 requests, refilling at 1 request per second.
+/// let allowance = take_token_bucket(key, 1, 20, 1, 1).expect("state operation failed");
+/// if !allowance.allowed {
+///     // Retry in `allowance.retry_after` seconds.
+/// }
+/// ```
+#[inline]
+pub fn take_token_bucket(
+    key: &str,
+    tokens: i64,
+    capacity: i64,
+    refill: i64,
+    window: i64,
+) -> Result<Allowance, StateError> {
+    Ok(crate::bulwark_host::take_token_bucket(
+        key, tokens, capacity, refill, window,
+    )?)
+}
+
+/// Checks a token bucket without taking any tokens.
+///
+/// In order for this function to succeed, a plugin's configuration must explicitly declare a permission grant for
+/// the prefix of the key being requested. This function returns a [`StateError`] if permission has not been
+/// granted or if the state backend fails.
+///
+/// See [`take_token_bucket`].
+///
+/// # Arguments
+///
+/// * `key` - The key name corresponding to the token bucket.
+/// * `capacity` - The maximum number of tokens the bucket holds.
+/// * `refill` - The number of tokens added to the bucket per window.
+/// * `window` - How long it takes to add `refill` tokens, in seconds.
+#[inline]
+pub fn check_token_bucket(
+    key: &str,
+    capacity: i64,
+    refill: i64,
+    window: i64,
+) -> Result<Allowance, StateError> {
+    Ok(crate::bulwark_host::take_token_bucket(
+        key, 0, capacity, refill, window,
+    )?)
 }
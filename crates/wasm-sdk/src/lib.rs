@@ -1,16 +1,30 @@
 //! This crate provides the logic for Bulwark's guest environment.
 
-// TODO: the host/guest wit files seem to be why the latest version switched to one generate macro?
-// TODO: switch to wasmtime::component::bindgen!
-wit_bindgen_rust::import!("../../bulwark-host.wit");
+// The bindings are public so that the `bulwark_plugin!` macro can export a plugin's handlers through them.
+#[doc(hidden)]
+pub mod bindings {
+    wit_bindgen::generate!({
+        path: "../../bulwark-plugin.wit",
+        world: "bulwark-plugin",
+        pub_export_macro: true,
+        default_bindings_module: "bulwark_wasm_sdk::bindings",
+    });
+}
+
+// The interface version is only named here, so that the rest of the SDK doesn't change when it's bumped.
+use bindings::bulwark::plugin::host as bulwark_host;
 
 mod errors;
 mod from;
+mod handlers;
 mod host_calls;
+mod macros;
+
+pub mod metrics;
 
 pub use bulwark_decision::*;
 pub use errors::*;
-pub use from::*;
+pub use handlers::*;
 pub use host_calls::*;
 
 #[allow(unused_imports)]
@@ -0,0 +1,80 @@
+// These macros mirror the `tracing` macros of the same names, with fields separated from the message by a semicolon
+// in the same way as the `log` crate's key-value syntax.
+
+/// Logs an event at the given [`LogLevel`](crate::LogLevel).
+///
+/// See [`info!`](crate::info) for the supported syntax.
+#[macro_export]
+macro_rules! event {
+    ($level:expr, $($name:ident = $value:expr),+ ; $($arg:tt)+) => {
+        $crate::log(
+            $level,
+            &format!($($arg)+),
+            &[$((stringify!($name), ($value).to_string())),+],
+        )
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log($level, &format!($($arg)+), &[])
+    };
+}
+
+/// Logs an event at the error level.
+///
+/// See [`info!`](crate::info) for the supported syntax.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::event!($crate::LogLevel::Error, $($arg)+)
+    };
+}
+
+/// Logs an event at the warn level.
+///
+/// See [`info!`](crate::info) for the supported syntax.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::event!($crate::LogLevel::Warn, $($arg)+)
+    };
+}
+
+/// Logs an event at the info level.
+///
+/// The message accepts the same arguments as [`format!`]. Named fields may precede the message, separated from it
+/// by a semicolon. Field values are recorded using their [`Display`](std::fmt::Display) implementation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bulwark_wasm_sdk::info;
+///
+/// let attempts = 3;
+/// info!("checked rate limit");
+/// info!(attempts = attempts, key = "client.ip:192.168.0.1"; "rate limit at {}%", 75);
+/// ```
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::event!($crate::LogLevel::Info, $($arg)+)
+    };
+}
+
+/// Logs an event at the debug level.
+///
+/// See [`info!`](crate::info) for the supported syntax.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::event!($crate::LogLevel::Debug, $($arg)+)
+    };
+}
+
+/// Logs an event at the trace level.
+///
+/// See [`info!`](crate::info) for the supported syntax.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::event!($crate::LogLevel::Trace, $($arg)+)
+    };
+}
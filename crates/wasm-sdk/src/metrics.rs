@@ -0,0 +1,173 @@
+//! Custom metrics recorded by a plugin.
+//!
+//! Metrics are exported by Bulwark's admin service in the Prometheus text format, labeled with the plugin's
+//! reference. A metric named `login_failures` recorded by the `evil-bit` plugin is exported as
+//! `bulwark_plugin_login_failures{plugin="evil-bit"}`. The `plugin` label is reserved, as are the `le` and
+//! `quantile` labels on histograms, and plugins that record a metric with the same name must agree on its kind.
+//!
+//! Each metric may have at most 1000 distinct label sets. Updates that would exceed the limit are dropped, so labels
+//! should never carry unbounded values like IP addresses or user IDs.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use bulwark_wasm_sdk::metrics;
+//!
+//! metrics::counter("login_failures")
+//!     .with_label("reason", "bad_password")
+//!     .increment(1);
+//! metrics::histogram("password_length").record(12.0);
+//! ```
+
+/// Returns a handle to the counter with the given name.
+///
+/// Metric names must start with a letter or underscore and contain only letters, digits, and underscores.
+pub fn counter(name: &str) -> Counter {
+    Counter {
+        name: name.to_string(),
+        labels: vec![],
+    }
+}
+
+/// Returns a handle to the gauge with the given name.
+///
+/// Metric names must start with a letter or underscore and contain only letters, digits, and underscores.
+pub fn gauge(name: &str) -> Gauge {
+    Gauge {
+        name: name.to_string(),
+        labels: vec![],
+    }
+}
+
+/// Returns a handle to the histogram with the given name.
+///
+/// Metric names must start with a letter or underscore and contain only letters, digits, and underscores.
+pub fn histogram(name: &str) -> Histogram {
+    Histogram {
+        name: name.to_string(),
+        labels: vec![],
+    }
+}
+
+/// A value that only ever increases, such as the number of times a heuristic has fired.
+#[derive(Debug, Clone)]
+pub struct Counter {
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+impl Counter {
+    /// Adds a label identifying which series of the counter to update.
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Increases the counter.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The amount to increase the counter by.
+    pub fn increment(&self, delta: u64) {
+        crate::bulwark_host::increment_counter(
+            &self.name,
+            label_interfaces(&self.labels).as_slice(),
+            delta,
+        );
+    }
+}
+
+/// A value that may go up or down, such as the size of a queue.
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+impl Gauge {
+    /// Adds a label identifying which series of the gauge to update.
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Replaces the value of the gauge.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new value of the gauge.
+    pub fn set(&self, value: f64) {
+        crate::bulwark_host::set_gauge(
+            &self.name,
+            label_interfaces(&self.labels).as_slice(),
+            value,
+        );
+    }
+
+    /// Increases the value of the gauge.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The amount to increase the gauge by.
+    pub fn increment(&self, delta: f64) {
+        crate::bulwark_host::add_gauge(
+            &self.name,
+            label_interfaces(&self.labels).as_slice(),
+            delta,
+        );
+    }
+
+    /// Decreases the value of the gauge.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The amount to decrease the gauge by.
+    pub fn decrement(&self, delta: f64) {
+        crate::bulwark_host::add_gauge(
+            &self.name,
+            label_interfaces(&self.labels).as_slice(),
+            -delta,
+        );
+    }
+}
+
+/// A distribution of observed values, such as scores or sizes.
+///
+/// Observations are sorted into buckets with upper bounds from 0.005 to 10.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+impl Histogram {
+    /// Adds a label identifying which series of the histogram to update.
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Records an observation in the histogram.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The observed value.
+    pub fn record(&self, value: f64) {
+        crate::bulwark_host::observe_histogram(
+            &self.name,
+            label_interfaces(&self.labels).as_slice(),
+            value,
+        );
+    }
+}
+
+/// Copies labels into the records passed to the host.
+fn label_interfaces(labels: &[(String, String)]) -> Vec<crate::bulwark_host::MetricLabelInterface> {
+    labels
+        .iter()
+        .map(|(name, value)| crate::bulwark_host::MetricLabelInterface {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
@@ -111,6 +111,10 @@ impl EcsFormatter {
                 "process response" => EcsFormatter::parse_process_response_event(event, ecs_event),
                 "plugin decision" => EcsFormatter::parse_plugin_decision_event(event, ecs_event),
                 "combine decision" => EcsFormatter::parse_combine_decision_event(event, ecs_event),
+                "plugin output" | "plugin output suppressed" => {
+                    EcsFormatter::parse_plugin_output_event(event, ecs_event)
+                }
+                "plugin log" => EcsFormatter::parse_plugin_log_event(event, ecs_event),
                 _ => EcsFormatter::parse_unknown_event(event, ecs_event),
             }
         } else {
@@ -173,6 +177,17 @@ impl EcsFormatter {
                     url.original = unquoted_url.to_string();
                     ecs_event.url = Some(url);
                 }
+                "request_id" => {
+                    let unquoted_request_id =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    let mut event_meta = ecs_event.event.clone().unwrap_or_default();
+                    event_meta.id = Some(unquoted_request_id.to_string());
+                    ecs_event.event = Some(event_meta);
+                    ecs_event.trace = Some(EcsTraceFieldSet {
+                        id: unquoted_request_id.to_string(),
+                    });
+                }
                 "user_agent" => {
                     let mut user_agent = ecs_event.user_agent.clone().unwrap_or_default();
                     let unquoted_ua = quoted_string::to_content::<TraceQuoteSpec>(field.value())
@@ -223,7 +238,10 @@ impl EcsFormatter {
     }
 
     /// Parses `"plugin decision"` messages emitted after each request and response phase.
+    ///
+    /// Decisions made by shadow plugins are recorded separately from the decisions that contributed to the outcome.
     fn parse_plugin_decision_event(event: &Event, ecs_event: &mut EcsEvent) -> fmt::Result {
+        let shadow = EcsFormatter::is_shadow_event(event);
         let mut plugin_field_set = EcsBulwarkDecisionFieldSet::default();
         let mut reference_name: Option<String> = None;
 
@@ -251,6 +269,12 @@ impl EcsFormatter {
                     plugin_field_set.score =
                         str::parse::<f64>(field.value()).map_err(|_| fmt::Error)?;
                 }
+                "failure" => {
+                    let unquoted_failure =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    plugin_field_set.failure = Some(unquoted_failure.to_string());
+                }
                 _ => {}
             }
         }
@@ -264,12 +288,18 @@ impl EcsFormatter {
         // the final decision.
         if let Some(reference_name) = reference_name {
             let mut bulwark = ecs_event.bulwark.clone().unwrap_or_default();
-            let mut plugins = bulwark.plugins.clone().unwrap_or_default();
-            plugins.insert(
-                reference_name,
-                serde_json::to_value(plugin_field_set).map_err(|_| fmt::Error)?,
-            );
-            bulwark.plugins = Some(plugins);
+            let plugin_value = serde_json::to_value(plugin_field_set).map_err(|_| fmt::Error)?;
+            if shadow {
+                let mut shadow_field_set = bulwark.shadow.clone().unwrap_or_default();
+                let mut plugins = shadow_field_set.plugins.clone().unwrap_or_default();
+                plugins.insert(reference_name, plugin_value);
+                shadow_field_set.plugins = Some(plugins);
+                bulwark.shadow = Some(shadow_field_set);
+            } else {
+                let mut plugins = bulwark.plugins.clone().unwrap_or_default();
+                plugins.insert(reference_name, plugin_value);
+                bulwark.plugins = Some(plugins);
+            }
             ecs_event.bulwark = Some(bulwark);
         } else {
             return Err(fmt::Error);
@@ -284,6 +314,11 @@ impl EcsFormatter {
     /// The decision outcome based on the configured decision thresholds and the combined set of all tags emitted
     /// by the plugins is also parsed from these messages.
     fn parse_combine_decision_event(event: &Event, ecs_event: &mut EcsEvent) -> fmt::Result {
+        // Shadow decisions must never be reported as the outcome
+        if EcsFormatter::is_shadow_event(event) {
+            return EcsFormatter::parse_shadow_combine_decision_event(event, ecs_event);
+        }
+
         // If the service is in observe-only mode, don't report events as denied
         // Fields aren't sorted, scanning because we can't binary search
         let observe_only = event
@@ -346,6 +381,163 @@ impl EcsFormatter {
         Ok(())
     }
 
+    /// Parses `"combine decision"` messages emitted for a resource's shadow plugins.
+    ///
+    /// Shadow decisions are recorded in their own field set so that they can be compared against the
+    /// enforced decision without affecting the `risk`, `event`, or `tags` fields.
+    fn parse_shadow_combine_decision_event(event: &Event, ecs_event: &mut EcsEvent) -> fmt::Result {
+        let mut bulwark = ecs_event.bulwark.clone().unwrap_or_default();
+        let mut shadow_field_set = bulwark.shadow.clone().unwrap_or_default();
+
+        for field in event.fields().iter() {
+            match field.key() {
+                "accept" => {
+                    shadow_field_set.accept =
+                        str::parse::<f64>(field.value()).map_err(|_| fmt::Error)?;
+                }
+                "restrict" => {
+                    shadow_field_set.restrict =
+                        str::parse::<f64>(field.value()).map_err(|_| fmt::Error)?;
+                }
+                "unknown" => {
+                    shadow_field_set.unknown =
+                        str::parse::<f64>(field.value()).map_err(|_| fmt::Error)?;
+                }
+                "score" => {
+                    shadow_field_set.score =
+                        str::parse::<f64>(field.value()).map_err(|_| fmt::Error)?;
+                }
+                "outcome" => {
+                    let unquoted_outcome =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    shadow_field_set.outcome = unquoted_outcome.to_ascii_lowercase();
+                }
+                "tags" => {
+                    let unquoted_tags = quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                        .map_err(|_| fmt::Error)?;
+                    let tags: Vec<String> = unquoted_tags
+                        .to_string()
+                        .split(',')
+                        .map(|s| s.trim().to_ascii_lowercase())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                    shadow_field_set.tags = if tags.is_empty() { None } else { Some(tags) };
+                }
+                _ => {}
+            }
+        }
+
+        bulwark.shadow = Some(shadow_field_set);
+        ecs_event.bulwark = Some(bulwark);
+
+        Ok(())
+    }
+
+    /// Parses `"plugin output"` messages emitted for each line a plugin writes to stdout or stderr, as well as the
+    /// `"plugin output suppressed"` messages emitted when a plugin writes lines faster than they can be logged.
+    ///
+    /// The line written by the plugin becomes the message, while the plugin reference and stream become labels.
+    fn parse_plugin_output_event(event: &Event, ecs_event: &mut EcsEvent) -> fmt::Result {
+        let mut labels = ecs_event.labels.clone().unwrap_or_default();
+        for field in event.fields().iter() {
+            match field.key() {
+                "plugin" | "stream" | "lines" => {
+                    let unquoted_value = quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                        .map(|value| value.to_string())
+                        .unwrap_or_else(|_| field.value().to_string());
+                    labels.insert(field.key().to_string(), Value::String(unquoted_value));
+                }
+                "request_id" => {
+                    let unquoted_request_id =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    let mut event_meta = ecs_event.event.clone().unwrap_or_default();
+                    event_meta.id = Some(unquoted_request_id.to_string());
+                    ecs_event.event = Some(event_meta);
+                    ecs_event.trace = Some(EcsTraceFieldSet {
+                        id: unquoted_request_id.to_string(),
+                    });
+                }
+                "line" => {
+                    // Lines are recorded with their Display form, so they are never quoted.
+                    ecs_event.message = field.value().to_string();
+                }
+                _ => {}
+            }
+        }
+        ecs_event.labels = Some(labels);
+
+        Ok(())
+    }
+
+    /// Parses `"plugin log"` messages emitted when a plugin logs an event through the host's logging API.
+    ///
+    /// The plugin's message becomes the message, while the plugin reference and the plugin's own fields become
+    /// labels.
+    fn parse_plugin_log_event(event: &Event, ecs_event: &mut EcsEvent) -> fmt::Result {
+        let mut labels = ecs_event.labels.clone().unwrap_or_default();
+        for field in event.fields().iter() {
+            match field.key() {
+                "plugin" => {
+                    let unquoted_reference =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    labels.insert(
+                        "plugin".to_string(),
+                        Value::String(unquoted_reference.to_string()),
+                    );
+                }
+                "request_id" => {
+                    let unquoted_request_id =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    let mut event_meta = ecs_event.event.clone().unwrap_or_default();
+                    event_meta.id = Some(unquoted_request_id.to_string());
+                    ecs_event.event = Some(event_meta);
+                    ecs_event.trace = Some(EcsTraceFieldSet {
+                        id: unquoted_request_id.to_string(),
+                    });
+                }
+                "log_message" => {
+                    let unquoted_message =
+                        quoted_string::to_content::<TraceQuoteSpec>(field.value())
+                            .map_err(|_| fmt::Error)?;
+                    ecs_event.message = unquoted_message.to_string();
+                }
+                "fields" => {
+                    // Plugin fields are logged as a JSON object of string values. Fields can't override the labels
+                    // Bulwark sets itself.
+                    let fields: Map<String, Value> =
+                        serde_json::from_str(field.value()).map_err(|_| fmt::Error)?;
+                    for (name, value) in fields {
+                        labels.entry(name).or_insert(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        ecs_event.labels = Some(labels);
+
+        Ok(())
+    }
+
+    /// Returns true if the event was emitted for a shadow plugin or a shadow plugin set.
+    fn is_shadow_event(event: &Event) -> bool {
+        // Fields aren't sorted, scanning because we can't binary search
+        event
+            .fields()
+            .iter()
+            .find_map(|f| {
+                if f.key() == "shadow" {
+                    Some(f.value() == "true")
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
     /// Parses unrecognized messages on a "best effort" basis. Not currently implemented.
     fn parse_unknown_event(event: &Event, _ecs_event: &mut EcsEvent) -> fmt::Result {
         for _field in event.fields().iter() {
@@ -468,6 +660,9 @@ pub(crate) struct EcsEvent {
     /// File fields provide details about the affected file associated with the event or metric.
     #[serde(skip_serializing_if = "Option::is_none")]
     file: Option<EcsFileFieldSet>,
+    /// Distributed tracing fields that correlate the event with other services handling the same request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<EcsTraceFieldSet>,
     /// The custom field values namespaced for Bulwark.
     #[serde(skip_serializing_if = "Option::is_none")]
     bulwark: Option<EcsBulwarkFieldSet>,
@@ -479,6 +674,9 @@ fn default_timestamp_now() -> DateTime<Utc> {
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub(crate) struct EcsEventFieldSet {
+    /// Unique ID to describe the event, which Bulwark takes from the request ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     /// Kind represents high-level information about what type of information the event contains.
     /// It is the highest-level event categorization in ECS.
     ///
@@ -625,6 +823,16 @@ pub(crate) struct EcsRiskFieldSet {
     calculated_risk_score_norm: Option<f64>,
 }
 
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub(crate) struct EcsTraceFieldSet {
+    /// Unique identifier of the trace, which Bulwark takes from the request ID.
+    ///
+    /// # Example
+    ///
+    /// `4bf92f3577b34da6a3ce929d0e0e4736`
+    id: String,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub(crate) struct EcsFileFieldSet {
     /// The full path to the file.
@@ -649,6 +857,9 @@ pub(crate) struct EcsBulwarkFieldSet {
     /// The decision components that contributed to the outcome.
     #[serde(skip_serializing_if = "Option::is_none")]
     plugins: Option<serde_json::Map<String, serde_json::Value>>,
+    /// The decision made by the shadow plugins, which did not contribute to the outcome.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shadow: Option<EcsBulwarkShadowFieldSet>,
 }
 
 impl std::fmt::Debug for EcsBulwarkFieldSet {
@@ -658,10 +869,31 @@ impl std::fmt::Debug for EcsBulwarkFieldSet {
             .field("restrict", &self.restrict)
             .field("unknown", &self.unknown)
             .field("plugins", &self.plugins)
+            .field("shadow", &self.shadow)
             .finish()
     }
 }
 
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub(crate) struct EcsBulwarkShadowFieldSet {
+    /// The combined shadow decision accept value.
+    accept: f64,
+    /// The combined shadow decision restrict value.
+    restrict: f64,
+    /// The combined shadow decision unknown value.
+    unknown: f64,
+    /// The combined shadow decision risk score.
+    score: f64,
+    /// The outcome the shadow decision would have had if it had been enforced.
+    outcome: String,
+    /// The combined set of tags emitted by the shadow plugins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+    /// The shadow decision components that contributed to the shadow outcome.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plugins: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub(crate) struct EcsBulwarkDecisionFieldSet {
     /// The plugin decision accept value.
@@ -672,6 +904,9 @@ pub(crate) struct EcsBulwarkDecisionFieldSet {
     unknown: f64,
     /// The plugin decision risk score.
     score: f64,
+    /// The kind of failure, if the plugin failed and its failure policy supplied the decision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
 }
 
 #[cfg(test)]
@@ -4,11 +4,21 @@ mod ecs;
 mod errors;
 
 use {
-    axum::{extract::Path, extract::State, http::StatusCode, response::Json, routing::get, Router},
-    bulwark_ext_processor::BulwarkProcessor,
+    axum::{
+        extract::Path,
+        extract::State,
+        http::{header, StatusCode},
+        response::Json,
+        routing::get,
+        Router,
+    },
+    bulwark_ext_processor::{open_state_backend, BulwarkProcessor, MetricsRegistry, StateBackend},
     clap::{Parser, Subcommand},
     color_eyre::eyre::Result,
-    envoy_control_plane::envoy::service::ext_proc::v3::external_processor_server::ExternalProcessorServer,
+    envoy_control_plane::envoy::service::{
+        auth::v3::authorization_server::AuthorizationServer,
+        ext_proc::v3::external_processor_server::ExternalProcessorServer,
+    },
     errors::*,
     serde::Serialize,
     std::net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -74,6 +84,20 @@ struct HealthState {
     /// entered a deadlock state, however this is not currently implemented and does not meaningfully
     /// differ from the started state.
     ready: bool,
+    /// The state backend shared by plugins, whose availability is reported alongside the health of the
+    /// primary service.
+    state_backend: Arc<dyn StateBackend>,
+}
+
+/// The metrics state structure holds everything the metrics endpoint exports.
+#[derive(Clone)]
+struct MetricsState {
+    /// The registry that custom metrics recorded by plugins are stored in.
+    registry: Arc<MetricsRegistry>,
+    /// The state backend shared by plugins, whose availability is exported alongside plugin metrics.
+    state_backend: Arc<dyn StateBackend>,
+    /// The primary service, whose load is exported once it has been created.
+    processor: Arc<Mutex<Option<Arc<BulwarkProcessor>>>>,
 }
 
 /// The health response structure determines the JSON serialization for health probe responses. Regardless
@@ -97,6 +121,10 @@ struct HealthResponse {
     /// ready field has identical behavior to the started field since no detection for things like
     /// deadlock states exist yet.
     pub ready: bool,
+    /// The state_available field indicates whether the state backend could be reached by the most recent
+    /// state operation. Plugins keep running while the state backend is unavailable, so this field does not
+    /// affect the status code of any probe.
+    pub state_available: bool,
 }
 
 /// The default probe handler is intended to be at the apex of the health check resource. It simply performs
@@ -109,6 +137,40 @@ async fn default_probe_handler(
     probe_handler(State(state), Path(String::from("live"))).await
 }
 
+/// The metrics handler exports the custom metrics recorded by plugins in the Prometheus text format, along with
+/// the availability of the state backend and the load and decision outcomes of the primary service.
+async fn metrics_handler(
+    State(metrics): State<MetricsState>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    let mut body = metrics.registry.render();
+    body.push_str(&format!(
+        "# TYPE bulwark_state_available gauge\nbulwark_state_available {}\n",
+        u8::from(metrics.state_backend.is_available())
+    ));
+    body.push_str(&format!(
+        "# TYPE bulwark_state_failures_total counter\nbulwark_state_failures_total {}\n",
+        metrics.state_backend.failures()
+    ));
+    // The primary service doesn't exist until every plugin has been compiled.
+    if let Some(processor) = metrics.processor.lock().unwrap().as_ref() {
+        let load = processor.load();
+        body.push_str(&format!(
+            "# TYPE bulwark_requests_in_flight gauge\nbulwark_requests_in_flight {}\n",
+            load.requests
+        ));
+        body.push_str(&format!(
+            "# TYPE bulwark_plugins_in_flight gauge\nbulwark_plugins_in_flight {}\n",
+            load.plugin_instances
+        ));
+        body.push_str(&format!(
+            "# TYPE bulwark_shed_total counter\nbulwark_shed_total {}\n",
+            load.shed
+        ));
+        body.push_str(&processor.render_outcomes());
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
 /// The probe handler returns a JSON HealthResponse with a status code that depends on the probe type requested.
 ///
 /// - live - Always returns an HTTP OK status if the endpoint is serving requests.
@@ -146,6 +208,7 @@ async fn probe_handler(
             live: true,
             started: state.started,
             ready: state.ready,
+            state_available: state.state_backend.is_available(),
         }),
     )
 }
@@ -230,15 +293,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let port = config_root.service.port;
             let admin_port = config_root.service.admin_port;
             let admin_enabled = config_root.service.admin_enabled;
+            // The state backend is opened up-front so that its availability can be reported by the admin service.
+            let state_backend = open_state_backend(
+                config_root.service.remote_state.as_deref(),
+                &config_root.state,
+            )?;
             let health_state = Arc::new(Mutex::new(HealthState {
                 started: false,
                 ready: false,
+                state_backend: state_backend.clone(),
             }));
 
-            // TODO: need a reference to the bulwark processor to pass to the admin service but that doesn't exist yet
+            // Plugin metrics are recorded by the processor and exported by the admin service.
+            let metrics = Arc::new(MetricsRegistry::default());
+            // The admin service starts before the processor exists, so it's handed over once it has been created.
+            let processor = Arc::new(Mutex::new(None));
 
             if admin_enabled {
                 let health_state = health_state.clone();
+                let metrics = MetricsState {
+                    registry: metrics.clone(),
+                    state_backend: state_backend.clone(),
+                    processor: processor.clone(),
+                };
 
                 // TODO: make admin service optional
                 service_tasks.spawn(async move {
@@ -248,7 +325,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         Router::new()
                             .route("/health", get(default_probe_handler)) // :probe is optional and defaults to liveness probe
                             .route("/health/:probe", get(probe_handler))
-                            .with_state(health_state),
+                            .with_state(health_state)
+                            .merge(
+                                Router::new()
+                                    .route("/metrics", get(metrics_handler))
+                                    .with_state(metrics),
+                            ),
                     );
 
                     axum::Server::bind(&addr)
@@ -258,8 +340,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 });
             }
 
-            let bulwark_processor = BulwarkProcessor::new(config_root)?;
-            let ext_processor = ExternalProcessorServer::new(bulwark_processor);
+            let bulwark_processor =
+                Arc::new(BulwarkProcessor::new(config_root, metrics, state_backend)?);
+            *processor.lock().unwrap() = Some(bulwark_processor.clone());
+            let ext_processor = ExternalProcessorServer::from_arc(bulwark_processor.clone());
+            let ext_authz = AuthorizationServer::from_arc(bulwark_processor);
 
             {
                 let health_state = health_state.clone();
@@ -272,6 +357,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                     Server::builder()
                         .add_service(ext_processor)
+                        .add_service(ext_authz)
                         .serve(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)) // TODO: make socket addr configurable?
                         .await
                         .map_err(ServiceError::ExtProcessorService)